@@ -0,0 +1,197 @@
+// what `hugorm` makes of its command line, main.rs does the actual work
+
+pub const USAGE: &str = "\
+usage:
+    hugorm [--no-color] [<file> | -e <code> | -] [args...]
+    hugorm run [--no-color] <file | -e <code> | -> [args...]
+    hugorm run [--no-color] [-- args...]
+    hugorm repl [--no-color] [file]
+    hugorm check [--no-color] [paths...]
+    hugorm fmt [--no-color] [--check] [paths...]
+    hugorm test [--no-color] [paths...]
+    hugorm lsp
+
+    -e <code>     run <code> instead of a file
+    -             read the program from stdin
+    -i <file>     run <file>, then start the REPL
+    --no-color    plain output, no escape codes
+    -h, --help    print this
+    --version     print the version
+
+everything after the program ends up in the `args` list of the script. `hugorm run`
+without a program runs the entry of the closest `hugorm.toml`, here or in a folder
+above, with everything after `--` as its `args`
+
+exits with 1 when the program doesn't compile, 3 when it falls over while
+running, or whatever it gave `exit`. `check`, `fmt` and `test` exit with 1
+when they aren't happy, bad arguments get a 2
+";
+
+// what `hugorm` exits with when things go wrong
+pub const COMPILE_ERROR: i32 = 1;
+pub const USAGE_ERROR: i32 = 2;
+pub const RUNTIME_ERROR: i32 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Script {
+    File(String),
+    Code(String), // -e
+    Stdin,        // -
+}
+
+impl Script {
+    // the name diagnostics point at
+    pub fn name(&self) -> String {
+        match *self {
+            Script::File(ref path) => path.clone(),
+            Script::Code(_) => "<-e>".to_string(),
+            Script::Stdin => "<stdin>".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Run(Script, Vec<String>),
+    Project(Vec<String>), // `hugorm run` with nothing to run, the args after its `--`
+    Repl(Option<String>),
+    Check(Vec<String>),
+    Fmt(bool, Vec<String>),
+    Test(Vec<String>),
+    Lsp,
+    Help,
+    Version,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub command: Command,
+    pub color: bool,
+}
+
+// `args` is without the program name, `Err` is what was wrong with them
+pub fn parse(args: &[String]) -> Result<Options, String> {
+    let mut color = true;
+    let mut rest = args;
+
+    while let Some(arg) = rest.first() {
+        match arg.as_str() {
+            "--no-color" => color = false,
+            "-h" | "--help" => return Ok(Options { command: Command::Help, color }),
+            "--version" => return Ok(Options { command: Command::Version, color }),
+            _ => break,
+        }
+
+        rest = &rest[1..]
+    }
+
+    let command = match rest.first().map(|arg| arg.as_str()) {
+        None => Command::Repl(None),
+
+        Some("run") => match run(&rest[1..], &mut color, true)? {
+            Some(command) => command,
+            None => Command::Project(Vec::new()),
+        },
+
+        Some("repl") => {
+            let mut paths = paths(&rest[1..], &mut color, &[])?.1;
+
+            if paths.len() > 1 {
+                return Err("`hugorm repl` takes at most one file".to_string())
+            }
+
+            Command::Repl(paths.pop())
+        },
+
+        Some("-i") => match rest.get(1) {
+            Some(file) if rest.len() == 2 => Command::Repl(Some(file.clone())),
+            _ => return Err("`-i` takes exactly one file".to_string()),
+        },
+
+        Some("check") => Command::Check(paths(&rest[1..], &mut color, &[])?.1),
+        Some("test") => Command::Test(paths(&rest[1..], &mut color, &[])?.1),
+
+        Some("fmt") => {
+            let (flags, paths) = paths(&rest[1..], &mut color, &["--check"])?;
+
+            Command::Fmt(!flags.is_empty(), paths)
+        },
+
+        Some("lsp") if rest.len() == 1 => Command::Lsp,
+        Some("lsp") => return Err("`hugorm lsp` doesn't take any arguments".to_string()),
+
+        Some(_) => match run(rest, &mut color, false)? {
+            Some(command) => command,
+            None => Command::Repl(None),
+        },
+    };
+
+    Ok(Options { command, color })
+}
+
+// the flags of `run` come before the program, anything after it belongs to the script.
+// With `project`, a `--` instead of a program is where the project's args start
+fn run(args: &[String], color: &mut bool, project: bool) -> Result<Option<Command>, String> {
+    let mut rest = args;
+
+    while let Some(arg) = rest.first() {
+        let script = match arg.as_str() {
+            "--no-color" => {
+                *color = false;
+                rest = &rest[1..];
+
+                continue
+            },
+
+            "-e" => match rest.get(1) {
+                Some(code) => {
+                    rest = &rest[1..];
+                    Script::Code(code.clone())
+                },
+
+                None => return Err("`-e` needs some code to run".to_string()),
+            },
+
+            "-" => Script::Stdin,
+
+            "--" if project => return Ok(Some(Command::Project(rest[1..].to_vec()))),
+
+            "--" => match rest.get(1) {
+                Some(path) => {
+                    rest = &rest[1..];
+                    Script::File(path.clone())
+                },
+
+                None => return Ok(None),
+            },
+
+            flag if flag.starts_with('-') => return Err(format!("no flag called `{}`", flag)),
+
+            path => Script::File(path.to_string()),
+        };
+
+        return Ok(Some(Command::Run(script, rest[1..].to_vec())))
+    }
+
+    Ok(None)
+}
+
+// splits `known` flags from paths, `--no-color` can go anywhere
+fn paths(args: &[String], color: &mut bool, known: &[&str]) -> Result<(Vec<String>, Vec<String>), String> {
+    let mut flags = Vec::new();
+    let mut paths = Vec::new();
+
+    for arg in args.iter() {
+        if arg == "--no-color" {
+            *color = false
+        } else if known.contains(&arg.as_str()) {
+            flags.push(arg.clone())
+        } else if arg.starts_with('-') && arg != "-" {
+            return Err(format!("no flag called `{}`", arg))
+        } else {
+            paths.push(arg.clone())
+        }
+    }
+
+    Ok((flags, paths))
+}
@@ -0,0 +1,367 @@
+use std::any::Any;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::mem;
+use std::path::PathBuf;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+use zub::ir::ExprNode;
+use zub::vm::*;
+
+use super::error::{self, Diagnostic, Severity};
+use super::lexer::*;
+use super::manifest::Manifest;
+use super::parser::*;
+use super::prelude::{base, iter, num, string};
+use super::runtime::{self, RuntimeError};
+use super::source::*;
+use super::visitor::*;
+
+// a Rust function callable from Hugorm, `args[0]` being the function itself
+pub type Native = fn(&mut Heap<Object>, &[Value]) -> Result<Value, RuntimeError>;
+
+// `Err` is everything reported by a run that failed
+pub type EvalResult = Result<Ran, Vec<Diagnostic>>;
+
+// how a run that didn't fail ended
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ran {
+    pub warnings: Vec<Diagnostic>,
+    pub exit: Option<i32>, // what `exit` was called with, when the program stopped itself
+}
+
+// owns everything needed to run Hugorm code, keeping globals around between runs
+pub struct Engine {
+    pub(crate) vm: VM,
+    pub(crate) symtab: SymTab,
+    pub(crate) spans: Vec<(String, Pos)>, // what the lines of the VM's code point at, by index
+    natives: HashMap<String, Native>, // what the VM's natives really are, by name
+    pub(crate) modules: Modules, // what's been grabbed, so it only runs once
+    root: String, // where to look for modules
+    manifests: HashMap<PathBuf, Result<Manifest, Diagnostic>>, // read once, not on every grab
+    search: Vec<PathBuf>, // where else to look for modules, `HUGORM_PATH` to begin with
+    before: Option<(SymTab, Modules)>, // what the last compile changed, in case its run fails
+    output: Option<Box<dyn Write>>, // where `print` writes, `None` being stdout
+    input: Option<Box<dyn BufRead>>, // where `input` reads, `None` being stdin
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        let mut engine = Engine {
+            vm: VM::new(),
+            symtab: SymTab::new(),
+            root: String::new(),
+            spans: Vec::new(),
+            natives: HashMap::new(),
+            modules: HashMap::new(),
+            manifests: HashMap::new(),
+            search: search_path(),
+            before: None,
+            output: None,
+            input: None,
+        };
+
+        base::include_base(&mut engine);
+        string::include_string(&mut engine);
+        num::include_num(&mut engine);
+        iter::include_iter(&mut engine);
+
+        engine.set_args(&[]);
+
+        engine
+    }
+
+    // a runtime error is an `Err` too and `exit` ends up in `Ran::exit`, anything
+    // else the run panics with (like a failed assertion) is passed on
+    pub fn eval_str(&mut self, path: &str, content: &str) -> EvalResult {
+        let (ir, mut warnings) = self.compile_str(path, content)?;
+
+        if let Err(payload) = self.exec(&ir) {
+            if let Some(&base::Exit(code)) = payload.downcast_ref::<base::Exit>() {
+                return Ok(Ran { warnings, exit: Some(code) })
+            }
+
+            match payload.downcast::<RuntimeError>() {
+                Ok(error) => {
+                    warnings.push(error.diagnostic(path));
+
+                    return Err(warnings)
+                },
+
+                Err(payload) => panic::resume_unwind(payload),
+            }
+        }
+
+        Ok(Ran { warnings, exit: None })
+    }
+
+    // lex, parse and visit without running anything
+    pub fn compile_str(&mut self, path: &str, content: &str) -> Result<(Vec<ExprNode>, Vec<Diagnostic>), Vec<Diagnostic>> {
+        let source = Source::from(path, content.lines().map(|x| x.into()).collect::<Vec<String>>());
+        let (ast, comments) = parse_str(&source, content)?;
+
+        self.compile_ast(&source, &ast, &comments)
+    }
+
+    // the comments are only there for lint pragmas
+    pub fn compile_ast(&mut self, source: &Source, ast: &[Statement], comments: &[Token]) -> Result<(Vec<ExprNode>, Vec<Diagnostic>), Vec<Diagnostic>> {
+        // a failed run shouldn't leave half its declarations behind
+        let backup = self.symtab.clone();
+        let modules = self.modules.clone();
+
+        let mut visitor = Visitor::from(source, self.symtab.clone(), self.root.clone());
+
+        visitor.spans = mem::take(&mut self.spans);
+        visitor.modules = self.modules.clone();
+        visitor.manifests = mem::take(&mut self.manifests);
+        visitor.search = self.search.clone();
+
+        let visited = ast.iter().try_for_each(|statement| visitor.visit_statement(statement));
+
+        self.spans = mem::take(&mut visitor.spans);
+        self.manifests = mem::take(&mut visitor.manifests);
+
+        if visited.is_err() {
+            self.symtab = backup;
+
+            return Err(error::drain())
+        }
+
+        self.modules = mem::take(&mut visitor.modules);
+
+        let ir = visitor.build();
+
+        self.symtab = visitor.symtab;
+        self.before = Some((backup, modules));
+
+        lint::lint(ast, comments, source);
+
+        Ok((ir, error::drain()))
+    }
+
+    // a runtime error comes back as a `RuntimeError` with its trace, anything
+    // else the run panicked with as it was
+    pub fn exec(&mut self, ir: &[ExprNode]) -> Result<(), Box<dyn Any + Send>> {
+        self.exec_debug(ir, false)
+    }
+
+    // with `debug` on, the VM disassembles what it runs
+    pub(crate) fn exec_debug(&mut self, ir: &[ExprNode], debug: bool) -> Result<(), Box<dyn Any + Send>> {
+        let vm = &mut self.vm;
+        let spans = &self.spans;
+        let natives = &mut self.natives;
+
+        let result = base::lend_io(&mut self.output, &mut self.input, || {
+            runtime::traced(vm, spans, natives, |vm| vm.exec(ir, debug))
+        });
+
+        if let Err(ref payload) = result {
+            self.recover(&**payload)
+        }
+
+        result
+    }
+
+    // whatever a run that fell over left behind would otherwise be picked up
+    // again by the next one. That includes what it declared and grabbed, or a
+    // module it never got to would count as run. An `exit` did finish, though
+    pub fn recover(&mut self, payload: &(dyn Any + Send)) {
+        self.vm.stack.clear();
+        self.vm.frames.clear();
+        self.vm.open_upvalues.clear();
+
+        if let Some((symtab, modules)) = self.before.take() {
+            if !payload.is::<base::Exit>() {
+                self.symtab = symtab;
+                self.modules = modules;
+            }
+        }
+    }
+
+    pub fn eval_file(&mut self, path: &str) -> EvalResult {
+        match fs::read_to_string(path) {
+            Ok(content) => self.eval_str(path, &content),
+            Err(why) => {
+                let mut diagnostic = Diagnostic::new(Severity::Error, format!("failed to read: {}", why));
+                diagnostic.file = Some(path.to_string());
+
+                Err(vec!(diagnostic))
+            }
+        }
+    }
+
+    // makes a Rust function callable from Hugorm
+    pub fn register_native(&mut self, name: &str, arity: u8, function: Native) {
+        self.symtab.assign_str(name, Type::from(TypeNode::Func(arity as usize)));
+        self.add_native(name, arity, function)
+    }
+
+    // a native the checker doesn't know about, for the ones programs can't see
+    // or that get hidden arguments
+    pub(crate) fn add_native(&mut self, name: &str, arity: u8, function: Native) {
+        self.natives.insert(name.to_string(), function);
+        self.vm.add_native(name, runtime::dispatch, arity)
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.vm.globals.get(name).cloned()
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.symtab.assign_str(name, Type::from(TypeNode::Any));
+        self.vm.globals.insert(name.to_string(), value);
+    }
+
+    // where `print` and friends write from now on, `None` being stdout. Hands back
+    // whatever was written to before
+    pub fn set_output(&mut self, output: Option<Box<dyn Write>>) -> Option<Box<dyn Write>> {
+        mem::replace(&mut self.output, output)
+    }
+
+    // where `input` and `prompt` read from now on, `None` being stdin
+    pub fn set_input(&mut self, input: Option<Box<dyn BufRead>>) -> Option<Box<dyn BufRead>> {
+        mem::replace(&mut self.input, input)
+    }
+
+    // writes where the program's own output goes
+    pub fn write_output(&mut self, text: &str) {
+        base::lend_io(&mut self.output, &mut self.input, || base::write_output(text))
+    }
+
+    // the folder modules are looked for in after the one of the file grabbing them
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    pub fn set_root(&mut self, root: &str) {
+        self.root = root.to_string()
+    }
+
+    // the folders modules are looked for in after the project, `HUGORM_PATH` to begin with
+    pub fn search(&self) -> &[PathBuf] {
+        &self.search
+    }
+
+    pub fn set_search(&mut self, search: Vec<PathBuf>) {
+        self.search = search
+    }
+
+    // where the values of globals live, for printing them
+    pub fn heap(&self) -> &Heap<Object> {
+        &self.vm.heap
+    }
+
+    // what a script gets as `args`, the command line after the program
+    pub fn set_args(&mut self, args: &[String]) {
+        let content = args.iter()
+            .map(|arg| Value::object(self.vm.heap.insert_temp(Object::String(arg.clone()))))
+            .collect();
+
+        let list = self.vm.heap.insert_temp(Object::List(List::new(content)));
+
+        self.set_global("args", Value::object(list))
+    }
+}
+
+// lexes and parses a whole file, handing back the comments along with the AST
+pub fn parse_str(source: &Source, content: &str) -> Result<(Vec<Statement>, Vec<Token>), Vec<Diagnostic>> {
+    let mut lexer = Lexer::default(content.chars().collect(), source);
+
+    let mut tokens = Vec::new();
+    let mut lexed = true;
+
+    for token_res in lexer.by_ref() {
+        match token_res {
+            Ok(token) => tokens.push(token),
+            Err(_) => lexed = false,
+        }
+    }
+
+    let lexer_errors = error::drain();
+
+    match Parser::new(tokens, source).parse() {
+        Ok(ast) if lexed => Ok((ast, lexer.comments)),
+        _ => {
+            // the lexer skips the rest of a broken line, so whatever the parser
+            // has to say about that line is just noise
+            let broken = lexer_errors.iter()
+                .filter_map(|d| d.span.as_ref().map(|span| (span.0).0))
+                .collect::<Vec<usize>>();
+
+            let mut diagnostics = lexer_errors;
+
+            diagnostics.extend(error::drain().into_iter().filter(|d| {
+                d.span.as_ref().is_none_or(|span| !broken.contains(&(span.0).0))
+            }));
+
+            // top to bottom, whichever of the two found them
+            diagnostics.sort_by_key(|d| d.span.as_ref().map(|span| (span.0).0));
+
+            Err(diagnostics)
+        }
+    }
+}
+
+// the folders in `HUGORM_PATH`, separated like `PATH`
+pub fn search_path() -> Vec<PathBuf> {
+    match std::env::var_os("HUGORM_PATH") {
+        Some(paths) => std::env::split_paths(&paths).filter(|dir| !dir.as_os_str().is_empty()).collect(),
+        None => Vec::new(),
+    }
+}
+
+// what a panicking run had to say for itself
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(base::Exit(code)) = payload.downcast_ref::<base::Exit>() {
+        return format!("exited with {}", code)
+    }
+
+    if let Some(error) = payload.downcast_ref::<RuntimeError>() {
+        return error.message.clone()
+    }
+
+    payload.downcast_ref::<String>().cloned()
+        .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+        .unwrap_or_else(|| "something went wrong".to_string())
+}
+
+thread_local! {
+    // how many `quietly`s this thread is in
+    static QUIET: Cell<usize> = const { Cell::new(0) };
+}
+
+static HOOK: Once = Once::new();
+
+// catches a panicking run. What it panics with is reported as a diagnostic, so
+// the panic hook leaves out the VM's errors and runtime errors while it's going.
+// Anything else still gets shouted about, it's a bug
+pub fn quietly<T, F: FnOnce() -> T>(f: F) -> Result<T, Box<dyn Any + Send>> {
+    HOOK.call_once(|| {
+        let hook = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            let expected = info.payload().is::<RuntimeError>() || info.payload().is::<VmError>();
+
+            if !expected || QUIET.with(Cell::get) == 0 {
+                hook(info)
+            }
+        }))
+    });
+
+    QUIET.with(|quiet| quiet.set(quiet.get() + 1));
+
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+
+    QUIET.with(|quiet| quiet.set(quiet.get() - 1));
+
+    result
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
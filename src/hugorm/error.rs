@@ -1,37 +1,253 @@
 use colored::Colorize;
+use std::cell::RefCell;
 use std::fmt;
 
+use super::lexer::Pos;
+use super::source::FilePath;
+
 pub enum Response<T: fmt::Display> {
     Wrong(T),
     Weird(T),
     Note(T),
+    Help(T),
 }
 
 use self::Response::*;
 
+// builds a diagnostic out of responses, files and positions (in that order),
+// drops it in the sink and hands it back
 #[macro_export]
 macro_rules! response {
   ( $( $r:expr ),+ ) => {{
+    let mut parts = $crate::hugorm::error::Parts::default();
     $(
-        print!("{}", $r);
+        $crate::hugorm::error::Part::feed(&$r, &mut parts);
     )*
-    println!();
+    parts.emit()
   }};
 }
 
-impl<T: fmt::Display> fmt::Display for Response<T> {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &str {
+        match *self {
+            Severity::Error => "error",
+            Severity::Warning => "hmmm",
+            Severity::Note => "note",
+        }
+    }
+
+    fn color(&self) -> &str {
+        match *self {
+            Severity::Error => "red",
+            Severity::Warning => "yellow",
+            Severity::Note => "cyan",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub file: Option<String>,
+    pub span: Option<Pos>,
+    pub notes: Vec<Diagnostic>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: String) -> Self {
+        Diagnostic {
+            severity,
+            message,
+            file: None,
+            span: None,
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let (color, message_type, message) = match *self {
-            Wrong(ref m) => ("red", "error", m),
-            Weird(ref m) => ("yellow", "hmmm", m),
-            Note(ref m) => ("cyan", "note", m),
+        write!(f, "{}", Render::Color.render(self))
+    }
+}
+
+// what `response!` collects its arguments into
+#[derive(Default)]
+pub struct Parts {
+    main: Option<Diagnostic>,
+}
+
+impl Parts {
+    fn current(&mut self) -> &mut Diagnostic {
+        let main = self.main.get_or_insert_with(|| Diagnostic::new(Severity::Error, String::new()));
+
+        if main.notes.is_empty() {
+            main
+        } else {
+            main.notes.last_mut().unwrap()
+        }
+    }
+
+    pub fn emit(self) -> Diagnostic {
+        let diagnostic = self.main.expect("`response!` without a response");
+
+        emit(diagnostic.clone());
+
+        diagnostic
+    }
+}
+
+pub trait Part {
+    fn feed(&self, parts: &mut Parts);
+}
+
+impl<T: fmt::Display> Part for Response<T> {
+    fn feed(&self, parts: &mut Parts) {
+        let (severity, message) = match *self {
+            Wrong(ref m) => (Severity::Error, m.to_string()),
+            Weird(ref m) => (Severity::Warning, m.to_string()),
+            Note(ref m) => (Severity::Note, m.to_string()),
+            Help(ref m) => {
+                parts.main.as_mut().expect("help before response").help = Some(m.to_string());
+                return
+            }
         };
 
-        let message_type = format!("\n{}", message_type).color(color).bold();
-        let message = format!("{}", message);
+        let diagnostic = Diagnostic::new(severity, message);
+
+        match parts.main {
+            Some(ref mut main) => main.notes.push(diagnostic),
+            None => parts.main = Some(diagnostic),
+        }
+    }
+}
+
+impl Part for FilePath {
+    fn feed(&self, parts: &mut Parts) {
+        parts.current().file = Some(self.0.clone())
+    }
+}
+
+impl Part for Pos {
+    fn feed(&self, parts: &mut Parts) {
+        parts.current().span = Some(self.clone())
+    }
+}
+
+impl<P: Part> Part for &P {
+    fn feed(&self, parts: &mut Parts) {
+        (*self).feed(parts)
+    }
+}
+
+thread_local! {
+    static SINK: RefCell<Vec<Diagnostic>> = const { RefCell::new(Vec::new()) };
+}
+
+pub fn emit(diagnostic: Diagnostic) {
+    SINK.with(|sink| sink.borrow_mut().push(diagnostic))
+}
+
+// everything reported since last time
+pub fn drain() -> Vec<Diagnostic> {
+    SINK.with(|sink| sink.borrow_mut().drain(..).collect())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Render {
+    Color,
+    Plain,
+    Json, // one object per line
+}
+
+impl Render {
+    // `HUGORM_DIAGNOSTICS=plain|json`, colors otherwise
+    pub fn from_env() -> Self {
+        match std::env::var("HUGORM_DIAGNOSTICS").as_ref().map(|s| s.as_str()) {
+            Ok("plain") => Render::Plain,
+            Ok("json") => Render::Json,
+            _ => Render::Color,
+        }
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        match *self {
+            Render::Color => Self::text(diagnostic, true) + "\n",
+            Render::Plain => Self::text(diagnostic, false) + "\n",
+            Render::Json => Self::json(diagnostic).to_string() + "\n",
+        }
+    }
+
+    fn text(diagnostic: &Diagnostic, color: bool) -> String {
+        let message_type = format!("\n{}", diagnostic.severity.as_str());
+
+        let mut out = if color {
+            format!("{}: {}", message_type.color(diagnostic.severity.color()).bold(), diagnostic.message)
+        } else {
+            format!("{}: {}", message_type, diagnostic.message)
+        };
+
+        if let Some(ref file) = diagnostic.file {
+            if color {
+                out.push_str(&format!("{}", FilePath(file.clone())))
+            } else {
+                out.push_str(&format!("\n{:>8} {}", "-->", file))
+            }
+        }
+
+        if let Some(ref span) = diagnostic.span {
+            out.push_str(&span.excerpt(color))
+        }
+
+        for note in diagnostic.notes.iter() {
+            out.push_str(&Self::text(note, color))
+        }
+
+        if let Some(ref help) = diagnostic.help {
+            if color {
+                out.push_str(&format!("\n{}: {}", "help".green().bold(), help))
+            } else {
+                out.push_str(&format!("\nhelp: {}", help))
+            }
+        }
+
+        out
+    }
 
-        let message = format!("{}: {}", message_type, message);
+    // lines and columns are 1-based, `column_end` is inclusive
+    fn json(diagnostic: &Diagnostic) -> serde_json::Value {
+        let span = diagnostic.span.as_ref().map(|span| serde_json::json!({
+            "line": (span.0).0,
+            "column_start": (span.1).0,
+            "column_end": (span.1).1,
+            "text": (span.0).1,
+        }));
 
-        write!(f, "{}", message)
+        serde_json::json!({
+            "severity": match diagnostic.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Note => "note",
+            },
+            "message": diagnostic.message,
+            "file": diagnostic.file,
+            "span": span,
+            "notes": diagnostic.notes.iter().map(Self::json).collect::<Vec<_>>(),
+            "help": diagnostic.help,
+        })
     }
 }
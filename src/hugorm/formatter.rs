@@ -0,0 +1,656 @@
+use super::error::{self, Diagnostic};
+use super::lexer::*;
+use super::parser::*;
+use super::source::*;
+
+// `hugorm fmt`: prints the AST back out as canonical source, comments and all
+
+const INDENT: &str = "    ";
+
+struct Comment {
+    line: usize,
+    text: String,
+    own_line: bool, // nothing but whitespace in front of it
+    code: String,   // what's in front of it on its line
+}
+
+// code that doesn't parse doesn't get formatted
+pub fn format(path: &str, content: &str) -> Result<String, Vec<Diagnostic>> {
+    let lines = content.lines().map(|x| x.into()).collect::<Vec<String>>();
+    let source = Source::from(path, lines.clone());
+
+    let mut lexer = Lexer::default(content.chars().collect(), &source);
+
+    let mut tokens = Vec::new();
+    let mut lexed = true;
+
+    for token_res in lexer.by_ref() {
+        match token_res {
+            Ok(token) => tokens.push(token),
+            Err(_) => lexed = false,
+        }
+    }
+
+    let comments = lexer.comments.iter().map(|token| Comment {
+        line: token.line.0,
+        text: token.lexeme.trim_end().to_string(),
+        own_line: token.line.1.chars().take(token.slice.0.saturating_sub(1)).all(char::is_whitespace),
+        code: token.line.1.chars().take(token.slice.0.saturating_sub(1)).collect(),
+    }).collect();
+
+    let (ast, errors) = Parser::new(tokens, &source).parse_partial();
+
+    let diagnostics = error::drain();
+
+    if !lexed || !errors.is_empty() {
+        return Err(diagnostics)
+    }
+
+    let mut formatter = Formatter {
+        out: String::new(),
+        lines,
+        comments,
+        next_comment: 0,
+        fresh: true,
+        reach: 0,
+    };
+
+    formatter.block(&ast, 0);
+    formatter.comments_before(usize::MAX, 0);
+
+    let mut out = formatter.out.trim_end().to_string();
+
+    if !out.is_empty() {
+        out.push('\n')
+    }
+
+    Ok(out)
+}
+
+struct Formatter {
+    out: String,
+    lines: Vec<String>,
+    comments: Vec<Comment>,
+    next_comment: usize,
+    fresh: bool, // right after a block opened, where blank lines don't go
+    reach: usize, // the last line a bracket closed on, its comment goes after the statement
+}
+
+fn line_of(pos: &Pos) -> usize {
+    (pos.0).0
+}
+
+fn precedence(op: &Operator) -> u8 {
+    Operator::from_str(op.as_str()).map(|(_, precedence)| precedence).unwrap_or(0)
+}
+
+fn is_name(name: &str) -> bool {
+    name.chars().next().is_some_and(char::is_alphabetic) && name.chars().all(|c| c.is_alphanumeric() || "_-'".contains(c))
+}
+
+fn string_literal(s: &str) -> String {
+    // there's no escape for backslashes, so those only fit in raw strings
+    if s.contains('\\') && !s.contains('"') {
+        return format!("r\"{}\"", s)
+    }
+
+    let escaped = s.replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t");
+
+    format!("\"{}\"", escaped)
+}
+
+impl Formatter {
+    fn write(&mut self, text: &str) {
+        self.out.push_str(text)
+    }
+
+    fn indent(&mut self, indent: usize) {
+        self.out.push_str(&INDENT.repeat(indent))
+    }
+
+    // one blank line where the source had any
+    fn gap(&mut self, line: usize) {
+        let blank = line >= 2 && self.lines.get(line - 2).is_some_and(|l| l.trim().is_empty());
+
+        if blank && !self.fresh && !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n')
+        }
+    }
+
+    // own-line comments, and trailing ones whose line never got printed on its own
+    fn comments_before(&mut self, line: usize, indent: usize) {
+        while self.next_comment < self.comments.len() && self.comments[self.next_comment].line < line {
+            let comment_line = self.comments[self.next_comment].line;
+            let text = self.comments[self.next_comment].text.clone();
+
+            self.gap(comment_line);
+            self.indent(indent);
+            self.write(&text);
+            self.write("\n");
+
+            self.fresh = false;
+            self.next_comment += 1
+        }
+    }
+
+    // ends the line that came from `line`, bringing its trailing comment along
+    fn finish_line(&mut self, line: usize) {
+        let start = self.out.rfind('\n').map(|i| i + 1).unwrap_or(0);
+
+        if self.out[start..].trim().is_empty() {
+            self.out.truncate(start);
+            return
+        }
+
+        let last = line.max(self.reach);
+        let trailing = self.comments.get(self.next_comment).is_some_and(|c| (line ..= last).contains(&c.line) && !c.own_line);
+
+        if trailing {
+            let text = self.comments[self.next_comment].text.clone();
+
+            self.write("  ");
+            self.write(&text);
+
+            self.next_comment += 1
+        }
+
+        self.write("\n")
+    }
+
+    // the elements of a list or dict that doesn't fit on one line, on the lines they
+    // were on, so the comments after them still are. `lines` says where each one was,
+    // dicts don't want commas at the end of a line
+    fn spread<F: FnMut(&mut Self, usize)>(&mut self, open: usize, lines: &[usize], close: char, indent: usize, mut element: F) {
+        let commas = close == ']';
+
+        // a comment after the first elements is theirs, not the bracket's
+        if lines.first() == Some(&open) {
+            self.write("\n")
+        } else {
+            self.finish_line(open)
+        }
+
+        let mut i = 0;
+
+        while i < lines.len() {
+            let line = lines[i];
+
+            self.comments_before(line, indent + 1);
+            self.indent(indent + 1);
+
+            let start = i;
+
+            while i < lines.len() && lines[i] == line {
+                if i > start {
+                    self.write(" ")
+                }
+
+                element(self, i);
+
+                let same_line = lines.get(i + 1) == Some(&line);
+
+                if same_line || (commas && i + 1 < lines.len()) {
+                    self.write(",")
+                }
+
+                i += 1
+            }
+
+            // the comment on the line that closes the bracket goes after the bracket
+            if i == lines.len() && self.closes(line, close) {
+                self.write("\n");
+                self.reach = line
+            } else {
+                self.finish_line(line)
+            }
+        }
+
+        // or on a line of its own
+        let after = self.comments.get(self.next_comment).map(|comment| comment.line);
+
+        if let (Some(after), Some(&last)) = (after, lines.last()) {
+            if after > last && self.closes(after, close) {
+                self.reach = after
+            }
+        }
+
+        self.indent(indent);
+        self.write(&close.to_string())
+    }
+
+    // whether `line` closes more `close` brackets than it opens, going by what's in
+    // front of its comment
+    fn closes(&self, line: usize, close: char) -> bool {
+        let code = match self.comments.get(self.next_comment) {
+            Some(comment) if comment.line == line && !comment.own_line => comment.code.clone(),
+            _ => return false,
+        };
+
+        let open = if close == ']' { '[' } else { '{' };
+
+        let mut depth = 0i32;
+        let mut quoted = false;
+
+        for c in code.chars() {
+            match c {
+                '"' => quoted = !quoted,
+                _ if quoted => (),
+                c if c == open => depth += 1,
+                c if c == close => depth -= 1,
+                _ => (),
+            }
+        }
+
+        depth < 0
+    }
+
+    fn block(&mut self, body: &[Statement], indent: usize) {
+        for statement in body.iter() {
+            self.statement(statement, indent)
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement, indent: usize) {
+        let line = line_of(&statement.pos);
+
+        self.comments_before(line, indent);
+        self.gap(line);
+
+        self.fresh = false;
+
+        self.indent(indent);
+        self.content(statement, indent);
+        self.finish_line(line)
+    }
+
+    // a body on the same line as its header stays there
+    fn body(&mut self, body: &[Statement], header_line: usize, indent: usize) {
+        if body.len() == 1 && line_of(&body[0].pos) == header_line {
+            self.write(" ");
+            self.content(&body[0], indent)
+        } else {
+            self.finish_line(header_line);
+
+            self.fresh = true;
+            self.block(body, indent + 1);
+            self.fresh = false
+        }
+    }
+
+    fn content(&mut self, statement: &Statement, indent: usize) {
+        use self::StatementNode::*;
+
+        let line = line_of(&statement.pos);
+
+        match statement.node {
+            Expression(ref expr) => self.expression(expr, indent),
+
+            Declaration(ref name, ref right) => {
+                self.write(&format!("let {}", name));
+
+                if let Some(ref right) = *right {
+                    self.write(" = ");
+                    self.expression(right, indent)
+                }
+            }
+
+            Const(ref name, ref right) => {
+                self.write(&format!("const {} = ", name));
+                self.expression(right, indent)
+            }
+
+            ConstFunction(ref fun) => {
+                self.write("const ");
+                self.content(fun, indent)
+            }
+
+            Test(ref fun) => {
+                self.write("test ");
+                self.content(fun, indent)
+            }
+
+            Assignment(ref left, ref right) => {
+                self.expression(left, indent);
+
+                // `x += 1` comes out of the parser as `x = x + 1`, with the same `x` twice
+                if let ExpressionNode::Binary(ref target, ref op, ref value) = right.node {
+                    if **target == *left && Operator::is_compoundable(op.as_str()) {
+                        self.write(&format!(" {}= ", op));
+                        self.expression(value, indent);
+
+                        return
+                    }
+                }
+
+                self.write(" = ");
+                self.expression(right, indent)
+            }
+
+            Function(ref name, ref params, ref body) => {
+                self.write(&format!("fun {}({}):", name, params.join(", ")));
+                self.body(body, line, indent)
+            }
+
+            Return(ref value) => {
+                self.write("return");
+
+                if let Some(ref value) = *value {
+                    self.write(" ");
+                    self.expression(value, indent)
+                }
+            }
+
+            Interface(ref name, ref body) => {
+                self.write(&format!("interface {}:", name));
+                self.body(body, line, indent)
+            }
+
+            If(ref cond, ref body, ref else_) => {
+                // `unless` is an `if not` sharing its condition's position
+                match cond.node {
+                    ExpressionNode::Not(ref inner) if inner.pos == cond.pos => {
+                        self.write("unless ");
+                        self.expression(inner, indent)
+                    }
+
+                    _ => {
+                        self.write("if ");
+                        self.expression(cond, indent)
+                    }
+                }
+
+                self.write(":");
+                self.body(body, line, indent);
+
+                for (cond, body) in else_.iter() {
+                    self.finish_line(line);
+                    self.indent(indent);
+
+                    let header_line = match *cond {
+                        Some(ref cond) => {
+                            self.write("elif ");
+                            self.expression(cond, indent);
+
+                            line_of(&cond.pos)
+                        }
+
+                        None => {
+                            self.write("else");
+
+                            // `else` has no position of its own, its body might share its line
+                            let first = body.first().map(|s| line_of(&s.pos)).unwrap_or(line);
+                            let shared = self.lines.get(first - 1).is_some_and(|l| l.trim_start().starts_with("else"));
+
+                            if shared { first } else { first - 1 }
+                        }
+                    };
+
+                    self.write(":");
+                    self.body(body, header_line, indent)
+                }
+            }
+
+            While(ref cond, ref body) => {
+                // `loop:` is a `while true` at the loop's own position
+                if cond.node == ExpressionNode::Bool(true) && cond.pos == statement.pos {
+                    self.write("loop:")
+                } else {
+                    self.write("while ");
+                    self.expression(cond, indent);
+                    self.write(":")
+                }
+
+                self.body(body, line, indent)
+            }
+
+            For(ref name, ref iterable, ref body) => {
+                self.write(&format!("for {} in ", name));
+                self.expression(iterable, indent);
+                self.write(":");
+
+                self.body(body, line, indent)
+            }
+
+            Block(ref body) => {
+                // `loop n:` is a counter, a `while` and an increment at the end of its body
+                if let [Statement { node: Declaration(ref counter, _), .. }, Statement { node: While(ref cond, ref body), .. }] = body[..] {
+                    if let ExpressionNode::Binary(_, Operator::Lt, ref count) = cond.node {
+                        if counter.starts_with('$') {
+                            self.write("loop ");
+                            self.expression(count, indent);
+                            self.write(":");
+
+                            self.body(&body[..body.len() - 1], line, indent);
+
+                            return
+                        }
+                    }
+                }
+
+                self.finish_line(line);
+                self.block(body, indent)
+            }
+
+            Use(ref module, ref alias, ref names) => {
+                if !names.is_empty() {
+                    self.write(&format!("grab {} from {}", names.join(", "), module))
+                } else if let Some(ref alias) = *alias {
+                    self.write(&format!("grab {} as {}", module, alias))
+                } else {
+                    self.write(&format!("grab {}", module))
+                }
+            },
+
+            Break => self.write("break"),
+        }
+    }
+
+    fn operand(&mut self, expression: &Expression, indent: usize, wrap: bool) {
+        if wrap {
+            self.write("(");
+            self.expression(expression, indent);
+            self.write(")")
+        } else {
+            self.expression(expression, indent)
+        }
+    }
+
+    // whether `child` needs parentheses to stay on its side of `parent`
+    fn loose(child: &Expression, parent: &Operator, right: bool) -> bool {
+        use self::ExpressionNode::*;
+
+        match child.node {
+            Binary(_, Operator::Index, _) => false,
+
+            Binary(_, ref op, _) => {
+                let (child, parent_precedence) = (precedence(op), precedence(parent));
+
+                child < parent_precedence || (child == parent_precedence && right != parent.is_right_ass())
+            }
+
+            // these take everything to their right
+            Not(_) | Neg(_) | With(..) | AnonFunction(..) => true,
+
+            _ => false,
+        }
+    }
+
+    // the literal the way it was written, `0xff` stays `0xff`
+    fn written(&self, pos: &Pos) -> Option<String> {
+        let line = (pos.0).1.chars().collect::<Vec<char>>();
+        let (start, end) = pos.1;
+
+        if start == 0 || end > line.len() || start > end {
+            return None
+        }
+
+        Some(line[start - 1..end].iter().collect())
+    }
+
+    fn expression(&mut self, expression: &Expression, indent: usize) {
+        use self::ExpressionNode::*;
+
+        match expression.node {
+            Nil => self.write("nil"),
+            Bool(b) => self.write(if b { "true" } else { "false" }),
+            Identifier(ref name) => self.write(name),
+            Str(ref s) => self.write(&string_literal(s)),
+
+            Int(n) => {
+                let text = self.written(&expression.pos)
+                    .filter(|text| text.starts_with(|c: char| c.is_ascii_digit() || c == '-'))
+                    .unwrap_or_else(|| n.to_string());
+
+                self.write(&text)
+            }
+
+            Float(n) => {
+                let text = self.written(&expression.pos)
+                    .filter(|text| text.replace('_', "").parse::<f64>().ok() == Some(n))
+                    .unwrap_or_else(|| format!("{:?}", n));
+
+                self.write(&text)
+            }
+
+            Neg(ref inner) | Not(ref inner) => {
+                self.write(if let Neg(_) = expression.node { "-" } else { "not " });
+
+                let wrap = matches!(inner.node, Binary(_, ref op, _) if *op != Operator::Index) || matches!(inner.node, With(..));
+                self.operand(inner, indent, wrap)
+            }
+
+            Binary(ref left, Operator::Index, ref right) => {
+                self.operand(left, indent, Self::loose(left, &Operator::Index, false));
+
+                // `a.b` and `a["b"]` are the same thing, keep whichever was written
+                let dotted = match right.node {
+                    Str(ref name) => is_name(name) && (right.pos.0).1.chars().nth((right.pos.1).0.saturating_sub(2)) == Some('.'),
+                    _ => false,
+                };
+
+                if dotted {
+                    if let Str(ref name) = right.node {
+                        self.write(".");
+                        self.write(name)
+                    }
+                } else {
+                    self.write("[");
+                    self.expression(right, indent);
+                    self.write("]")
+                }
+            }
+
+            Binary(ref left, ref op, ref right) => {
+                self.operand(left, indent, Self::loose(left, op, false));
+                self.write(&format!(" {} ", op));
+                self.operand(right, indent, Self::loose(right, op, true))
+            }
+
+            Call(ref callee, ref args) => {
+                let wrap = !matches!(callee.node, Identifier(_) | Call(..) | Binary(_, Operator::Index, _));
+                self.operand(callee, indent, wrap);
+
+                self.write("(");
+
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        self.write(", ")
+                    }
+
+                    self.expression(arg, indent)
+                }
+
+                self.write(")")
+            }
+
+            Array(ref content) => {
+                let line = line_of(&expression.pos);
+
+                if content.iter().all(|element| line_of(&element.pos) == line) {
+                    self.write("[");
+
+                    for (i, element) in content.iter().enumerate() {
+                        if i > 0 {
+                            self.write(", ")
+                        }
+
+                        self.expression(element, indent)
+                    }
+
+                    self.write("]")
+                } else {
+                    let lines = content.iter().map(|element| line_of(&element.pos)).collect::<Vec<_>>();
+
+                    self.write("[");
+
+                    self.spread(line, &lines, ']', indent, |formatter, i| {
+                        formatter.expression(&content[i], indent + 1)
+                    })
+                }
+            }
+
+            Dict(ref content) => {
+                let line = line_of(&expression.pos);
+
+                let inline = content.iter().all(|(_, value)| {
+                    line_of(&value.pos) == line && !matches!(value.node, AnonFunction(_, _, ref body) if body.iter().any(|s| line_of(&s.pos) != line))
+                });
+
+                if inline {
+                    self.write("{");
+
+                    for (i, (key, value)) in content.iter().enumerate() {
+                        if i > 0 {
+                            self.write(", ")
+                        }
+
+                        self.write(&format!("{}: ", key));
+                        self.expression(value, indent)
+                    }
+
+                    self.write("}")
+                } else {
+                    let lines = content.iter().map(|(_, value)| line_of(&value.pos)).collect::<Vec<_>>();
+
+                    self.write("{");
+
+                    self.spread(line, &lines, '}', indent, |formatter, i| {
+                        formatter.write(&format!("{}: ", content[i].0));
+                        formatter.expression(&content[i].1, indent + 1)
+                    })
+                }
+            }
+
+            With(ref left, ref right) => {
+                self.operand(left, indent, matches!(left.node, Binary(_, ref op, _) if *op != Operator::Index));
+                self.write(" with ");
+                self.expression(right, indent)
+            }
+
+            AnonFunction(_, ref params, ref body) => {
+                let line = line_of(&expression.pos);
+
+                self.write(&format!("fun({}):", params.join(", ")));
+
+                if body.len() == 1 && line_of(&body[0].pos) == line {
+                    self.write(" ");
+                    self.content(&body[0], indent)
+                } else {
+                    self.finish_line(line);
+
+                    self.fresh = true;
+                    self.block(body, indent + 1);
+                    self.fresh = false;
+
+                    // whatever follows the body, like a `)`, goes back at our level
+                    self.indent(indent)
+                }
+            }
+
+            Empty | EOF => (),
+        }
+    }
+}
@@ -4,10 +4,31 @@ use super::super::error::Response::Wrong;
 
 use std::rc::Rc;
 
+pub const KEYWORDS: &[&str] = &[
+    "fun",
+    "let",
+    "return",
+    "interface",
+    "with",
+    "const",
+    "nil",
+    "else",
+    "elif",
+    "if",
+    "while",
+    "break",
+    "loop",
+    "unless",
+    "grab",
+    "for",
+    "in"
+];
+
 pub struct Lexer<'l> {
     tokenizer: Tokenizer<'l>,
-    matchers: Vec<Rc<Matcher<'l>>>,
+    matchers: Vec<Rc<dyn Matcher<'l>>>,
     source: &'l Source,
+    pub comments: Vec<Token>, // the parser never sees them, the formatter and lint pragmas do
 }
 
 impl<'l> Lexer<'l> {
@@ -16,6 +37,7 @@ impl<'l> Lexer<'l> {
             tokenizer,
             matchers: Vec::new(),
             source,
+            comments: Vec::new(),
         }
     }
 
@@ -30,26 +52,7 @@ impl<'l> Lexer<'l> {
         lexer.matchers.push(Rc::new(EOLMatcher));
         lexer.matchers.push(Rc::new(StringLiteralMatcher));
 
-        lexer.matchers.push(Rc::new(KeyMatcher::new(
-            Keyword,
-            &[
-                "fun",
-                "let",
-                "return",
-                "interface",
-                "with",
-                "const",
-                "nil",
-                "else",
-                "elif",
-                "if",
-                "while",
-                "break",
-                "loop",
-                "unless",
-                "grab"
-            ],
-        )));
+        lexer.matchers.push(Rc::new(KeyMatcher::new(Keyword, KEYWORDS)));
 
         lexer
             .matchers
@@ -88,7 +91,7 @@ impl<'l> Lexer<'l> {
         lexer
     }
 
-    pub fn match_token(&mut self) -> Result<Option<Token>, ()> {
+    pub fn match_token(&mut self) -> Result<Option<Token>, Diagnostic> {
         for matcher in &mut self.matchers {
             match self.tokenizer.try_match_token(matcher.as_ref())? {
                 Some(t) => return Ok(Some(t)),
@@ -101,15 +104,17 @@ impl<'l> Lexer<'l> {
 }
 
 impl<'l> Iterator for Lexer<'l> {
-    type Item = Result<Token, ()>;
+    type Item = Result<Token, Diagnostic>;
 
-    fn next(&mut self) -> Option<Result<Token, ()>> {
+    fn next(&mut self) -> Option<Result<Token, Diagnostic>> {
         let token = match self.match_token() {
             Ok(hmm) => match hmm {
                 Some(n) => n,
                 None => {
                     let pos = self.tokenizer.pos;
 
+                    self.tokenizer.advance(); // skip it and keep going
+
                     return Some(Err(response!(
                         Wrong("bumped into weird character"),
                         self.source.file,
@@ -128,12 +133,25 @@ impl<'l> Iterator for Lexer<'l> {
                 }
             },
 
-            Err(_) => return Some(Err(())),
+            Err(diagnostic) => {
+                self.tokenizer.skip_line();
+
+                return Some(Err(diagnostic))
+            }
         };
 
         match token.token_type {
             TokenType::EOF => None,
             TokenType::Whitespace => self.next(),
+            // the parser still gets the line break a comment used to be, it's
+            // where blocks starting with a comment take their indentation from
+            TokenType::Comment => {
+                let eol = Token::new(TokenType::EOL, token.line.clone(), token.slice, "\n");
+
+                self.comments.push(token);
+
+                Some(Ok(eol))
+            }
             _ => Some(Ok(token)),
         }
     }
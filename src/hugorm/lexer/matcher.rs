@@ -1,6 +1,8 @@
 use super::super::error::Response::*;
 use super::*;
 
+use std::num::IntErrorKind;
+
 macro_rules! token {
     ($tokenizer:expr, $token_type:ident, $accum:expr) => {{
         token!($tokenizer, TokenType::$token_type, $accum)
@@ -38,19 +40,21 @@ macro_rules! token {
 }
 
 pub trait Matcher<'t> {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()>;
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic>;
 }
 
 pub struct CommentMatcher;
 
 impl<'t> Matcher<'t> for CommentMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
-        if tokenizer.peek_range(1).unwrap_or_else(String::new) == "#" {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
+        if tokenizer.peek_range(1).unwrap_or_default() == "#" {
+            let mut comment = String::new();
+
             while !tokenizer.end() && tokenizer.peek() != Some('\n') {
-                tokenizer.advance()
+                comment.push(tokenizer.next().unwrap())
             }
 
-            Ok(Some(token!(tokenizer, EOL, "\n".into())))
+            Ok(Some(token!(tokenizer, Comment, comment)))
         } else {
             Ok(None)
         }
@@ -72,7 +76,7 @@ impl ConstantStringMatcher {
 }
 
 impl<'t> Matcher<'t> for ConstantStringMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
         for constant in self.constants {
             let len = constant.len();
             let c = match tokenizer.peek_range(len) {
@@ -113,7 +117,7 @@ impl ConstantCharMatcher {
 }
 
 impl<'t> Matcher<'t> for ConstantCharMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
         let c = tokenizer.peek().unwrap();
 
         for constant in self.constants {
@@ -137,24 +141,20 @@ impl<'t> Matcher<'t> for ConstantCharMatcher {
 pub struct StringLiteralMatcher;
 
 impl<'t> Matcher<'t> for StringLiteralMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
         let mut raw_marker = false;
 
         let mut pos = tokenizer.pos;
 
         let delimeter = match tokenizer.peek().unwrap() {
             '"' => '"',
-            'r' => {
-                if tokenizer.peek_n(1) == Some('"') {
-                    raw_marker = true;
-                    tokenizer.advance();
+            'r' if tokenizer.peek_n(1) == Some('"') => {
+                raw_marker = true;
+                tokenizer.advance();
 
-                    pos = tokenizer.pos;
+                pos = tokenizer.pos;
 
-                    '"'
-                } else {
-                    return Ok(None);
-                }
+                '"'
             }
             _ => return Ok(None),
         };
@@ -227,11 +227,7 @@ impl<'t> Matcher<'t> for StringLiteralMatcher {
                     // check for valid closing delimeter and alternative
                     c => {
                         if c == delimeter {
-                            if string.len() > 0 && string != " " {
-                                break;
-                            } else {
-                                string.push(tokenizer.next().unwrap())
-                            }
+                            break;
                         } else {
                             string.push(tokenizer.next().unwrap())
                         }
@@ -255,7 +251,7 @@ impl<'t> Matcher<'t> for StringLiteralMatcher {
 pub struct IdentifierMatcher;
 
 impl<'t> Matcher<'t> for IdentifierMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
         let peeked = tokenizer.peek().unwrap();
 
         if !peeked.is_alphabetic() && ['\''].contains(&peeked) {
@@ -274,12 +270,46 @@ impl<'t> Matcher<'t> for IdentifierMatcher {
 
 pub struct NumberLiteralMatcher;
 
+// ints live in floats at runtime, so this is as big as they get while staying exact
+pub const MAX_INT: i64 = (1 << 53) - 1;
+
+impl NumberLiteralMatcher {
+    fn radix(prefix: &str) -> Option<u32> {
+        match prefix {
+            "0x" | "0X" => Some(16),
+            "0o" | "0O" => Some(8),
+            "0b" | "0B" => Some(2),
+            _ => None,
+        }
+    }
+
+    fn literal_error<'t>(tokenizer: &Tokenizer<'t>, raw: &str, message: String, help: &str) -> Diagnostic {
+        let token = token!(tokenizer, Int, raw.to_string());
+
+        response!(
+            Wrong(message),
+            tokenizer.source.file,
+            Pos(token.line, token.slice),
+            Help(help)
+        )
+    }
+
+    fn too_big(tokenizer: &Tokenizer, raw: &str) -> Diagnostic {
+        Self::literal_error(
+            tokenizer, raw,
+            format!("int literal `{}` is too big", raw),
+            &format!("ints are exact up to {}, use a float for anything bigger", MAX_INT)
+        )
+    }
+}
+
 impl<'t> Matcher<'t> for NumberLiteralMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
         let mut accum = String::new();
+        let mut raw = String::new(); // what was actually written, for positions
 
         let curr = tokenizer.next().unwrap();
-        if curr.is_digit(10) {
+        if curr.is_ascii_digit() {
             accum.push(curr)
         } else if curr == '.' {
             accum.push_str("0.")
@@ -289,9 +319,76 @@ impl<'t> Matcher<'t> for NumberLiteralMatcher {
             return Ok(None);
         }
 
+        raw.push(curr);
+
+        if accum == "-" && tokenizer.peek() == Some('0') {
+            accum.push(tokenizer.next().unwrap());
+            raw.push('0')
+        }
+
+        // 0x.., 0o.. and 0b..
+        let radix = if accum == "0" || accum == "-0" {
+            let prefix = format!("0{}", tokenizer.peek().unwrap_or(' '));
+
+            Self::radix(&prefix).inspect(|_| raw.push(tokenizer.next().unwrap()))
+        } else {
+            None
+        };
+
+        if let Some(radix) = radix {
+            let mut digits = String::new();
+
+            while let Some(current) = tokenizer.peek() {
+                if current.is_ascii_alphanumeric() || current == '_' {
+                    raw.push(tokenizer.next().unwrap());
+
+                    if current != '_' {
+                        digits.push(current)
+                    }
+                } else {
+                    break
+                }
+            }
+
+            let value = match i64::from_str_radix(&digits, radix) {
+                Ok(value) if value <= MAX_INT => value,
+
+                Ok(_) => return Err(Self::too_big(tokenizer, &raw)),
+                Err(ref error) if *error.kind() == IntErrorKind::PosOverflow => return Err(Self::too_big(tokenizer, &raw)),
+
+                Err(_) => return Err(Self::literal_error(
+                    tokenizer, &raw,
+                    format!("invalid base {} literal `{}`", radix, raw),
+                    "digits must fit the base, like `0xff`, `0o17` or `0b1010`"
+                )),
+            };
+
+            let value = if accum.starts_with('-') { -value } else { value };
+
+            let mut token = token!(tokenizer, Int, raw);
+            token.lexeme = value.to_string();
+
+            return Ok(Some(token))
+        }
+
         while !tokenizer.end() {
             let current = tokenizer.peek().unwrap();
-            if !current.is_whitespace() && current.is_digit(10) || current == '.' {
+
+            // `1_000_000`, separators only go between digits
+            if current == '_' && accum.ends_with(|c: char| c.is_ascii_digit()) {
+                if tokenizer.peek_n(1).is_some_and(|c| c.is_ascii_digit()) {
+                    raw.push(tokenizer.next().unwrap());
+                    continue
+                }
+
+                return Err(Self::literal_error(
+                    tokenizer, &format!("{}_", raw),
+                    "misplaced digit separator".to_string(),
+                    "`_` can only go between two digits"
+                ))
+            }
+
+            if !current.is_whitespace() && current.is_ascii_digit() || current == '.' {
                 if current == '.' && accum.contains('.') {
                     let pos = tokenizer.pos;
 
@@ -312,7 +409,11 @@ impl<'t> Matcher<'t> for NumberLiteralMatcher {
                         )
                     ));
                 }
-                accum.push(tokenizer.next().unwrap())
+
+                let c = tokenizer.next().unwrap();
+
+                accum.push(c);
+                raw.push(c)
             } else {
                 break;
             }
@@ -320,22 +421,32 @@ impl<'t> Matcher<'t> for NumberLiteralMatcher {
 
         if ["-", "-0.", "-.", "0."].contains(&accum.as_str()) {
             Ok(None)
+        } else if accum.contains('.') {
+            let literal: String = match accum.parse::<f64>() {
+                Ok(result) => result.to_string(),
+
+                Err(_) => return Err(Self::literal_error(
+                    tokenizer, &raw,
+                    format!("invalid float literal `{}`", raw),
+                    "floats look like `1.5` or `.5`"
+                )),
+            };
+
+            let mut token = token!(tokenizer, Float, raw);
+            token.lexeme = literal;
+
+            Ok(Some(token))
         } else {
-            if accum.contains(".") {
-                let literal: String = match accum.parse::<f64>() {
-                    Ok(result) => result.to_string(),
-                    Err(error) => panic!("unable to parse float `{}`: {}", accum, error),
-                };
+            // the digits are all there is, so the only way this fails is by overflowing
+            let literal = match accum.parse::<i64>() {
+                Ok(result) if result.unsigned_abs() <= MAX_INT as u64 => result.to_string(),
+                _ => return Err(Self::too_big(tokenizer, &raw)),
+            };
 
-                Ok(Some(token!(tokenizer, Float, literal)))
-            } else {
-                let literal: String = match accum.parse::<f64>() {
-                    Ok(result) => result.to_string(),
-                    Err(error) => panic!("unable to parse int `{}`: {}", accum, error),
-                };
+            let mut token = token!(tokenizer, Int, raw);
+            token.lexeme = literal;
 
-                Ok(Some(token!(tokenizer, Int, literal)))
-            }
+            Ok(Some(token))
         }
     }
 }
@@ -355,7 +466,7 @@ impl KeyMatcher {
 }
 
 impl<'t> Matcher<'t> for KeyMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
         for constant in self.constants {
             if let Some(s) = tokenizer.peek_range(constant.len()) {
                 if s == *constant {
@@ -382,7 +493,7 @@ impl<'t> Matcher<'t> for KeyMatcher {
 pub struct EOLMatcher;
 
 impl<'t> Matcher<'t> for EOLMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
         if tokenizer.peek() == Some('\n') {
             tokenizer.pos.0 += 1;
             tokenizer.pos.1 = 0;
@@ -398,7 +509,7 @@ impl<'t> Matcher<'t> for EOLMatcher {
 pub struct WhitespaceMatcher;
 
 impl<'t> Matcher<'t> for WhitespaceMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
         let string = tokenizer.collect_while(|c| c.is_whitespace() && c != '\n');
 
         if !string.is_empty() {
@@ -1,9 +1,11 @@
+#[allow(clippy::module_inception)]
 pub mod lexer;
 pub mod matcher;
 pub mod token;
 pub mod tokenizer;
 
 use super::source::Source;
+use super::error::Diagnostic;
 
 pub use self::lexer::*;
 pub use self::matcher::*;
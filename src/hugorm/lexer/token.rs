@@ -13,6 +13,7 @@ pub enum TokenType {
     Operator,
     Bool,
     Whitespace,
+    Comment,
     EOL,
     EOF,
 }
@@ -32,6 +33,7 @@ impl fmt::Display for TokenType {
             Symbol => write!(f, "Symbol"),
             Operator => write!(f, "Operator"),
             Whitespace => write!(f, "Whitespace"),
+            Comment => write!(f, "Comment"),
             EOL => write!(f, "EOL"),
             EOF => write!(f, "EOF"),
         }
@@ -41,44 +43,84 @@ impl fmt::Display for TokenType {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Pos(pub (usize, String), pub (usize, usize));
 
+pub fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || "_-'".contains(c)
+}
+
 impl Pos {
+    // the first whole-word `name` on this line from `column` on
+    pub fn find(&self, name: &str, column: usize) -> Option<Pos> {
+        let line = (self.0).1.chars().collect::<Vec<char>>();
+        let wanted = name.chars().collect::<Vec<char>>();
+
+        let mut start = column.saturating_sub(1);
+
+        while start + wanted.len() <= line.len() {
+            let end = start + wanted.len();
+
+            let fits = line[start..end] == wanted[..]
+                && (start == 0 || !is_name_char(line[start - 1]))
+                && (end == line.len() || !is_name_char(line[end]));
+
+            if fits {
+                return Some(Pos(self.0.clone(), (start + 1, end)))
+            }
+
+            start += 1
+        }
+
+        None
+    }
+
     pub fn get_lexeme(&self) -> String {
         (self.0).1[(self.1).0 - if (self.1).0 > 0 { 1 } else { 0 }..(self.1).1].to_string()
     }
-}
 
-impl fmt::Display for Pos {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let linepad = format!("{:5} │", " ").blue().bold();
-        let lineno = format!("{:5} │ ", (self.0).0).blue().bold();
-        let mut mark = (self.0).1[(self.1).0.saturating_sub(1)..(self.1).1].to_string();
+    // the line with the span marked out below it
+    pub fn excerpt(&self, color: bool) -> String {
+        let paint = |s: &str, c: &str| if color { format!("{}", s.color(c).bold()) } else { s.to_string() };
+
+        let linepad = paint(&format!("{:5} │", " "), "blue");
+        let lineno = paint(&format!("{:5} │ ", (self.0).0), "blue");
+
+        // spans may hang off the end of the line after a bad token
+        let line = &(self.0).1;
+        let end = (self.1).1.min(line.len());
+        let start = (self.1).0.saturating_sub(1).min(end);
+
+        let mut mark = line[start..end].to_string();
 
         if mark.split_whitespace().count() == 0 {
-            mark = format!("{:─>count$}", ">".red().bold(), count = mark.len());
+            mark = format!("{:─>count$}", paint(">", "red"), count = mark.len());
         } else {
-            mark = format!("{}", mark.red().bold());
+            mark = paint(&mark, "red");
         }
 
         let mut arrows = format!("{: <count$}", " ", count = (self.1).0);
 
-        for _ in 0..(self.1).1 - (self.1).0 + 1 {
+        for _ in 0..((self.1).1 + 1).saturating_sub((self.1).0) {
             arrows.push('^')
         }
 
-        write!(
-            f,
+        format!(
             "\n{}\n{}{}{}{}\n{}{}",
             linepad,
             lineno,
-            &(self.0).1[..(self.1).0.saturating_sub(1)],
+            &line[..start],
             mark,
-            &(self.0).1[(self.1).1..],
+            &line[end..],
             linepad,
-            arrows.red().bold()
+            paint(&arrows, "red")
         )
     }
 }
 
+impl fmt::Display for Pos {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.excerpt(true))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
@@ -1,5 +1,5 @@
 use super::token::*;
-use super::{Matcher, Source};
+use super::{Diagnostic, Matcher, Source};
 
 pub struct Snapshot {
     pub index: usize,
@@ -38,7 +38,7 @@ impl<'t> Tokenizer<'t> {
     }
 
     pub fn advance(&mut self) {
-        if let Some(item) = self.items.get(self.index + 1) {
+        if self.items.get(self.index + 1).is_some() {
             self.pos.1 += 1
         }
 
@@ -89,13 +89,13 @@ impl<'t> Tokenizer<'t> {
             .pos
     }
 
-    pub fn try_match_token(&mut self, matcher: &Matcher<'t>) -> Result<Option<Token>, ()> {
+    pub fn try_match_token(&mut self, matcher: &dyn Matcher<'t>) -> Result<Option<Token>, Diagnostic> {
         if self.end() {
             return Ok(Some(Token::new(
                 TokenType::EOF,
                 (
                     self.pos.0,
-                    if self.source.lines.len() > 0 {
+                    if !self.source.lines.is_empty() {
                         self.source
                             .lines
                             .get(self.pos.0)
@@ -112,16 +112,32 @@ impl<'t> Tokenizer<'t> {
 
         self.take_snapshot();
 
-        match matcher.try_match(self)? {
-            Some(t) => {
+        match matcher.try_match(self) {
+            Ok(Some(t)) => {
                 self.commit_snapshot();
                 Ok(Some(t))
             }
 
-            None => {
+            Ok(None) => {
                 self.rollback_snapshot();
                 Ok(None)
             }
+
+            Err(diagnostic) => {
+                self.commit_snapshot();
+                Err(diagnostic)
+            }
+        }
+    }
+
+    // give up on the rest of the line, keeping the newline
+    pub fn skip_line(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '\n' {
+                break
+            }
+
+            self.advance()
         }
     }
 
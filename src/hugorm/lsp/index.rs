@@ -0,0 +1,372 @@
+use super::super::lexer::Pos;
+use super::super::parser::*;
+
+// where every name is declared and used in a document, for go-to-definition,
+// hover, completion and the outline
+
+// 1-based, both ends included, like `Pos`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    // the cursor touching either end counts
+    pub fn contains(&self, line: usize, column: usize) -> bool {
+        self.line == line && self.start <= column && column <= self.end + 1
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kind {
+    Function(Vec<String>),
+    Method(String, Vec<String>), // interface, params
+    Interface(Vec<String>),      // methods
+    Variable,
+    Constant,
+    Parameter,
+}
+
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub name: String,
+    pub kind: Kind,
+    pub span: Span,
+    pub last_line: usize, // of the body, for functions and interfaces
+    pub scope: Option<usize>, // methods aren't reachable by name
+    pub parent: Option<usize>, // enclosing function or interface
+}
+
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: String,
+    pub span: Span,
+    pub member: bool, // `x.name`
+    pub scope: usize,
+    pub definition: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Scope {
+    parent: Option<usize>,
+    lines: (usize, usize),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Index {
+    pub definitions: Vec<Definition>,
+    pub references: Vec<Reference>,
+    scopes: Vec<Scope>,
+}
+
+impl Index {
+    pub fn build(ast: &[Statement]) -> Self {
+        let mut index = Index::default();
+
+        index.scopes.push(Scope {
+            parent: None,
+            lines: (0, usize::MAX),
+        });
+
+        index.statements(ast, 0, None);
+        index.resolve();
+
+        index
+    }
+
+    // the definition of whatever's under the cursor
+    pub fn at(&self, line: usize, column: usize) -> Option<usize> {
+        self.references.iter()
+            .find(|reference| reference.span.contains(line, column))
+            .and_then(|reference| reference.definition)
+            .or_else(|| self.definitions.iter().position(|definition| definition.span.contains(line, column)))
+    }
+
+    // the name under the cursor, resolved or not
+    pub fn name_at(&self, line: usize, column: usize) -> Option<&str> {
+        self.references.iter().map(|r| (&r.name, r.span))
+            .chain(self.definitions.iter().map(|d| (&d.name, d.span)))
+            .find(|(_, span)| span.contains(line, column))
+            .map(|(name, _)| name.as_str())
+    }
+
+    // everything reachable by name from a line
+    pub fn visible(&self, line: usize) -> Vec<&Definition> {
+        self.definitions.iter()
+            .filter(|definition| definition.scope.is_some_and(|scope| {
+                let (first, last) = self.scopes[scope].lines;
+                first <= line && line <= last
+            }))
+            .collect()
+    }
+
+    pub fn methods(&self) -> Vec<&Definition> {
+        self.definitions.iter()
+            .filter(|definition| matches!(definition.kind, Kind::Method(..)))
+            .collect()
+    }
+
+    fn define(&mut self, name: &str, kind: Kind, span: Span, scope: Option<usize>, parent: Option<usize>) -> usize {
+        self.definitions.push(Definition {
+            name: name.to_string(),
+            kind,
+            span,
+            last_line: span.line,
+            scope,
+            parent,
+        });
+
+        self.definitions.len() - 1
+    }
+
+    fn refer(&mut self, name: &str, pos: &Pos, member: bool, scope: usize) {
+        self.references.push(Reference {
+            name: name.to_string(),
+            span: Span {
+                line: (pos.0).0,
+                start: (pos.1).0,
+                end: (pos.1).1,
+            },
+            member,
+            scope,
+            definition: None,
+        })
+    }
+
+    fn scope(&mut self, parent: usize, first: usize, body: &[Statement]) -> usize {
+        self.scopes.push(Scope {
+            parent: Some(parent),
+            lines: (first, last_line(body).max(first)),
+        });
+
+        self.scopes.len() - 1
+    }
+
+    // declarations are hoisted, so the nearest scope declaring a name wins
+    fn resolve(&mut self) {
+        for i in 0..self.references.len() {
+            let reference = &self.references[i];
+
+            let found = if reference.member {
+                self.definitions.iter().position(|d| d.name == reference.name && matches!(d.kind, Kind::Method(..)))
+            } else {
+                let mut scope = Some(reference.scope);
+                let mut found = None;
+
+                while let Some(current) = scope {
+                    found = self.definitions.iter().position(|d| d.name == reference.name && d.scope == Some(current));
+
+                    if found.is_some() {
+                        break
+                    }
+
+                    scope = self.scopes[current].parent
+                }
+
+                found
+            };
+
+            self.references[i].definition = found
+        }
+    }
+
+    fn statements(&mut self, body: &[Statement], scope: usize, parent: Option<usize>) {
+        for statement in body.iter() {
+            self.statement(statement, scope, parent)
+        }
+    }
+
+    fn function(&mut self, pos: &Pos, params: &[String], body: &[Statement], after: usize, scope: usize, parent: Option<usize>) {
+        let inner = self.scope(scope, (pos.0).0, body);
+
+        let mut column = after;
+
+        for param in params.iter() {
+            let span = find_name(pos, param, column);
+            column = span.end + 1;
+
+            self.define(param, Kind::Parameter, span, Some(inner), parent);
+        }
+
+        self.statements(body, inner, parent)
+    }
+
+    fn statement(&mut self, statement: &Statement, scope: usize, parent: Option<usize>) {
+        use self::StatementNode::*;
+
+        let pos = &statement.pos;
+
+        match statement.node {
+            Expression(ref expr) => self.expression(expr, scope, parent),
+
+            Declaration(ref name, ref right) => {
+                self.define(name, Kind::Variable, find_name(pos, name, (pos.1).0), Some(scope), parent);
+
+                if let Some(ref right) = *right {
+                    self.expression(right, scope, parent)
+                }
+            }
+
+            Const(ref name, ref right) => {
+                self.define(name, Kind::Constant, find_name(pos, name, (pos.1).0), Some(scope), parent);
+                self.expression(right, scope, parent)
+            }
+
+            ConstFunction(ref fun) | Test(ref fun) => self.statement(fun, scope, parent),
+
+            Assignment(ref left, ref right) => {
+                self.expression(left, scope, parent);
+                self.expression(right, scope, parent)
+            }
+
+            Function(ref name, ref params, ref body) => {
+                let span = find_name(pos, name, (pos.1).0);
+                let function = self.define(name, Kind::Function(params.clone()), span, Some(scope), parent);
+
+                self.definitions[function].last_line = last_line(body).max(span.line);
+
+                self.function(pos, params, body, span.end + 1, scope, Some(function))
+            }
+
+            Interface(ref name, ref body) => {
+                let methods = body.iter().filter_map(|statement| match statement.node {
+                    Function(ref name, ..) => Some(name.clone()),
+                    _ => None,
+                }).collect();
+
+                let span = find_name(pos, name, (pos.1).0);
+                let interface = self.define(name, Kind::Interface(methods), span, Some(scope), parent);
+
+                self.definitions[interface].last_line = last_line(body).max(span.line);
+
+                for statement in body.iter() {
+                    if let Function(ref method, ref params, ref body) = statement.node {
+                        let pos = &statement.pos;
+                        let span = find_name(pos, method, (pos.1).0);
+
+                        let kind = Kind::Method(name.clone(), params.clone());
+                        let function = self.define(method, kind, span, None, Some(interface));
+
+                        self.definitions[function].last_line = last_line(body).max(span.line);
+
+                        self.function(pos, params, body, span.end + 1, scope, Some(function))
+                    }
+                }
+            }
+
+            Return(ref value) => if let Some(ref value) = *value {
+                self.expression(value, scope, parent)
+            },
+
+            If(ref cond, ref body, ref else_) => {
+                self.expression(cond, scope, parent);
+                self.statements(body, scope, parent);
+
+                for (cond, body) in else_.iter() {
+                    if let Some(ref cond) = *cond {
+                        self.expression(cond, scope, parent)
+                    }
+
+                    self.statements(body, scope, parent)
+                }
+            }
+
+            While(ref cond, ref body) => {
+                self.expression(cond, scope, parent);
+                self.statements(body, scope, parent)
+            }
+
+            For(ref name, ref iterable, ref body) => {
+                self.define(name, Kind::Variable, find_name(pos, name, (pos.1).0), Some(scope), parent);
+
+                self.expression(iterable, scope, parent);
+                self.statements(body, scope, parent)
+            }
+
+            Block(ref body) => self.statements(body, scope, parent),
+
+            Use(..) | Break => (),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression, scope: usize, parent: Option<usize>) {
+        use self::ExpressionNode::*;
+
+        match expression.node {
+            Identifier(ref name) => self.refer(name, &expression.pos, false, scope),
+
+            Binary(ref left, Operator::Index, ref right) => {
+                self.expression(left, scope, parent);
+
+                match right.node {
+                    Str(ref name) => self.refer(name, &right.pos, true, scope),
+                    _ => self.expression(right, scope, parent),
+                }
+            }
+
+            Neg(ref expr) | Not(ref expr) => self.expression(expr, scope, parent),
+
+            Binary(ref left, _, ref right) | With(ref left, ref right) => {
+                self.expression(left, scope, parent);
+                self.expression(right, scope, parent)
+            }
+
+            Call(ref callee, ref args) => {
+                self.expression(callee, scope, parent);
+
+                for arg in args.iter() {
+                    self.expression(arg, scope, parent)
+                }
+            }
+
+            Array(ref content) => for element in content.iter() {
+                self.expression(element, scope, parent)
+            },
+
+            Dict(ref content) => for (_, value) in content.iter() {
+                self.expression(value, scope, parent)
+            },
+
+            AnonFunction(_, ref params, ref body) => {
+                self.function(&expression.pos, params, body, (expression.pos.1).0, scope, parent)
+            }
+
+            Nil | Int(_) | Float(_) | Str(_) | Bool(_) | Empty | EOF => (),
+        }
+    }
+}
+
+// declarations only know where their statement starts, so the name is looked
+// up on the line from `column` on
+fn find_name(pos: &Pos, name: &str, column: usize) -> Span {
+    let found = pos.find(name, column).unwrap_or_else(|| pos.clone());
+
+    Span {
+        line: (found.0).0,
+        start: (found.1).0,
+        end: (found.1).1,
+    }
+}
+
+fn last_line(body: &[Statement]) -> usize {
+    use self::StatementNode::*;
+
+    body.iter().map(|statement| {
+        let nested = match statement.node {
+            Function(_, _, ref body) | Interface(_, ref body) | While(_, ref body)
+            | For(_, _, ref body) | Block(ref body) => last_line(body),
+
+            ConstFunction(ref fun) | Test(ref fun) => last_line(std::slice::from_ref(fun)),
+
+            If(_, ref body, ref else_) => else_.iter()
+                .map(|(_, body)| last_line(body))
+                .fold(last_line(body), usize::max),
+
+            _ => 0,
+        };
+
+        nested.max((statement.pos.0).0)
+    }).max().unwrap_or(0)
+}
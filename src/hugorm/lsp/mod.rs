@@ -0,0 +1,5 @@
+pub mod index;
+pub mod server;
+
+pub use self::index::*;
+pub use self::server::*;
@@ -0,0 +1,529 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use super::super::engine::{panic_message, quietly, Engine};
+use super::super::error::{self, Diagnostic, Severity};
+use super::super::lexer::*;
+use super::super::parser::*;
+use super::super::prelude::assert;
+use super::super::source::*;
+use super::super::visitor::*;
+use super::index::*;
+
+// `hugorm lsp`, a language server speaking JSON-RPC over stdio
+
+// what's known about an open document
+pub struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    pub index: Index,
+    pub symtab: SymTab, // globals and natives, as the visitor left them
+    pub lines: Vec<String>,
+}
+
+pub fn analyze(path: &str, text: &str) -> Analysis {
+    let mut engine = Engine::new();
+
+    engine.set_root(&Path::new(path).parent().map(|dir| dir.display().to_string()).unwrap_or_default());
+    assert::include_assert(&mut engine);
+
+    let diagnostics = match quietly(|| engine.compile_str(path, text)) {
+        Ok(Ok((_, warnings))) => warnings,
+        Ok(Err(errors)) => errors,
+        Err(payload) => {
+            let why = panic_message(&*payload);
+
+            let mut diagnostic = Diagnostic::new(Severity::Error, format!("the compiler fell over: {}", why));
+            diagnostic.file = Some(path.to_string());
+
+            vec!(diagnostic)
+        }
+    };
+
+    // the index wants whatever parses, even when something else doesn't
+    let lines = text.lines().map(|x| x.into()).collect::<Vec<String>>();
+    let source = Source::from(path, lines.clone());
+
+    let tokens = Lexer::default(text.chars().collect(), &source).filter_map(Result::ok).collect();
+    let (ast, _) = Parser::new(tokens, &source).parse_partial();
+
+    error::drain();
+
+    Analysis {
+        diagnostics,
+        index: Index::build(&ast),
+        symtab: engine.symtab,
+        lines,
+    }
+}
+
+impl Analysis {
+    // where the 1-based `column` is on `line`, as `encoding` counts it from 0
+    fn character(&self, line: usize, column: usize, encoding: Encoding) -> usize {
+        let text = self.lines.get(line - 1).map(String::as_str).unwrap_or_default();
+
+        match encoding {
+            Encoding::Utf32 => column - 1,
+            Encoding::Utf16 => {
+                let units = text.chars().take(column - 1).map(char::len_utf16).sum::<usize>();
+
+                // past the end of the line it's one a column
+                units + (column - 1).saturating_sub(text.chars().count())
+            }
+        }
+    }
+
+    // the other way around
+    fn column(&self, line: usize, character: usize, encoding: Encoding) -> usize {
+        let text = self.lines.get(line - 1).map(String::as_str).unwrap_or_default();
+
+        match encoding {
+            Encoding::Utf32 => character + 1,
+            Encoding::Utf16 => {
+                let mut units = 0;
+                let mut column = 1;
+
+                for c in text.chars() {
+                    if units >= character {
+                        break
+                    }
+
+                    units += c.len_utf16();
+                    column += 1
+                }
+
+                column + character.saturating_sub(units)
+            }
+        }
+    }
+
+    fn range(&self, span: &Span, encoding: Encoding) -> Value {
+        json!({
+            "start": { "line": span.line - 1, "character": self.character(span.line, span.start, encoding) },
+            "end": { "line": span.line - 1, "character": self.character(span.line, span.end + 1, encoding) },
+        })
+    }
+}
+
+// what a position's `character` counts. clients have to understand utf-16,
+// utf-32 (chars, like everything in here) is used when they say they can
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf16,
+    Utf32,
+}
+
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Analysis>,
+    encoding: Encoding,
+    shut_down: bool,
+    exited: bool,
+}
+
+// serves until `exit` or the end of input, true when it was shut down properly
+pub fn run<R: BufRead, W: Write>(mut input: R, mut output: W) -> io::Result<bool> {
+    let mut server = Server::default();
+
+    while let Some(message) = read_message(&mut input)? {
+        for reply in server.handle(&message) {
+            write_message(&mut output, &reply)?
+        }
+
+        if server.exited {
+            break
+        }
+    }
+
+    Ok(server.shut_down)
+}
+
+// `None` at the end of input, `Null` for a body that isn't JSON
+pub fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Value>> {
+    let mut length = None;
+
+    loop {
+        let mut line = String::new();
+
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None)
+        }
+
+        let line = line.trim_end();
+
+        if line.is_empty() {
+            break
+        }
+
+        if let Some(n) = line.strip_prefix("Content-Length:") {
+            length = n.trim().parse::<usize>().ok()
+        }
+    }
+
+    let length = length.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length"))?;
+
+    let mut body = vec!(0; length);
+    input.read_exact(&mut body)?;
+
+    Ok(Some(serde_json::from_slice(&body).unwrap_or(Value::Null)))
+}
+
+pub fn write_message<W: Write>(output: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn respond(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn respond_error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn uri_to_path(uri: &str) -> String {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+
+    let mut decoded = Vec::new();
+    let bytes = path.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = if bytes[i] == b'%' && i + 2 < bytes.len() {
+            std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+impl Server {
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        if !message.is_object() {
+            return vec!(respond_error(Value::Null, -32700, "couldn't parse that"))
+        }
+
+        let id = message.get("id").cloned();
+        let params = &message["params"];
+
+        let method = match message["method"].as_str() {
+            Some(method) => method,
+            None => return Vec::new(), // a response, nothing asked for any
+        };
+
+        match method {
+            "initialize" => {
+                let offered = params["capabilities"]["general"]["positionEncodings"].as_array();
+
+                if offered.is_some_and(|offered| offered.contains(&json!("utf-32"))) {
+                    self.encoding = Encoding::Utf32
+                }
+
+                let encoding = match self.encoding {
+                    Encoding::Utf16 => "utf-16",
+                    Encoding::Utf32 => "utf-32",
+                };
+
+                vec!(respond(id.unwrap_or_default(), json!({
+                    "capabilities": {
+                        "positionEncoding": encoding,
+                        "textDocumentSync": 1,
+                        "definitionProvider": true,
+                        "hoverProvider": true,
+                        "completionProvider": { "triggerCharacters": ["."] },
+                        "documentSymbolProvider": true,
+                    },
+                    "serverInfo": { "name": "hugorm" },
+                })))
+            }
+
+            "shutdown" => {
+                self.shut_down = true;
+                vec!(respond(id.unwrap_or_default(), Value::Null))
+            }
+
+            "exit" => {
+                self.exited = true;
+                Vec::new()
+            }
+
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                self.update(document["uri"].as_str().unwrap_or_default(), document["text"].as_str().unwrap_or_default())
+            }
+
+            // the whole text every time, that's what we asked for
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+
+                match params["contentChanges"].as_array().and_then(|changes| changes.last()) {
+                    Some(change) => self.update(uri, change["text"].as_str().unwrap_or_default()),
+                    None => Vec::new(),
+                }
+            }
+
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                self.documents.remove(uri);
+
+                vec!(json!({
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": { "uri": uri, "diagnostics": [] },
+                }))
+            }
+
+            "textDocument/definition" | "textDocument/hover" | "textDocument/completion" | "textDocument/documentSymbol" => {
+                let id = id.unwrap_or_default();
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+
+                let analysis = match self.documents.get(uri) {
+                    Some(analysis) => analysis,
+                    None => return vec!(respond_error(id, -32602, "that document isn't open")),
+                };
+
+                // 1-based, like everything else here
+                let line = params["position"]["line"].as_u64().unwrap_or(0) as usize + 1;
+                let column = analysis.column(line, params["position"]["character"].as_u64().unwrap_or(0) as usize, self.encoding);
+
+                let result = match method {
+                    "textDocument/definition" => definition(analysis, uri, line, column, self.encoding),
+                    "textDocument/hover" => hover(analysis, line, column),
+                    "textDocument/completion" => completion(analysis, line, column),
+                    _ => document_symbols(analysis, self.encoding),
+                };
+
+                vec!(respond(id, result))
+            }
+
+            _ => match id {
+                Some(id) => vec!(respond_error(id, -32601, &format!("no such method `{}`", method))),
+                None => Vec::new(),
+            },
+        }
+    }
+
+    fn update(&mut self, uri: &str, text: &str) -> Vec<Value> {
+        let path = uri_to_path(uri);
+        let analysis = analyze(&path, text);
+
+        let diagnostics = analysis.diagnostics.iter().map(|d| lsp_diagnostic(&analysis, d, &path, self.encoding)).collect::<Vec<Value>>();
+
+        self.documents.insert(uri.to_string(), analysis);
+
+        vec!(json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        }))
+    }
+}
+
+fn lsp_diagnostic(analysis: &Analysis, diagnostic: &Diagnostic, path: &str, encoding: Encoding) -> Value {
+    let mut message = diagnostic.message.clone();
+
+    if let Some(ref help) = diagnostic.help {
+        message.push_str(&format!("\nhelp: {}", help))
+    }
+
+    for note in diagnostic.notes.iter() {
+        message.push_str(&format!("\nnote: {}", note.message))
+    }
+
+    // things wrong in grabbed modules show up at the top
+    let elsewhere = diagnostic.file.as_ref().is_some_and(|file| file != path);
+
+    let span = match diagnostic.span {
+        Some(Pos((line, _), (start, end))) if !elsewhere => Span {
+            line: line.max(1),
+            start: start.max(1),
+            end: end.max(start),
+        },
+
+        _ => {
+            if let (true, Some(file)) = (elsewhere, diagnostic.file.as_ref()) {
+                message = format!("in {}: {}", file, message)
+            }
+
+            Span { line: 1, start: 1, end: 0 }
+        }
+    };
+
+    let severity = match diagnostic.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+        Severity::Note => 3,
+    };
+
+    json!({
+        "range": analysis.range(&span, encoding),
+        "severity": severity,
+        "source": "hugorm",
+        "message": message,
+    })
+}
+
+fn definition(analysis: &Analysis, uri: &str, line: usize, column: usize, encoding: Encoding) -> Value {
+    match analysis.index.at(line, column) {
+        Some(found) => json!({
+            "uri": uri,
+            "range": analysis.range(&analysis.index.definitions[found].span, encoding),
+        }),
+
+        None => Value::Null,
+    }
+}
+
+fn signature(analysis: &Analysis, definition: &Definition) -> String {
+    // only globals are still around in the symtab afterwards
+    let typed = |keyword: &str| match analysis.symtab.stack[0].get(&definition.name) {
+        Some(t) if definition.scope == Some(0) => format!("{} {}: {}", keyword, definition.name, t.node),
+        _ => format!("{} {}", keyword, definition.name),
+    };
+
+    match definition.kind {
+        Kind::Function(ref params) => format!("fun {}({})", definition.name, params.join(", ")),
+        Kind::Method(ref interface, ref params) => format!("fun {}({})\n# from interface {}", definition.name, params.join(", "), interface),
+        Kind::Interface(ref methods) => format!("interface {}: {}", definition.name, methods.join(", ")),
+        Kind::Variable => typed("let"),
+        Kind::Constant => typed("const"),
+        Kind::Parameter => format!("param {}", definition.name),
+    }
+}
+
+fn hover(analysis: &Analysis, line: usize, column: usize) -> Value {
+    let text = match analysis.index.at(line, column) {
+        Some(found) => signature(analysis, &analysis.index.definitions[found]),
+
+        // natives and whatever came from grabbed modules
+        None => match analysis.index.name_at(line, column).and_then(|name| analysis.symtab.fetch_str(name).map(|t| (name, t))) {
+            Some((name, t)) => match t.node {
+                TypeNode::Func(params) if t.meta.is_none() => format!("builtin {}/{}", name, params),
+                ref node => format!("{}: {}", name, node),
+            },
+
+            None => return Value::Null,
+        },
+    };
+
+    json!({
+        "contents": { "kind": "markdown", "value": format!("```hugorm\n{}\n```", text) },
+    })
+}
+
+fn completion(analysis: &Analysis, line: usize, column: usize) -> Value {
+    const KEYWORD: u8 = 14;
+    const FUNCTION: u8 = 3;
+    const VARIABLE: u8 = 6;
+    const CONSTANT: u8 = 21;
+    const METHOD: u8 = 2;
+    const INTERFACE: u8 = 8;
+
+    let text = analysis.lines.get(line - 1).map(|line| line.chars().take(column - 1).collect::<Vec<char>>()).unwrap_or_default();
+
+    let mut start = text.len();
+
+    while start > 0 && is_name_char(text[start - 1]) {
+        start -= 1
+    }
+
+    // sorted and without duplicates
+    let mut items = BTreeMap::new();
+
+    if start > 0 && text[start - 1] == '.' {
+        for method in analysis.index.methods() {
+            items.insert(method.name.clone(), METHOD);
+        }
+
+        for frame in analysis.symtab.stack.iter() {
+            for interface in frame.interfaces.values() {
+                for name in interface.methods() {
+                    items.insert(name, METHOD);
+                }
+            }
+        }
+    } else {
+        for keyword in KEYWORDS.iter().chain(["true", "false", "and", "or", "not"].iter()) {
+            items.insert(keyword.to_string(), KEYWORD);
+        }
+
+        for (name, t) in analysis.symtab.stack[0].table.borrow().iter() {
+            if !name.starts_with('$') {
+                let kind = if let TypeNode::Func(_) = t.node { FUNCTION } else { VARIABLE };
+                items.insert(name.clone(), kind);
+            }
+        }
+
+        for definition in analysis.index.visible(line) {
+            let kind = match definition.kind {
+                Kind::Function(_) | Kind::Method(..) => FUNCTION,
+                Kind::Interface(_) => INTERFACE,
+                Kind::Constant => CONSTANT,
+                Kind::Variable | Kind::Parameter => VARIABLE,
+            };
+
+            items.insert(definition.name.clone(), kind);
+        }
+    }
+
+    Value::Array(items.into_iter().map(|(label, kind)| json!({ "label": label, "kind": kind })).collect())
+}
+
+fn document_symbols(analysis: &Analysis, encoding: Encoding) -> Value {
+    let definitions = &analysis.index.definitions;
+
+    fn symbol(analysis: &Analysis, at: usize, encoding: Encoding) -> Option<Value> {
+        let definition = &analysis.index.definitions[at];
+
+        let kind = match definition.kind {
+            Kind::Function(_) => 12,
+            Kind::Method(..) => 6,
+            Kind::Interface(_) => 11,
+            _ => return None,
+        };
+
+        let last = analysis.lines.get(definition.last_line - 1).map(|line| line.chars().count()).unwrap_or(0);
+        let last = analysis.character(definition.last_line, last + 1, encoding);
+
+        let children = (0..analysis.index.definitions.len())
+            .filter(|&child| analysis.index.definitions[child].parent == Some(at))
+            .filter_map(|child| symbol(analysis, child, encoding))
+            .collect::<Vec<Value>>();
+
+        Some(json!({
+            "name": definition.name,
+            "kind": kind,
+            "range": {
+                "start": { "line": definition.span.line - 1, "character": 0 },
+                "end": { "line": definition.last_line - 1, "character": last },
+            },
+            "selectionRange": analysis.range(&definition.span, encoding),
+            "children": children,
+        }))
+    }
+
+    Value::Array(
+        (0..definitions.len())
+            .filter(|&at| definitions[at].parent.is_none())
+            .filter_map(|at| symbol(analysis, at, encoding))
+            .collect()
+    )
+}
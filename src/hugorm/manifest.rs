@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use toml::{Spanned, Value};
+
+use super::error::Response::*;
+use super::error::Diagnostic;
+use super::lexer::{is_name_char, Pos};
+use super::source::FilePath;
+
+// `hugorm.toml`, which makes a folder a project:
+//
+//     [package]
+//     name = "shapes"
+//     version = "0.1.0"
+//     entry = "main.hug"    # what `hugorm run` runs, `main.hug` when left out
+//
+//     [dependencies]
+//     helpers = "../helpers"                # a folder somewhere
+//     geometry = { path = "../geometry" }   # the same thing, spelled out
+//     strings = { vendored = true }         # `vendor/strings` in this project
+//
+// any TOML goes as far as reading it, `[dependencies.geometry]` with a `path`
+// under it being the same as the inline table
+
+pub const FILE: &str = "hugorm.toml";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    pub root: PathBuf, // the folder it's in
+    pub name: String,
+    pub version: String,
+    pub entry: String,
+    pub dependencies: Vec<Dependency>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dependency {
+    pub name: String,
+    pub path: PathBuf, // the folder it's in, already joined onto the root
+}
+
+impl Manifest {
+    pub fn entry_path(&self) -> PathBuf {
+        self.root.join(&self.entry)
+    }
+
+    pub fn dependency(&self, name: &str) -> Option<&Dependency> {
+        self.dependencies.iter().find(|dependency| dependency.name == name)
+    }
+}
+
+// the closest `hugorm.toml` in `dir` or any folder above it
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    let dir = fs::canonicalize(dir).ok()?;
+
+    dir.ancestors().map(|dir| dir.join(FILE)).find(|path| path.is_file())
+}
+
+pub fn load(path: &Path) -> Result<Manifest, Diagnostic> {
+    let file = FilePath(path.display().to_string());
+
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(why) => return Err(response!(Wrong(format!("can't read manifest: {}", why)), file)),
+    };
+
+    let root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    let sections = match toml::from_str::<BTreeMap<Spanned<String>, Section>>(&content) {
+        Ok(sections) => sections,
+
+        Err(why) => {
+            // the position goes into the diagnostic instead
+            let message = why.to_string();
+            let message = message.split(" at line ").next().unwrap_or_default().to_string();
+
+            return Err(match why.line_col() {
+                Some((line, _)) => response!(Wrong(message), file, line_pos(&content, line + 1)),
+                None => response!(Wrong(message), file),
+            })
+        }
+    };
+
+    let mut name = None;
+    let mut version = None;
+    let mut entry = None;
+    let mut dependencies = Vec::new();
+
+    for (section, fields) in sections.iter() {
+        let wrong = |message: String, key: &Spanned<String>| response!(Wrong(message), file, key_pos(&content, key));
+
+        match section.get_ref().as_str() {
+            "package" => for (key, value) in fields.iter() {
+                let slot = match key.get_ref().as_str() {
+                    "name" => &mut name,
+                    "version" => &mut version,
+                    "entry" => &mut entry,
+                    other => return Err(wrong(format!("`[package]` doesn't take `{}`", other), key)),
+                };
+
+                match value.get_ref() {
+                    Value::String(value) => *slot = Some(value.clone()),
+                    _ => return Err(wrong(format!("`{}` should be a string", key.get_ref()), key)),
+                }
+            },
+
+            "dependencies" => {
+                // in the order they're written down
+                let mut fields = fields.iter().collect::<Vec<_>>();
+                fields.sort_by_key(|(key, _)| key.start());
+
+                for (key, value) in fields {
+                    let name = key.get_ref();
+
+                    if name.is_empty() || name.starts_with(|c: char| c.is_numeric() || c == '-') || !name.chars().all(is_name_char) {
+                        return Err(wrong(format!("`{}` can't be grabbed, dependencies need plain names", name), key))
+                    }
+
+                    let dir = match value.get_ref() {
+                        Value::String(path) => root.join(path),
+
+                        Value::Table(fields) => match (fields.len(), fields.get("path"), fields.get("vendored")) {
+                            (1, Some(Value::String(path)), _) => root.join(path),
+                            (1, _, Some(Value::Boolean(true))) => root.join("vendor").join(name),
+                            _ => return Err(wrong(format!("`{}` needs either a `path` or `vendored = true`", name), key)),
+                        },
+
+                        _ => return Err(wrong(format!("`{}` should be a path", name), key)),
+                    };
+
+                    if !dir.is_dir() {
+                        return Err(wrong(format!("there's no folder for `{}` at `{}`", name, dir.display()), key))
+                    }
+
+                    dependencies.push(Dependency { name: name.clone(), path: dir })
+                }
+            },
+
+            other => return Err(wrong(format!("no section called `{}`, only `package` and `dependencies`", other), section)),
+        }
+    }
+
+    let name = match name {
+        Some(name) => name,
+        None => return Err(response!(Wrong("the manifest needs a `name` under `[package]`"), file)),
+    };
+
+    let version = match version {
+        Some(version) => version,
+        None => return Err(response!(Wrong("the manifest needs a `version` under `[package]`"), file)),
+    };
+
+    Ok(Manifest {
+        root,
+        name,
+        version,
+        entry: entry.unwrap_or_else(|| "main.hug".to_string()),
+        dependencies,
+    })
+}
+
+// the keys of a section, which know where they are for the errors to point at
+type Section = BTreeMap<Spanned<String>, Spanned<Value>>;
+
+// the whole line the key is on
+fn key_pos(content: &str, key: &Spanned<String>) -> Pos {
+    line_pos(content, content[.. key.start()].matches('\n').count() + 1)
+}
+
+fn line_pos(content: &str, line: usize) -> Pos {
+    let text = content.lines().nth(line - 1).unwrap_or_default();
+
+    Pos((line, text.to_string()), (1, text.len().max(1)))
+}
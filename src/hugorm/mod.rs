@@ -4,4 +4,12 @@ pub mod source;
 pub mod lexer;
 pub mod parser;
 pub mod visitor;
-pub mod prelude;
\ No newline at end of file
+pub mod prelude;
+pub mod engine;
+pub mod testing;
+pub mod lsp;
+pub mod formatter;
+pub mod repl;
+pub mod cli;
+pub mod runtime;
+pub mod manifest;
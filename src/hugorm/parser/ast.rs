@@ -9,14 +9,16 @@ pub enum StatementNode {
   Declaration(String, Option<Expression>),
   Const(String, Expression),
   ConstFunction(Rc<Statement>),
+  Test(Rc<Statement>), // `test fun`, for `hugorm test`
   Assignment(Expression, Expression),
   Function(String, Vec<String>, Vec<Statement>),
   Return(Option<Expression>),
   Interface(String, Vec<Statement>),
   If(Expression, Vec<Statement>, Vec<(Option<Expression>, Vec<Statement>)>),
   While(Expression, Vec<Statement>),
+  For(String, Expression, Vec<Statement>),
   Block(Vec<Statement>),
-  Use(String),
+  Use(String, Option<String>, Vec<String>), // module, `as` name, names picked out with `from`
   Break,
 }
 
@@ -40,7 +42,7 @@ impl Statement {
 #[derive(Debug, Clone, PartialEq)]
 pub enum ExpressionNode {
   Nil,
-  Int(i32),
+  Int(i64),
   Float(f64),
   Str(String),
   Identifier(String),
@@ -88,6 +90,7 @@ impl Operator {
     ["+", "-", "*", "/", "++", "%", "^", "or", "and"].contains(&operator)
   }
 
+  #[allow(clippy::should_implement_trait)]
   pub fn from_str(operator: &str) -> Option<(Operator, u8)> {
     use self::Operator::*;
 
@@ -1,8 +1,10 @@
 pub mod ast;
+#[allow(clippy::module_inception)]
 pub mod parser;
 
 use self::super::lexer::*;
 use self::super::source::Source;
+use self::super::error::Diagnostic;
 
 pub use self::ast::*;
 pub use self::parser::*;
\ No newline at end of file
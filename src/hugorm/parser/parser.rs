@@ -11,7 +11,9 @@ pub struct Parser<'p> {
     indent_standard: usize,
     indent: usize,
 
-    min_prec: usize,
+    inline: bool, // parsing the one-line body of an anonymous function
+
+    errors: Vec<Diagnostic>, // the ones we recovered from
 }
 
 impl<'p> Parser<'p> {
@@ -24,21 +26,71 @@ impl<'p> Parser<'p> {
             indent_standard: 0,
             indent: 0,
 
-            min_prec: 0
+            inline: false,
+
+            errors: Vec::new(),
         }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Statement>, ()> {
+    pub fn parse(&mut self) -> Result<Vec<Statement>, Diagnostic> {
+        let (ast, mut errors) = self.parse_partial();
+
+        if errors.is_empty() {
+            Ok(ast)
+        } else {
+            Err(errors.remove(0))
+        }
+    }
+
+    // keeps going after errors, giving back whatever made sense
+    pub fn parse_partial(&mut self) -> (Vec<Statement>, Vec<Diagnostic>) {
         let mut ast = Vec::new();
 
         while self.remaining() > 0 {
-            ast.push(self.parse_statement()?)
+            match self.parse_statement() {
+                Ok(statement) => ast.push(statement),
+                Err(error) => {
+                    self.errors.push(error);
+                    self.synchronize()
+                }
+            }
         }
 
-        Ok(ast)
+        (ast, self.errors.drain(..).collect())
     }
 
-    pub fn parse_statement(&mut self) -> Result<Statement, ()> {
+    // panic mode: skip ahead to the next line or something that starts a statement
+    fn synchronize(&mut self) {
+        if self.remaining() > 0 {
+            self.index += 1
+        }
+
+        while self.remaining() > 0 {
+            if self.current_lexeme() == "\n" {
+                self.index += 1;
+                return
+            }
+
+            if self.current_type() == TokenType::Keyword {
+                match self.current_lexeme().as_str() {
+                    "let" | "const" | "if" | "while" | "for" | "loop" | "interface" | "return" | "break" | "grab" => return,
+
+                    // anonymous ones are expressions
+                    "fun" => if let Some(token) = self.tokens.get(self.index + 1) {
+                        if token.token_type == TokenType::Identifier {
+                            return
+                        }
+                    },
+
+                    _ => (),
+                }
+            }
+
+            self.index += 1
+        }
+    }
+
+    pub fn parse_statement(&mut self) -> Result<Statement, Diagnostic> {
         use self::TokenType::*;
 
         while self.current_type() == EOL && self.remaining() != 0 {
@@ -102,11 +154,17 @@ impl<'p> Parser<'p> {
                     self.next()?;
 
                     if self.current_lexeme() == "fun" {
-                        Statement::new(
-                            StatementNode::ConstFunction(
-                                Rc::new(self.parse_statement()?)
-                            ),
-                            self.span_from(position)
+                        let fun = self.parse_statement()?;
+                        let pos = fun.pos.clone();
+
+                        // `fun` already took care of its own body
+                        return Ok(
+                            Statement::new(
+                                StatementNode::ConstFunction(
+                                    Rc::new(fun)
+                                ),
+                                pos
+                            )
                         )
                     } else {
                         let name = self.eat_type(&TokenType::Identifier)?;
@@ -116,9 +174,9 @@ impl<'p> Parser<'p> {
                         let right = self.parse_expression()?;
 
                         Statement::new(
-                            StatementNode::Declaration(
+                            StatementNode::Const(
                                 name,
-                                Some(right)
+                                right
                             ),
                             self.span_from(position)
                         )
@@ -190,7 +248,7 @@ impl<'p> Parser<'p> {
                             continue
                         } else {
                             return Err(response!(
-                                Wrong(format!("can't interface non-function")),
+                                Wrong("can't interface non-function"),
                                 self.source.file,
                                 s.pos
                             ));
@@ -232,6 +290,34 @@ impl<'p> Parser<'p> {
                     )
                 }
 
+                "for" => {
+                    self.next()?;
+
+                    let name = self.eat_type(&TokenType::Identifier)?;
+
+                    self.eat_lexeme("in")?;
+
+                    let iterable = self.parse_expression()?;
+
+                    self.eat_lexeme(":")?;
+
+                    let pos = self.span_from(position);
+
+                    let body = if self.current_lexeme() == "\n" {
+                        self.next()?;
+                        self.parse_body()?
+                    } else {
+                        vec!(self.parse_statement()?)
+                    };
+
+                    return Ok(
+                        Statement::new(
+                            StatementNode::For(name, iterable, body),
+                            pos
+                        )
+                    )
+                }
+
                 "loop" => {
                     self.next()?;
 
@@ -356,13 +442,52 @@ impl<'p> Parser<'p> {
                     )
                 }
 
+                // `grab foo`, `grab foo as f` and `grab a, b from foo`. `as` and `from`
+                // aren't keywords, they only mean something here
                 "grab" => {
                     self.next()?;
 
-                    let import = self.eat_type(&TokenType::Identifier)?;
+                    let mut names = vec!(self.parse_module_path()?);
+
+                    while self.current_lexeme() == "," {
+                        self.next()?;
+                        names.push(self.eat_type(&TokenType::Identifier)?)
+                    }
+
+                    if names.len() > 1 || self.current_lexeme() == "from" {
+                        if let Some(name) = names.iter().find(|name| name.contains('.')) {
+                            return Err(response!(
+                                Wrong(format!("can only grab plain names from a module, not `{}`", name)),
+                                self.source.file,
+                                position
+                            ))
+                        }
+                    }
+
+                    let node = if self.current_lexeme() == "from" {
+                        self.next()?;
+
+                        let module = self.parse_module_path()?;
+
+                        StatementNode::Use(module, None, names)
+                    } else if names.len() > 1 {
+                        return Err(response!(
+                            Wrong("expected `from` after the names to grab"),
+                            self.source.file,
+                            self.current_position()
+                        ))
+                    } else if self.current_lexeme() == "as" {
+                        self.next()?;
+
+                        let alias = self.eat_type(&TokenType::Identifier)?;
+
+                        StatementNode::Use(names.remove(0), Some(alias), Vec::new())
+                    } else {
+                        StatementNode::Use(names.remove(0), None, Vec::new())
+                    };
 
                     Statement::new(
-                        StatementNode::Use(import),
+                        node,
                         self.span_from(position)
                     )
                 }
@@ -519,6 +644,24 @@ impl<'p> Parser<'p> {
                 }
             },
 
+            // `test` is only special right before a `fun`
+            Identifier if self.current_lexeme() == "test" && self.peek_lexeme(1) == Some("fun") => {
+                self.next()?;
+
+                let fun = self.parse_statement()?;
+                let pos = fun.pos.clone();
+
+                // `fun` already took care of its own body
+                return Ok(
+                    Statement::new(
+                        StatementNode::Test(
+                            Rc::new(fun)
+                        ),
+                        pos
+                    )
+                )
+            },
+
             Identifier => {
                 let backup_index = self.index;
                 let position = self.current_position();
@@ -526,6 +669,8 @@ impl<'p> Parser<'p> {
 
                 match self.current_lexeme().as_str() {
                     "=" => {
+                        self.next()?;
+
                         Statement::new(
                             StatementNode::Assignment(
                                 Expression::new(ExpressionNode::Identifier(name), position.clone()),
@@ -535,7 +680,7 @@ impl<'p> Parser<'p> {
                         )
                     }
 
-                    c => {
+                    _ => {
                         let expression = Expression::new(ExpressionNode::Identifier(name), position.clone());
 
                         if let Some(result) = self.try_parse_compound(&expression)? {
@@ -600,12 +745,15 @@ impl<'p> Parser<'p> {
             }
         };
 
-        self.new_line()?;
+        // an inline body ends wherever the expression holding it goes on
+        if !self.inline {
+            self.new_line()?;
+        }
 
         Ok(statement)
     }
 
-    fn try_parse_compound(&mut self, left: &Expression) -> Result<Option<Statement>, ()> {
+    fn try_parse_compound(&mut self, left: &Expression) -> Result<Option<Statement>, Diagnostic> {
         if self.current_type() != TokenType::Operator {
             return Ok(None)
         }
@@ -646,16 +794,19 @@ impl<'p> Parser<'p> {
         Ok(result)
     }
 
-    fn parse_body(&mut self) -> Result<Vec<Statement>, ()> {
+    fn parse_body(&mut self) -> Result<Vec<Statement>, Diagnostic> {
         let backup_indent = self.indent;
+        let backup_inline = self.inline;
+
         self.indent = self.get_indent();
+        self.inline = false;
 
         if self.indent_standard == 0 {
             self.indent_standard = self.indent
         } else {
-            if self.indent % self.indent_standard != 0 {
+            if !self.indent.is_multiple_of(self.indent_standard) {
                 return Err(response!(
-                    Wrong(format!("found inconsistently indented token")),
+                    Wrong("found inconsistently indented token"),
                     self.source.file,
                     self.current_position()
                 ));
@@ -665,19 +816,24 @@ impl<'p> Parser<'p> {
         let mut stack = Vec::new();
 
         while !self.is_dedent() && self.remaining() > 0 {
-            let statement = self.parse_statement()?;
-
-            self.next_newline()?;
+            match self.parse_statement() {
+                Ok(statement) => stack.push(statement),
+                Err(error) => {
+                    self.errors.push(error);
+                    self.synchronize()
+                }
+            }
 
-            stack.push(statement)
+            self.next_newline()?
         }
 
         self.indent = backup_indent;
+        self.inline = backup_inline;
 
         Ok(stack)
     }
 
-    fn parse_expression(&mut self) -> Result<Expression, ()> {
+    fn parse_expression(&mut self) -> Result<Expression, Diagnostic> {
         let atom = self.parse_atom()?;
 
         if self.current_type() == TokenType::Operator {
@@ -687,7 +843,7 @@ impl<'p> Parser<'p> {
         }
     }
 
-    fn parse_atom(&mut self) -> Result<Expression, ()> {
+    fn parse_atom(&mut self) -> Result<Expression, Diagnostic> {
         use self::TokenType::*;
 
         if self.remaining() == 0 {
@@ -700,15 +856,32 @@ impl<'p> Parser<'p> {
             let position = self.current_position();
 
             let expression = match token_type {
-                Int => Expression::new(
-                    ExpressionNode::Int(self.eat()?.parse::<i32>().unwrap()),
-                    position,
-                ),
+                // the lexer already checked these, but a token can come from anywhere
+                Int => {
+                    let lexeme = self.eat()?;
+
+                    match lexeme.parse::<i64>() {
+                        Ok(n) => Expression::new(ExpressionNode::Int(n), position),
+                        Err(_) => return Err(response!(
+                            Wrong(format!("invalid int literal `{}`", lexeme)),
+                            self.source.file,
+                            position
+                        )),
+                    }
+                },
 
-                Float => Expression::new(
-                    ExpressionNode::Float(self.eat()?.parse::<f64>().unwrap()),
-                    position,
-                ),
+                Float => {
+                    let lexeme = self.eat()?;
+
+                    match lexeme.parse::<f64>() {
+                        Ok(n) => Expression::new(ExpressionNode::Float(n), position),
+                        Err(_) => return Err(response!(
+                            Wrong(format!("invalid float literal `{}`", lexeme)),
+                            self.source.file,
+                            position
+                        )),
+                    }
+                },
 
                 Str => Expression::new(ExpressionNode::Str(self.eat()?), position),
 
@@ -763,16 +936,12 @@ impl<'p> Parser<'p> {
                         }
                     }
 
-                    "[" => {
-                        let expr = Expression::new(
-                            ExpressionNode::Array(
-                                self.parse_block_of(("[", "]"), &Self::_parse_expression_comma)?,
-                            ),
-                            self.span_from(position),
-                        );
-
-                        expr
-                    },
+                    "[" => Expression::new(
+                        ExpressionNode::Array(
+                            self.parse_block_of(("[", "]"), &Self::_parse_expression_comma)?,
+                        ),
+                        self.span_from(position),
+                    ),
 
                     "{" => {
                         let args =
@@ -827,9 +996,25 @@ impl<'p> Parser<'p> {
     
                         let body = if self.current_lexeme() == "\n" {
                             self.next()?;
-                            self.parse_body()?
+
+                            let body = self.parse_body()?;
+
+                            // the body ate the newline ending it, but whatever holds
+                            // the function (a `return`, a dict, a call) still wants it
+                            if self.index > 0 && self.tokens[self.index - 1].lexeme == "\n" {
+                                self.index -= 1
+                            }
+
+                            body
                         } else {
-                            vec!(self.parse_statement()?)
+                            let backup_inline = self.inline;
+                            self.inline = true;
+
+                            let statement = self.parse_statement();
+
+                            self.inline = backup_inline;
+
+                            vec!(statement?)
                         };
     
                         return Ok(
@@ -868,7 +1053,7 @@ impl<'p> Parser<'p> {
         }
     }
 
-    fn parse_postfix(&mut self, expression: Expression) -> Result<Expression, ()> {
+    fn parse_postfix(&mut self, expression: Expression) -> Result<Expression, Diagnostic> {
         let backup_index = self.index;
 
         if self.remaining() == 0 {
@@ -883,9 +1068,15 @@ impl<'p> Parser<'p> {
 
                     let mut args = Vec::new();
 
-                    if ![TokenType::Operator, TokenType::Keyword].contains(&self.current_type())
-                    {
-                        while !["\n", ")"].contains(&self.current_lexeme().as_str()) {
+                    let has_args = match self.current_type() {
+                        TokenType::Operator => false,
+                        TokenType::Keyword => ["fun", "nil"].contains(&self.current_lexeme().as_str()),
+                        _ => true,
+                    };
+
+                    if has_args {
+                        // a call left open at the very end, like while it's being typed
+                        while !["\n", ")"].contains(&self.current_lexeme().as_str()) && self.remaining() > 0 {
                             args.push(self.parse_expression()?);
 
                             if !["\n", ")"].contains(&self.current_lexeme().as_str())
@@ -957,7 +1148,7 @@ impl<'p> Parser<'p> {
 
                     let pos = expression.pos.clone();
 
-                    return Ok(Expression::new(
+                    Ok(Expression::new(
                         ExpressionNode::With(Rc::new(expression), Rc::new(with)),
                         self.span_from(pos)
                     ))
@@ -973,7 +1164,7 @@ impl<'p> Parser<'p> {
         }
     }
 
-    fn parse_binary(&mut self, left: Expression, min_prec: usize) -> Result<Expression, ()> {
+    fn parse_binary(&mut self, left: Expression, min_prec: usize) -> Result<Expression, Diagnostic> {
         let mut left = left;
         let left_position = left.pos.clone();
 
@@ -1008,7 +1199,7 @@ impl<'p> Parser<'p> {
         Ok(left)
     }
 
-    fn new_line(&mut self) -> Result<(), ()> {
+    fn new_line(&mut self) -> Result<(), Diagnostic> {
         if self.remaining() > 0 {
             match self.current_lexeme().as_str() {
                 "\n" => self.next(),
@@ -1028,7 +1219,7 @@ impl<'p> Parser<'p> {
         }
     }
 
-    fn next_newline(&mut self) -> Result<(), ()> {
+    fn next_newline(&mut self) -> Result<(), Diagnostic> {
         while self.current_lexeme() == "\n" && self.remaining() > 0 {
             self.next()?
         }
@@ -1044,7 +1235,7 @@ impl<'p> Parser<'p> {
         self.get_indent() < self.indent && self.current_lexeme() != "\n"
     }
 
-    fn next(&mut self) -> Result<(), ()> {
+    fn next(&mut self) -> Result<(), Diagnostic> {
         if self.index <= self.tokens.len() {
             self.index += 1;
 
@@ -1093,19 +1284,25 @@ impl<'p> Parser<'p> {
         }
     }
 
-    fn eat(&mut self) -> Result<String, ()> {
+    fn eat(&mut self) -> Result<String, Diagnostic> {
         let lexeme = self.current().lexeme;
         self.next()?;
 
         Ok(lexeme)
     }
 
-    fn eat_lexeme(&mut self, lexeme: &str) -> Result<String, ()> {
+    fn eat_lexeme(&mut self, lexeme: &str) -> Result<String, Diagnostic> {
         if self.current_lexeme() == lexeme {
             let lexeme = self.current().lexeme;
             self.next()?;
 
             Ok(lexeme)
+        } else if self.remaining() == 0 {
+            Err(response!(
+                Wrong(format!("expected `{}` but the file ended", lexeme)),
+                self.source.file,
+                self.current_position()
+            ))
         } else {
             Err(response!(
                 Wrong(format!(
@@ -1119,7 +1316,21 @@ impl<'p> Parser<'p> {
         }
     }
 
-    fn eat_type(&mut self, token_type: &TokenType) -> Result<String, ()> {
+    // `a.b.c`, which lives in `a/b/c.hug`
+    fn parse_module_path(&mut self) -> Result<String, Diagnostic> {
+        let mut path = self.eat_type(&TokenType::Identifier)?;
+
+        while self.current_lexeme() == "." {
+            self.next()?;
+
+            path.push('.');
+            path.push_str(&self.eat_type(&TokenType::Identifier)?)
+        }
+
+        Ok(path)
+    }
+
+    fn eat_type(&mut self, token_type: &TokenType) -> Result<String, Diagnostic> {
         if self.current_type() == *token_type {
             let lexeme = self.current().lexeme.clone();
             self.next()?;
@@ -1138,6 +1349,10 @@ impl<'p> Parser<'p> {
         }
     }
 
+    fn peek_lexeme(&self, offset: usize) -> Option<&str> {
+        self.tokens.get(self.index + offset).map(|token| token.lexeme.as_str())
+    }
+
     fn current_lexeme(&self) -> String {
         self.current().lexeme.clone()
     }
@@ -1146,39 +1361,7 @@ impl<'p> Parser<'p> {
         self.current().token_type
     }
 
-    fn expect_type(&self, token_type: TokenType) -> Result<(), ()> {
-        if self.current_type() == token_type {
-            Ok(())
-        } else {
-            Err(response!(
-                Wrong(format!(
-                    "expected `{}` but found `{}`",
-                    token_type,
-                    self.current_type()
-                )),
-                self.source.file
-            ))
-        }
-    }
-
-    fn expect_lexeme(&self, lexeme: &str) -> Result<(), ()> {
-        if self.current_lexeme() == lexeme {
-            Ok(())
-        } else {
-            Err(response!(
-                Wrong(format!(
-                    "expected `{}` but found `{}`",
-                    lexeme,
-                    self.current_lexeme()
-                )),
-                self.source.file
-            ))
-        }
-    }
-
-
-
-    fn _parse_statement(self: &mut Self) -> Result<Option<Statement>, ()> {
+    fn _parse_statement(&mut self) -> Result<Option<Statement>, Diagnostic> {
         if self.remaining() > 0 {
             Ok(Some(self.parse_statement()?))
         } else {
@@ -1186,7 +1369,7 @@ impl<'p> Parser<'p> {
         }
     }
 
-    fn _parse_expression(self: &mut Self) -> Result<Option<Expression>, ()> {
+    fn _parse_expression(&mut self) -> Result<Option<Expression>, Diagnostic> {
         let expression = self.parse_expression()?;
 
         match expression.node {
@@ -1195,7 +1378,7 @@ impl<'p> Parser<'p> {
         }
     }
 
-    fn _parse_expression_comma(self: &mut Self) -> Result<Option<Expression>, ()> {
+    fn _parse_expression_comma(&mut self) -> Result<Option<Expression>, Diagnostic> {
         if self.remaining() > 0 && self.current_lexeme() == "\n" {
             self.next()?
         }
@@ -1217,7 +1400,7 @@ impl<'p> Parser<'p> {
         expression
     }
 
-    fn _parse_definition_comma(self: &mut Self) -> Result<Option<(String, Expression)>, ()> {
+    fn _parse_definition_comma(&mut self) -> Result<Option<(String, Expression)>, Diagnostic> {
         if self.remaining() > 0 && self.current_lexeme() == "\n" {
             self.next()?
         }
@@ -1265,8 +1448,8 @@ impl<'p> Parser<'p> {
     fn parse_block_of<B>(
         &mut self,
         delimeters: (&str, &str),
-        parse_with: &dyn Fn(&mut Self) -> Result<Option<B>, ()>,
-    ) -> Result<Vec<B>, ()> {
+        parse_with: &dyn Fn(&mut Self) -> Result<Option<B>, Diagnostic>,
+    ) -> Result<Vec<B>, Diagnostic> {
         self.eat_lexeme(delimeters.0)?;
 
         if self.current_lexeme() == delimeters.1 {
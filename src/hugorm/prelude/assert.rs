@@ -0,0 +1,126 @@
+use std::panic::{self, AssertUnwindSafe};
+
+use zub::vm::*;
+
+use super::super::engine::{Engine, Native, Ran};
+use super::super::runtime::{stop, RuntimeError};
+use super::super::visitor::*;
+
+// the visitor hands these their own position as a hidden last argument,
+// `file:line:start:end` so a failure in a grabbed helper points into it
+pub const POSITIONED: &[&str] = &["assert", "assert-eq", "assert-error"];
+
+pub const NATIVES: &[(&str, u8, Native)] = &[
+    ("assert", 1, assert),
+    ("assert-eq", 2, assert_eq),
+    ("assert-error", 1, assert_error),
+];
+
+// what a failed assertion unwinds with, `hugorm test` catches it
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub message: String,
+    pub file: String,
+    pub line: usize,
+    pub slice: (usize, usize),
+}
+
+// only test runs get these
+pub fn include_assert(engine: &mut Engine) {
+    for &(name, arity, function) in NATIVES.iter() {
+        engine.symtab.assign_str(name, Type::from(TypeNode::Func(arity as usize)));
+        engine.add_native(name, arity + 1, function)
+    }
+}
+
+fn fail(heap: &Heap<Object>, position: &Value, message: String) -> ! {
+    let position = format!("{}", position.with_heap(heap));
+
+    // the file name can have colons of its own, so it's split from the back
+    let mut parts = position.rsplitn(4, ':');
+    let mut number = || parts.next().and_then(|part| part.parse::<usize>().ok()).unwrap_or(0);
+
+    let (end, start, line) = (number(), number(), number());
+
+    stop(Failure {
+        message,
+        file: parts.next().unwrap_or_default().to_string(),
+        line,
+        slice: (start, end),
+    })
+}
+
+fn equal(heap: &Heap<Object>, a: &Value, b: &Value) -> bool {
+    match (a.decode(), b.decode()) {
+        (Variant::Float(a), Variant::Float(b)) => a == b,
+        (Variant::True, Variant::True) | (Variant::False, Variant::False) | (Variant::Nil, Variant::Nil) => true,
+
+        (Variant::Obj(x), Variant::Obj(y)) => {
+            match unsafe { (heap.get_unchecked(x), heap.get_unchecked(y)) } {
+                (Object::String(ref a), Object::String(ref b)) => a == b,
+
+                (Object::List(ref a), Object::List(ref b)) => {
+                    a.content.len() == b.content.len()
+                        && a.content.iter().zip(b.content.iter()).all(|(a, b)| equal(heap, a, b))
+                }
+
+                (Object::Dict(ref a), Object::Dict(ref b)) => {
+                    a.content.len() == b.content.len()
+                        && a.content.iter().all(|(key, a)| b.get(key).is_some_and(|b| equal(heap, a, b)))
+                }
+
+                _ => x == y,
+            }
+        }
+
+        _ => false,
+    }
+}
+
+fn assert(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    if !args[1].truthy() {
+        fail(heap, &args[2], format!("assertion failed, got `{}`", args[1].with_heap(heap)))
+    }
+
+    Ok(Value::nil())
+}
+
+// `assert-eq(actual, expected)`
+fn assert_eq(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    if !equal(heap, &args[1], &args[2]) {
+        fail(heap, &args[3], format!(
+            "expected `{}`, found `{}`",
+            args[2].with_heap(heap),
+            args[1].with_heap(heap)
+        ))
+    }
+
+    Ok(Value::nil())
+}
+
+// `assert-error(code)` passes when the code fails to compile or blows up running
+fn assert_error(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let code = match args[1].decode() {
+        Variant::Obj(handle) => unsafe { heap.get_unchecked(handle) }.as_string().cloned(),
+        _ => None,
+    };
+
+    let code = match code {
+        Some(code) => code,
+        None => fail(heap, &args[2], format!("`assert-error` takes code as a string, not `{}`", args[1].with_heap(heap))),
+    };
+
+    let ran = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut engine = Engine::new();
+        include_assert(&mut engine);
+
+        // stopping with `exit` counts as failing
+        matches!(engine.eval_str("<assert-error>", &code), Ok(Ran { exit: None, .. }))
+    }));
+
+    if let Ok(true) = ran {
+        fail(heap, &args[2], format!("expected `{}` to fail, but it didn't", code))
+    }
+
+    Ok(Value::nil())
+}
@@ -0,0 +1,136 @@
+use std::cell::RefCell;
+use std::io::{self, BufRead, Write};
+
+use zub::vm::*;
+
+use super::super::engine::{Engine, Native};
+use super::super::runtime::{raise, stop, RuntimeError};
+use super::string::new_string;
+
+// natives whose last argument can be left out, the visitor passes nil for it then
+pub const OPTIONAL: &[&str] = &["prompt"];
+
+pub const NATIVES: &[(&str, u8, Native)] = &[
+    ("print", 1, print),
+    ("len", 1, len),
+    ("input", 0, input),
+    ("prompt", 1, prompt),
+    ("exit", 1, exit),
+    ("env", 1, env),
+];
+
+// what `exit` unwinds with, whoever is running the program decides what to do about it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exit(pub i32);
+
+thread_local! {
+    // the writer and reader of the engine running on this thread, lent to the natives
+    // for as long as it runs. `None` is stdout and stdin
+    static OUTPUT: RefCell<Option<Box<dyn Write>>> = RefCell::new(None);
+    static INPUT: RefCell<Option<Box<dyn BufRead>>> = RefCell::new(None);
+}
+
+// lends `output` and `input` out while `f` runs and takes them back afterwards, so
+// whatever was lent out before is back in place once it's done
+pub fn lend_io<T, F: FnOnce() -> T>(output: &mut Option<Box<dyn Write>>, input: &mut Option<Box<dyn BufRead>>, f: F) -> T {
+    let outer_output = OUTPUT.with(|current| current.replace(output.take()));
+    let outer_input = INPUT.with(|current| current.replace(input.take()));
+
+    let result = f();
+
+    *output = OUTPUT.with(|current| current.replace(outer_output));
+    *input = INPUT.with(|current| current.replace(outer_input));
+
+    result
+}
+
+pub fn write_output(text: &str) {
+    OUTPUT.with(|output| {
+        let _ = match *output.borrow_mut() {
+            Some(ref mut output) => output.write_all(text.as_bytes()).and_then(|_| output.flush()),
+            None => {
+                let mut stdout = io::stdout();
+                stdout.write_all(text.as_bytes()).and_then(|_| stdout.flush())
+            }
+        };
+    })
+}
+
+// a line without its newline, `None` at the end
+fn read_line() -> Option<String> {
+    let mut line = String::new();
+
+    let read = INPUT.with(|input| match *input.borrow_mut() {
+        Some(ref mut input) => input.read_line(&mut line),
+        None => io::stdin().lock().read_line(&mut line),
+    });
+
+    match read {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim_end_matches(['\n', '\r']).to_string()),
+    }
+}
+
+// the things every program gets
+pub fn include_base(engine: &mut Engine) {
+    for &(name, arity, function) in NATIVES.iter() {
+        engine.register_native(name, arity, function)
+    }
+}
+
+fn print(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    write_output(&format!("{}\n", args[1].with_heap(heap)));
+    Ok(Value::nil())
+}
+
+// nil once there's nothing left to read
+fn input(heap: &mut Heap<Object>, _args: &[Value]) -> Result<Value, RuntimeError> {
+    match read_line() {
+        Some(line) => Ok(new_string(heap, line)),
+        None => Ok(Value::nil()),
+    }
+}
+
+// `input` after showing what's asked, or `> ` when nothing is
+fn prompt(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    if args[1] == Value::nil() {
+        write_output("> ")
+    } else {
+        write_output(&format!("{}", args[1].with_heap(heap)))
+    }
+
+    input(heap, args)
+}
+
+// chars of a string, items of a list, entries of a dict
+fn len(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    if let Variant::Obj(handle) = args[1].decode() {
+        Ok(match unsafe { heap.get_unchecked(handle) } {
+            Object::List(ref list) => Value::float(list.content.len() as f64),
+            Object::Dict(ref dict) => Value::float(dict.content.len() as f64),
+            Object::String(ref s) => Value::float(s.chars().count() as f64),
+            _ => Value::nil(),
+        })
+    } else {
+        Ok(Value::nil())
+    }
+}
+
+// exit codes are a byte, anything else would be cut down to one by the OS
+fn exit(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    match args[1].decode() {
+        Variant::Float(code) if code.fract() == 0.0 && (0.0 ..= 255.0).contains(&code) => stop(Exit(code as i32)),
+        Variant::Float(_) => raise(format!("can't exit with {}, exit codes are whole numbers from 0 to 255", args[1].with_heap(heap))),
+        _ => raise("exit wants a number"),
+    }
+}
+
+// nil when it isn't set
+fn env(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let name = format!("{}", args[1].with_heap(heap));
+
+    match std::env::var(name) {
+        Ok(value) => Ok(new_string(heap, value)),
+        Err(_) => Ok(Value::nil()),
+    }
+}
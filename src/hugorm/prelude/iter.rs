@@ -0,0 +1,89 @@
+use std::cmp::Ordering;
+
+use zub::vm::*;
+
+use super::super::engine::Engine;
+use super::super::runtime::{raise, RuntimeError};
+
+// the natives behind `for x in y:`, hidden from programs by their `$` names
+pub fn include_iter(engine: &mut Engine) {
+    engine.add_native("$iter", 1, iter);
+    engine.add_native("$iterator?", 1, is_iterator);
+}
+
+fn has_key(dict: &Dict, key: &str) -> bool {
+    dict.get(&HashValue { variant: HashVariant::Str(key.to_string()) }).is_some()
+}
+
+fn is_custom(dict: &Dict) -> bool {
+    has_key(dict, "has-next") && has_key(dict, "next")
+}
+
+// dicts don't keep their order, so keys come out sorted by what they are:
+// numbers by value, then strings, then booleans and nil last
+fn compare_keys(a: &HashVariant, b: &HashVariant) -> Ordering {
+    // number keys are the bits of their float
+    let rank = |key: &HashVariant| match *key {
+        HashVariant::Int(_) => 0,
+        HashVariant::Str(_) => 1,
+        HashVariant::Bool(_) => 2,
+        HashVariant::Nil => 3,
+    };
+
+    match (a, b) {
+        (HashVariant::Int(a), HashVariant::Int(b)) => f64::from_bits(*a as u64).total_cmp(&f64::from_bits(*b as u64)),
+        (HashVariant::Str(a), HashVariant::Str(b)) => a.cmp(b),
+        (HashVariant::Bool(a), HashVariant::Bool(b)) => a.cmp(b),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn iter(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    if let Variant::Obj(handle) = args[1].decode() {
+        let content = match unsafe { heap.get_unchecked(handle) } {
+            Object::List(_) => return Ok(args[1]),
+
+            Object::Dict(ref dict) => {
+                if is_custom(dict) {
+                    return Ok(args[1])
+                }
+
+                let mut keys = dict.content.keys().map(|key| key.variant.clone()).collect::<Vec<HashVariant>>();
+
+                keys.sort_by(compare_keys);
+
+                keys.into_iter()
+                    .map(|key| match key {
+                        HashVariant::Str(s) => Value::object(heap.insert_temp(Object::String(s))),
+                        HashVariant::Int(bits) => Value::float(f64::from_bits(bits as u64)),
+                        HashVariant::Bool(b) => b.into(),
+                        HashVariant::Nil => Value::nil(),
+                    })
+                    .collect::<Vec<Value>>()
+            }
+
+            Object::String(ref s) => s.chars()
+                .map(|c| c.to_string())
+                .collect::<Vec<String>>()
+                .into_iter()
+                .map(|c| Value::object(heap.insert_temp(Object::String(c))))
+                .collect::<Vec<Value>>(),
+
+            _ => return raise(format!("can't iterate over `{}`", args[1].with_heap(heap)))
+        };
+
+        return Ok(Value::object(heap.insert_temp(Object::List(List::new(content)))))
+    }
+
+    raise(format!("can't iterate over `{}`", args[1].with_heap(heap)))
+}
+
+fn is_iterator(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    if let Variant::Obj(handle) = args[1].decode() {
+        if let Object::Dict(ref dict) = unsafe { heap.get_unchecked(handle) } {
+            return Ok(is_custom(dict).into())
+        }
+    }
+
+    Ok(false.into())
+}
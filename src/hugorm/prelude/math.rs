@@ -1,19 +1,25 @@
-use super::visitor::*;
-use zub::ir::*;
 use zub::vm::*;
 
+use super::super::engine::{Engine, Native};
+use super::super::runtime::{raise, RuntimeError};
+use super::base::write_output;
+
 use statrs::distribution::StudentsT;
 use statrs::statistics::*;
 
-pub fn include_math(visitor: &mut Visitor, vm: &mut VM) {
-    visitor.set_global("sum", TypeNode::Func(1));
-    vm.add_native("sum", sum, 1);
+pub const NATIVES: &[(&str, u8, Native)] = &[
+    ("sum", 1, sum),
+    ("student", 3, student),
+];
 
-    visitor.set_global("student", TypeNode::Func(3));
-    vm.add_native("student", student, 3);
+// not in `Engine::new`, ask for it with `@math`
+pub fn include_math(engine: &mut Engine) {
+    for &(name, arity, function) in NATIVES.iter() {
+        engine.register_native(name, arity, function)
+    }
 }
 
-fn sum(heap: &mut Heap<Object>, args: &[Value]) -> Value {
+fn sum(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
     if let Variant::Obj(handle) = args[1].decode() {
         let list = unsafe { heap.get_unchecked(handle) };
 
@@ -24,35 +30,33 @@ fn sum(heap: &mut Heap<Object>, args: &[Value]) -> Value {
                 if let Variant::Float(n) = item.decode() {
                     sum += n
                 } else {
-                    panic!("can't sum non-float")
+                    return raise(format!("can't sum `{}`, only numbers", item.with_heap(heap)))
                 }
             }
 
-            return Value::float(sum)
-        } else {
-            panic!("can't sum non-list")
+            return Ok(Value::float(sum))
         }
     }
 
-    panic!("can't sum non-list: {:#?}", args[1])
+    raise(format!("can't sum `{}`, only lists", args[1].with_heap(heap)))
 }
 
-fn student(heap: &mut Heap<Object>, args: &[Value]) -> Value {
+fn student(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
     let floats = args[1..].iter().take(3).map(|x| {
             if let Variant::Float(n) = x.decode() {
-                n
+                Ok(n)
             } else {
-                panic!("student can't take non-float")
+                raise(format!("`student` takes numbers, not `{}`", x.with_heap(heap)))
             }
         })
-        .collect::<Vec<f64>>();
+        .collect::<Result<Vec<f64>, RuntimeError>>()?;
 
-    let t = StudentsT::new(floats[0], floats[1], floats[2]).unwrap();
+    let t = match StudentsT::new(floats[0], floats[1], floats[2]) {
+        Ok(t) => t,
+        Err(why) => return raise(format!("`student` can't work with that: {}", why)),
+    };
 
-    println!("mean:    {}", t.mean());
-    println!("var:     {}", t.variance());
-    println!("std-var: {}", t.std_dev());
-    println!();
+    write_output(&format!("mean:    {}\nvar:     {}\nstd-var: {}\n\n", t.mean(), t.variance(), t.std_dev()));
 
-    Value::nil()
+    Ok(Value::nil())
 }
\ No newline at end of file
@@ -1,3 +1,6 @@
 pub mod math;
-
-use super::visitor;
\ No newline at end of file
+pub mod iter;
+pub mod base;
+pub mod string;
+pub mod num;
+pub mod assert;
@@ -0,0 +1,188 @@
+use zub::vm::*;
+
+use super::super::engine::{Engine, Native};
+use super::super::runtime::{raise, RuntimeError};
+use super::super::lexer::MAX_INT;
+
+pub const NATIVES: &[(&str, u8, Native)] = &[
+    ("int", 1, int),
+    ("float", 1, float),
+];
+
+// what the visitor lowers arithmetic on ints to, hidden by their `$` names
+pub const INT_OPS: &[(&str, u8, Native)] = &[
+    ("$int-add", 2, int_add),
+    ("$int-sub", 2, int_sub),
+    ("$int-mul", 2, int_mul),
+    ("$int-div", 2, int_div),
+    ("$int-mod", 2, int_mod),
+    ("$int-pow", 2, int_pow),
+];
+
+// what the visitor lowers arithmetic to when it can't tell what a side is
+pub const NUM_OPS: &[(&str, u8, Native)] = &[
+    ("$num-add", 2, num_add),
+    ("$num-sub", 2, num_sub),
+    ("$num-mul", 2, num_mul),
+    ("$num-div", 2, num_div),
+    ("$num-mod", 2, num_mod),
+    ("$num-pow", 2, num_pow),
+];
+
+pub fn include_num(engine: &mut Engine) {
+    for &(name, arity, function) in NATIVES.iter() {
+        engine.register_native(name, arity, function)
+    }
+
+    for &(name, arity, function) in INT_OPS.iter().chain(NUM_OPS.iter()) {
+        engine.add_native(name, arity, function)
+    }
+}
+
+fn checked(n: Option<i64>) -> Result<Value, RuntimeError> {
+    match n {
+        Some(n) if n.abs() <= MAX_INT => Ok(Value::float(n as f64)),
+        _ => raise(format!("integer overflow, ints are exact up to {}", MAX_INT)),
+    }
+}
+
+// `int(3.9)` is 3, `int("42")` is 42 and `int("nope")` is nil
+fn int(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let n = match args[1].decode() {
+        Variant::Float(n) => n,
+
+        Variant::Obj(handle) => match unsafe { heap.get_unchecked(handle) }.as_string() {
+            Some(s) => match s.trim().replace('_', "").parse::<f64>() {
+                Ok(n) => n,
+                Err(_) => return Ok(Value::nil()),
+            },
+
+            None => return raise(format!("can't make int out of {}", args[1].with_heap(heap))),
+        },
+
+        _ => return raise(format!("can't make int out of {}", args[1].with_heap(heap))),
+    };
+
+    if !n.is_finite() || n.trunc().abs() > MAX_INT as f64 {
+        return raise(format!("can't make int out of {}, ints are exact up to {}", n, MAX_INT))
+    }
+
+    Ok(Value::float(n.trunc()))
+}
+
+fn float(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    match args[1].decode() {
+        Variant::Float(_) => Ok(args[1]),
+
+        Variant::Obj(handle) => match unsafe { heap.get_unchecked(handle) }.as_string() {
+            Some(s) => match s.trim().replace('_', "").parse::<f64>() {
+                Ok(n) => Ok(Value::float(n)),
+                Err(_) => Ok(Value::nil()),
+            },
+
+            None => raise(format!("can't make float out of {}", args[1].with_heap(heap))),
+        },
+
+        _ => raise(format!("can't make float out of {}", args[1].with_heap(heap))),
+    }
+}
+
+// the type checker lets through two ints, or an int and a side it couldn't see,
+// which only works out when that's whole too
+fn ints(heap: &Heap<Object>, args: &[Value], what: &str) -> Result<(i64, i64), RuntimeError> {
+    match (args[1].decode(), args[2].decode()) {
+        (Variant::Float(a), Variant::Float(b)) if a.fract() == 0.0 && b.fract() == 0.0 => Ok((a as i64, b as i64)),
+        _ => raise(format!("can't {} {} and {} as ints", what, args[1].with_heap(heap), args[2].with_heap(heap))),
+    }
+}
+
+fn int_add(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (a, b) = ints(heap, args, "add")?;
+    checked(a.checked_add(b))
+}
+
+fn int_sub(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (a, b) = ints(heap, args, "subtract")?;
+    checked(a.checked_sub(b))
+}
+
+fn int_mul(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (a, b) = ints(heap, args, "multiply")?;
+    checked(a.checked_mul(b))
+}
+
+// truncates towards zero, like Rust does
+fn int_div(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    match ints(heap, args, "divide")? {
+        (_, 0) => raise("integer division by zero"),
+        (a, b) => checked(a.checked_div(b)),
+    }
+}
+
+// takes the sign of the left side, so `-7 % 3` is -1
+fn int_mod(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    match ints(heap, args, "take the remainder of")? {
+        (_, 0) => raise("integer modulo by zero"),
+        (a, b) => checked(a.checked_rem(b)),
+    }
+}
+
+fn int_pow(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    match ints(heap, args, "raise")? {
+        (_, b) if b < 0 => raise("can't raise an int to a negative power, use `float()` first"),
+        (a, b) => {
+            // past 64 everything overflows except -1, 0 and 1, where only the parity matters
+            let b = if b > 64 { 64 + b % 2 } else { b };
+
+            checked(a.checked_pow(b as u32))
+        }
+    }
+}
+
+// an int is a whole float at runtime, so this is float arithmetic whatever they are
+fn floats(heap: &Heap<Object>, args: &[Value], what: &str) -> Result<(f64, f64), RuntimeError> {
+    match (args[1].decode(), args[2].decode()) {
+        (Variant::Float(a), Variant::Float(b)) => Ok((a, b)),
+        _ => raise(format!("can't {} {} and {}", what, args[1].with_heap(heap), args[2].with_heap(heap))),
+    }
+}
+
+// two strings are joined, like `+` does on strings the checker knows about
+fn num_add(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let text = |value: &Value| match value.decode() {
+        Variant::Obj(handle) => unsafe { heap.get_unchecked(handle) }.as_string().cloned(),
+        _ => None,
+    };
+
+    if let (Some(a), Some(b)) = (text(&args[1]), text(&args[2])) {
+        return Ok(Value::object(heap.insert_temp(Object::String(format!("{}{}", a, b)))))
+    }
+
+    let (a, b) = floats(heap, args, "add")?;
+    Ok(Value::float(a + b))
+}
+
+fn num_sub(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (a, b) = floats(heap, args, "subtract")?;
+    Ok(Value::float(a - b))
+}
+
+fn num_mul(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (a, b) = floats(heap, args, "multiply")?;
+    Ok(Value::float(a * b))
+}
+
+fn num_div(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (a, b) = floats(heap, args, "divide")?;
+    Ok(Value::float(a / b))
+}
+
+fn num_mod(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (a, b) = floats(heap, args, "take the remainder of")?;
+    Ok(Value::float(a % b))
+}
+
+fn num_pow(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (a, b) = floats(heap, args, "raise")?;
+    Ok(Value::float(a.powf(b)))
+}
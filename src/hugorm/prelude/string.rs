@@ -0,0 +1,183 @@
+use zub::vm::*;
+
+use super::super::engine::{Engine, Native};
+use super::super::runtime::{raise, RuntimeError};
+
+// indices and lengths count chars, not bytes
+pub const NATIVES: &[(&str, u8, Native)] = &[
+    ("split", 2, split),
+    ("join", 2, join),
+    ("trim", 1, trim),
+    ("trim-start", 1, trim_start),
+    ("trim-end", 1, trim_end),
+    ("contains", 2, contains),
+    ("starts-with", 2, starts_with),
+    ("ends-with", 2, ends_with),
+    ("find", 2, find),
+    ("upper", 1, upper),
+    ("lower", 1, lower),
+    ("replace", 3, replace),
+    ("slice", 3, slice),
+    ("parse-number", 1, parse_number),
+];
+
+pub fn include_string(engine: &mut Engine) {
+    for &(name, arity, function) in NATIVES.iter() {
+        engine.register_native(name, arity, function)
+    }
+}
+
+fn string(heap: &Heap<Object>, value: &Value, who: &str) -> Result<String, RuntimeError> {
+    if let Variant::Obj(handle) = value.decode() {
+        if let Some(s) = unsafe { heap.get_unchecked(handle) }.as_string() {
+            return Ok(s.clone())
+        }
+    }
+
+    raise(format!("{} can't take non-string: {}", who, value.with_heap(heap)))
+}
+
+fn number(heap: &Heap<Object>, value: &Value, who: &str) -> Result<f64, RuntimeError> {
+    if let Variant::Float(n) = value.decode() {
+        Ok(n)
+    } else {
+        raise(format!("{} can't take non-number: {}", who, value.with_heap(heap)))
+    }
+}
+
+pub fn new_string(heap: &mut Heap<Object>, s: String) -> Value {
+    Value::object(heap.insert_temp(Object::String(s)))
+}
+
+fn split(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let s = string(heap, &args[1], "split")?;
+    let separator = string(heap, &args[2], "split")?;
+
+    // splitting on nothing gives the chars, like iterating does
+    let parts = if separator.is_empty() {
+        s.chars().map(|c| c.to_string()).collect::<Vec<String>>()
+    } else {
+        s.split(separator.as_str()).map(|part| part.to_string()).collect::<Vec<String>>()
+    };
+
+    let content = parts.into_iter()
+        .map(|part| new_string(heap, part))
+        .collect::<Vec<Value>>();
+
+    Ok(Value::object(heap.insert_temp(Object::List(List::new(content)))))
+}
+
+fn join(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let separator = string(heap, &args[2], "join")?;
+
+    let joined = if let Variant::Obj(handle) = args[1].decode() {
+        if let Some(list) = unsafe { heap.get_unchecked(handle) }.as_list() {
+            list.content.iter()
+                .map(|item| format!("{}", item.with_heap(heap)))
+                .collect::<Vec<String>>()
+                .join(&separator)
+        } else {
+            return raise("can't join non-list")
+        }
+    } else {
+        return raise(format!("can't join non-list: {}", args[1].with_heap(heap)))
+    };
+
+    Ok(new_string(heap, joined))
+}
+
+fn trim(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let s = string(heap, &args[1], "trim")?;
+    Ok(new_string(heap, s.trim().to_string()))
+}
+
+fn trim_start(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let s = string(heap, &args[1], "trim-start")?;
+    Ok(new_string(heap, s.trim_start().to_string()))
+}
+
+fn trim_end(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let s = string(heap, &args[1], "trim-end")?;
+    Ok(new_string(heap, s.trim_end().to_string()))
+}
+
+fn contains(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let s = string(heap, &args[1], "contains")?;
+    let part = string(heap, &args[2], "contains")?;
+
+    Ok(s.contains(part.as_str()).into())
+}
+
+fn starts_with(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let s = string(heap, &args[1], "starts-with")?;
+    let prefix = string(heap, &args[2], "starts-with")?;
+
+    Ok(s.starts_with(prefix.as_str()).into())
+}
+
+fn ends_with(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let s = string(heap, &args[1], "ends-with")?;
+    let suffix = string(heap, &args[2], "ends-with")?;
+
+    Ok(s.ends_with(suffix.as_str()).into())
+}
+
+// index of the first match, or nil
+fn find(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let s = string(heap, &args[1], "find")?;
+    let part = string(heap, &args[2], "find")?;
+
+    match s.find(part.as_str()) {
+        Some(byte) => Ok(Value::float(s[.. byte].chars().count() as f64)),
+        None => Ok(Value::nil()),
+    }
+}
+
+fn upper(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let s = string(heap, &args[1], "upper")?;
+    Ok(new_string(heap, s.to_uppercase()))
+}
+
+fn lower(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let s = string(heap, &args[1], "lower")?;
+    Ok(new_string(heap, s.to_lowercase()))
+}
+
+fn replace(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let s = string(heap, &args[1], "replace")?;
+    let from = string(heap, &args[2], "replace")?;
+    let to = string(heap, &args[3], "replace")?;
+
+    Ok(new_string(heap, s.replace(from.as_str(), to.as_str())))
+}
+
+// `slice(s, start, end)`, end exclusive, negative indices count from the back
+fn slice(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let s = string(heap, &args[1], "slice")?;
+    let len = s.chars().count() as f64;
+
+    let clamp = |n: f64| {
+        let n = if n < 0.0 { len + n } else { n };
+        n.max(0.0).min(len) as usize
+    };
+
+    let start = clamp(number(heap, &args[2], "slice")?);
+    let end = clamp(number(heap, &args[3], "slice")?);
+
+    let sliced = s.chars()
+        .skip(start)
+        .take(end.saturating_sub(start))
+        .collect::<String>();
+
+    Ok(new_string(heap, sliced))
+}
+
+// nil when it isn't a number
+fn parse_number(heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    let s = string(heap, &args[1], "parse-number")?;
+
+    match s.trim().parse::<f64>() {
+        Ok(n) => Ok(Value::float(n)),
+        Err(_) => Ok(Value::nil()),
+    }
+}
@@ -0,0 +1,579 @@
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+
+use colored::Colorize;
+use zub::vm::{HashVariant, Variant};
+
+use super::engine::{parse_str, Engine};
+use super::error::{self, Diagnostic, Severity};
+use super::lexer::*;
+use super::parser::*;
+use super::prelude::{base, math};
+use super::runtime;
+use super::source::*;
+use super::visitor::*;
+
+pub const COMMANDS: &[(&str, &str)] = &[
+    (":type <expr>", "the type of an expression"),
+    (":ast <code>", "what the code parses to"),
+    (":ir <code>", "what the code compiles to, without running it"),
+    (":env", "everything declared so far"),
+    (":load <file>", "runs a file in the session"),
+    (":reset", "forgets everything"),
+    (":help", "this"),
+    ("@math", "brings in `sum` and `student`"),
+    ("@debug", "disassembles whatever runs"),
+];
+
+// one REPL session. Every entry is compiled and run on its own against the same
+// engine, so globals stick around and nothing runs twice. Anything printed goes
+// wherever the engine's output is, see `Engine::set_output`
+pub struct Session {
+    pub engine: Engine,
+    pub debug: bool, // disassemble whatever runs
+    pub exited: Option<i32>, // what `exit` was called with, the session is over
+    pub init: Option<PathBuf>, // what `start` runs, `init_script()` unless set otherwise
+    starting: bool, // running `init`, where a `:reset` doesn't start it over again
+}
+
+impl Session {
+    pub fn new(root: String) -> Self {
+        let mut engine = Engine::new();
+        engine.set_root(&root);
+
+        Session {
+            engine,
+            debug: false,
+            exited: None,
+            init: init_script(),
+            starting: false,
+        }
+    }
+
+    // runs the startup script, if there is one
+    pub fn start(&mut self) -> Vec<Diagnostic> {
+        let init = match self.init.clone() {
+            Some(init) => init,
+            None => return Vec::new(),
+        };
+
+        self.starting = true;
+        let diagnostics = self.load(&init.display().to_string());
+        self.starting = false;
+
+        diagnostics
+    }
+
+    // forgets everything that ran, but not how the session was set up. Output
+    // and input go where they went before, and the startup script runs again
+    fn reset(&mut self) -> Vec<Diagnostic> {
+        let mut engine = Engine::new();
+
+        engine.set_root(self.engine.root());
+        engine.set_output(self.engine.set_output(None));
+        engine.set_input(self.engine.set_input(None));
+
+        self.engine = engine;
+        self.exited = None;
+
+        if self.starting {
+            return Vec::new()
+        }
+
+        self.start()
+    }
+
+    // a command, or code to run
+    pub fn handle(&mut self, entry: &str) -> Vec<Diagnostic> {
+        let trimmed = entry.trim();
+
+        if trimmed == "@math" {
+            math::include_math(&mut self.engine);
+        } else if trimmed == "@debug" {
+            self.debug = true;
+        } else if trimmed.starts_with(':') {
+            let (command, rest) = trimmed.split_once(char::is_whitespace).unwrap_or((trimmed, ""));
+
+            return self.command(command, rest.trim())
+        } else {
+            return self.eval(entry)
+        }
+
+        Vec::new()
+    }
+
+    fn command(&mut self, command: &str, rest: &str) -> Vec<Diagnostic> {
+        match command {
+            ":type" => return self.type_of(rest),
+            ":ast" => return self.ast(rest),
+            ":ir" => return self.ir(rest),
+            ":load" => return self.load(rest),
+            ":env" => self.env(),
+
+            ":reset" => return self.reset(),
+
+            ":help" => for (usage, what) in COMMANDS.iter() {
+                self.engine.write_output(&format!("{:<14} {}\n", usage, what))
+            },
+
+            _ => {
+                let mut diagnostic = Diagnostic::new(Severity::Error, format!("no command called `{}`", command));
+                diagnostic.help = Some("`:help` lists them".to_string());
+
+                return vec!(diagnostic)
+            }
+        }
+
+        Vec::new()
+    }
+
+    fn type_of(&mut self, code: &str) -> Vec<Diagnostic> {
+        let source = source(code);
+
+        let expression = match lone_expression(&source, code) {
+            Ok(expression) => expression,
+            Err(diagnostics) => return diagnostics,
+        };
+
+        let mut visitor = Visitor::from(&source, self.engine.symtab.clone(), self.engine.root().to_string());
+
+        match visitor.type_expression(&expression) {
+            Ok(t) => self.engine.write_output(&format!("{}\n", t.node)),
+            Err(_) => return error::drain(),
+        }
+
+        error::drain()
+    }
+
+    fn ast(&mut self, code: &str) -> Vec<Diagnostic> {
+        let source = source(code);
+
+        match parse_str(&source, code) {
+            Ok((ast, _)) => match ast.as_slice() {
+                [Statement { node: StatementNode::Expression(ref expression), .. }] => {
+                    self.engine.write_output(&format!("{:#?}\n", expression))
+                }
+
+                _ => self.engine.write_output(&format!("{:#?}\n", ast)),
+            },
+
+            Err(diagnostics) => return diagnostics,
+        }
+
+        Vec::new()
+    }
+
+    // compiled against the session, which is put back as it was afterwards
+    fn ir(&mut self, code: &str) -> Vec<Diagnostic> {
+        let source = source(code);
+
+        let (ast, comments) = match parse_str(&source, code) {
+            Ok(parsed) => parsed,
+            Err(diagnostics) => return diagnostics,
+        };
+
+        let backup = (self.engine.symtab.clone(), self.engine.modules.clone());
+        let compiled = self.engine.compile_ast(&source, &ast, &comments);
+
+        (self.engine.symtab, self.engine.modules) = backup;
+
+        match compiled {
+            Ok((ir, warnings)) => {
+                self.engine.write_output(&format!("{:#?}\n", ir));
+                warnings
+            }
+
+            Err(diagnostics) => diagnostics,
+        }
+    }
+
+    pub fn load(&mut self, path: &str) -> Vec<Diagnostic> {
+        match fs::read_to_string(path) {
+            Ok(content) => self.script(path, &content),
+            Err(why) => {
+                let mut diagnostic = Diagnostic::new(Severity::Error, format!("failed to read: {}", why));
+                diagnostic.file = Some(path.to_string());
+
+                vec!(diagnostic)
+            }
+        }
+    }
+
+    // a file of code with commands mixed in, on lines of their own. The code
+    // between them keeps its line numbers
+    pub fn script(&mut self, path: &str, content: &str) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut code = String::new();
+
+        for line in content.lines() {
+            if line.starts_with('@') || line.starts_with(':') {
+                diagnostics.extend(self.flush(path, &mut code));
+
+                // nothing after an `exit` runs
+                if self.exited.is_some() {
+                    return diagnostics
+                }
+
+                diagnostics.extend(self.handle(line));
+
+                code.push('\n')
+            } else {
+                code.push_str(line);
+                code.push('\n')
+            }
+        }
+
+        diagnostics.extend(self.flush(path, &mut code));
+
+        diagnostics
+    }
+
+    // runs the code gathered so far, leaving empty lines in its place
+    fn flush(&mut self, path: &str, code: &mut String) -> Vec<Diagnostic> {
+        let diagnostics = if code.trim().is_empty() {
+            Vec::new()
+        } else {
+            self.run(path, code)
+        };
+
+        *code = "\n".repeat(code.lines().count());
+
+        diagnostics
+    }
+
+    // builtins only get counted, there's a lot of them
+    fn env(&mut self) {
+        let mut shown = String::new();
+
+        for (i, frame) in self.engine.symtab.stack.iter().enumerate() {
+            let table = frame.table.borrow();
+
+            let mut names = table.iter()
+                .filter(|(name, t)| !name.starts_with('$') && t.meta.is_some())
+                .collect::<Vec<_>>();
+
+            names.sort_by(|a, b| a.0.cmp(b.0));
+
+            shown += &format!("frame {}\n", i);
+
+            for (name, t) in names.iter() {
+                let keyword = if t.mode == TypeMode::Immutable { "const" } else { "let" };
+                shown += &format!("  {} {}: {}\n", keyword, name, t.node)
+            }
+
+            for interface in frame.interfaces.keys() {
+                shown += &format!("  interface {}\n", interface)
+            }
+
+            let builtins = table.values().filter(|t| t.meta.is_none()).count();
+
+            if builtins > 0 {
+                shown += &format!("  and {} builtins\n", builtins)
+            }
+        }
+
+        self.engine.write_output(&shown)
+    }
+
+    // where the word under the cursor starts and what it could be. After a dot
+    // it's the fields of whatever's in front of it, as it is right now
+    pub fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let before = &line[..pos];
+        let start = word_start(before);
+        let word = &before[start..];
+
+        let mut candidates = if before[..start].ends_with('.') {
+            let mut path = Vec::new();
+            let mut end = start - 1;
+
+            loop {
+                let from = word_start(&before[..end]);
+
+                if from == end {
+                    break
+                }
+
+                path.insert(0, &before[from..end]);
+
+                if !before[..from].ends_with('.') {
+                    break
+                }
+
+                end = from - 1
+            }
+
+            self.fields(&path)
+        } else {
+            let mut names = KEYWORDS.iter().map(|keyword| keyword.to_string()).collect::<Vec<String>>();
+
+            for frame in self.engine.symtab.stack.iter() {
+                names.extend(frame.table.borrow().keys().filter(|name| !name.starts_with('$')).cloned());
+                names.extend(frame.interfaces.keys().cloned())
+            }
+
+            names
+        };
+
+        candidates.retain(|candidate| candidate.starts_with(word));
+        candidates.sort();
+        candidates.dedup();
+
+        (start, candidates)
+    }
+
+    // the string keys of the dict at the end of a path of globals and fields,
+    // methods brought in by `with` included
+    fn fields(&self, path: &[&str]) -> Vec<String> {
+        let heap = &self.engine.vm.heap;
+
+        let mut value = match path.first().and_then(|name| self.engine.get_global(name)) {
+            Some(value) => value,
+            None => return Vec::new(),
+        };
+
+        let dict = |value: zub::vm::Value| match value.decode() {
+            Variant::Obj(handle) => heap.get(handle).and_then(|object| object.as_dict()),
+            _ => None,
+        };
+
+        for field in path[1..].iter() {
+            let found = dict(value).and_then(|dict| dict.content.iter().find_map(|(key, value)| match key.variant {
+                HashVariant::Str(ref key) if key == field => Some(*value),
+                _ => None,
+            }));
+
+            match found {
+                Some(found) => value = found,
+                None => return Vec::new(),
+            }
+        }
+
+        match dict(value) {
+            Some(dict) => dict.content.keys().filter_map(|key| match key.variant {
+                HashVariant::Str(ref key) => Some(key.clone()),
+                _ => None,
+            }).collect(),
+
+            None => Vec::new(),
+        }
+    }
+
+    // runs an entry and echoes the value of a lone expression. Nothing that goes
+    // wrong ends the session, it's all handed back
+    pub fn eval(&mut self, code: &str) -> Vec<Diagnostic> {
+        self.run("<repl>", code)
+    }
+
+    fn run(&mut self, path: &str, code: &str) -> Vec<Diagnostic> {
+        let source = Source::from(path, code.lines().map(|x| x.into()).collect::<Vec<String>>());
+
+        let (ast, comments) = match parse_str(&source, code) {
+            Ok(parsed) => parsed,
+            Err(diagnostics) => return diagnostics,
+        };
+
+        let (ast, echo) = echoed(ast);
+
+        let (ir, mut diagnostics) = match self.engine.compile_ast(&source, &ast, &comments) {
+            Ok(compiled) => compiled,
+            Err(diagnostics) => return diagnostics,
+        };
+
+        if let Err(payload) = self.engine.exec_debug(&ir, self.debug) {
+            if let Some(&base::Exit(code)) = payload.downcast_ref::<base::Exit>() {
+                self.exited = Some(code);
+
+                return diagnostics
+            }
+
+            diagnostics.push(runtime::diagnostic(&*payload, path));
+
+            return diagnostics
+        }
+
+        if echo {
+            if let Some(value) = self.engine.get_global("$") {
+                if !matches!(value.decode(), Variant::Nil) {
+                    let shown = format!("{}\n", value.with_heap(self.engine.heap()));
+                    self.engine.write_output(&shown)
+                }
+            }
+        }
+
+        diagnostics
+    }
+}
+
+// a lone expression is kept in `$` to be shown afterwards
+fn echoed(mut ast: Vec<Statement>) -> (Vec<Statement>, bool) {
+    if ast.len() != 1 {
+        return (ast, false)
+    }
+
+    let statement = ast.pop().unwrap();
+
+    match statement.node {
+        StatementNode::Expression(expr) => {
+            let declaration = StatementNode::Declaration("$".to_string(), Some(expr));
+
+            (vec!(Statement::new(declaration, statement.pos)), true)
+        }
+
+        _ => (vec!(statement), false),
+    }
+}
+
+// `<data dir>/hugorm/history`
+pub fn history_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("hugorm").join("history"))
+}
+
+// `HUGORM_INIT`, or `~/.hugormrc.hug` when there is one. An empty `HUGORM_INIT`
+// means no startup script at all
+pub fn init_script() -> Option<PathBuf> {
+    pick_init_script(env::var_os("HUGORM_INIT"), dirs::home_dir())
+}
+
+// the same, given what `HUGORM_INIT` and the home folder are
+pub fn pick_init_script(var: Option<OsString>, home: Option<PathBuf>) -> Option<PathBuf> {
+    match var {
+        Some(path) if path.is_empty() => None,
+        Some(path) => Some(PathBuf::from(path)),
+        None => home.map(|home| home.join(".hugormrc.hug")).filter(|path| path.exists()),
+    }
+}
+
+// whether to keep reading: a bracket is still open, or a block was started
+// and hasn't been ended with an empty line yet
+pub fn incomplete(input: &str) -> bool {
+    let source = source(input);
+    let tokens = Lexer::default(input.chars().collect(), &source).flatten().collect::<Vec<Token>>();
+
+    // whatever the lexer had to say is for when it's run for real
+    error::drain();
+
+    let mut depth = 0isize;
+    let mut block = false;
+
+    for (i, token) in tokens.iter().enumerate() {
+        if token.token_type != TokenType::Symbol {
+            continue
+        }
+
+        match token.lexeme.as_str() {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => depth -= 1,
+            ":" => block |= tokens.get(i + 1).is_none_or(|next| next.token_type == TokenType::EOL),
+            _ => (),
+        }
+    }
+
+    depth > 0 || block && !input.ends_with('\n')
+}
+
+// the next entry out of `lines`, for when there's no editor asking `incomplete`
+// after every line, like when the input is piped in. Blank lines between
+// entries are skipped, `None` once there's nothing left
+pub fn read_entry<I: Iterator<Item = String>>(lines: &mut I) -> Option<String> {
+    let mut entry = lines.by_ref().find(|line| !line.trim().is_empty())?;
+
+    while incomplete(&entry) {
+        match lines.next() {
+            Some(line) => {
+                entry.push('\n');
+                entry.push_str(&line)
+            },
+
+            None => break,
+        }
+    }
+
+    Some(entry)
+}
+
+// colors what the lexer makes of the input, leaving alone whatever it doesn't
+pub fn highlight(input: &str) -> String {
+    let source = source(input);
+    let mut lexer = Lexer::default(input.chars().collect(), &source);
+
+    let mut painted = Vec::new(); // (line, start, end, color)
+
+    for token in lexer.by_ref().flatten() {
+        let color = match token.token_type {
+            TokenType::Keyword => "magenta",
+            TokenType::Str | TokenType::Char => "green",
+            TokenType::Int | TokenType::Float => "yellow",
+            TokenType::Bool => "cyan",
+            _ => continue,
+        };
+
+        painted.push((token.line.0, token.slice.0, token.slice.1, color))
+    }
+
+    for comment in lexer.comments.iter() {
+        painted.push((comment.line.0, comment.slice.0, usize::MAX, "bright black"))
+    }
+
+    error::drain();
+
+    let color_at = |line: usize, column: usize| painted.iter()
+        .find(|(l, start, end, _)| *l == line && *start <= column && column <= *end)
+        .map(|(_, _, _, color)| *color);
+
+    let mut out = String::new();
+
+    for (i, line) in input.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n')
+        }
+
+        let mut run = String::new();
+        let mut current = None;
+
+        for (j, c) in line.chars().enumerate() {
+            let color = color_at(i + 1, j + 1);
+
+            if color != current {
+                out.push_str(&paint(&run, current));
+                run.clear();
+                current = color
+            }
+
+            run.push(c)
+        }
+
+        out.push_str(&paint(&run, current))
+    }
+
+    out
+}
+
+fn paint(text: &str, color: Option<&str>) -> String {
+    match color {
+        Some(color) if !text.is_empty() => text.color(color).to_string(),
+        _ => text.to_string(),
+    }
+}
+
+// byte offset of the name that `text` ends in
+fn word_start(text: &str) -> usize {
+    text.char_indices().rev()
+        .take_while(|(_, c)| is_name_char(*c))
+        .last()
+        .map_or(text.len(), |(i, _)| i)
+}
+
+fn source(code: &str) -> Source {
+    Source::from("<repl>", code.lines().map(|x| x.into()).collect::<Vec<String>>())
+}
+
+fn lone_expression(source: &Source, code: &str) -> Result<Expression, Vec<Diagnostic>> {
+    let (mut ast, _) = parse_str(source, code)?;
+
+    match ast.pop() {
+        Some(Statement { node: StatementNode::Expression(expression), .. }) if ast.is_empty() => Ok(expression),
+        _ => Err(vec!(Diagnostic::new(Severity::Error, "expected just an expression".to_string()))),
+    }
+}
@@ -0,0 +1,161 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem;
+use std::panic;
+
+use zub::vm::*;
+
+use super::engine::{panic_message, quietly, Native};
+use super::error::{Diagnostic, Severity};
+use super::lexer::Pos;
+
+// zub only knows lines, so the visitor hands it numbers instead: everything that
+// can go wrong is compiled `At` an index into the spans it recorded, plus one.
+// The VM keeps those in its line table for nothing, and they're only looked up
+// once a run has failed
+
+// what a native raises when it can't do anything with what it got
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    pub message: String,
+    pub trace: Vec<Frame>, // innermost first, filled in once the run is unwound
+}
+
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub function: Option<String>, // `None` at the top level
+    pub file: Option<String>,
+    pub pos: Option<Pos>,
+}
+
+// what a native hands back when it fails
+pub fn raise<T, S: Into<String>>(message: S) -> Result<T, RuntimeError> {
+    Err(RuntimeError {
+        message: message.into(),
+        trace: Vec::new(),
+    })
+}
+
+// ends the run with something that isn't an error, like `exit` does. zub has no
+// way of stopping other than unwinding, and this doesn't bother the panic hook
+pub fn stop<P: Any + Send>(payload: P) -> ! {
+    panic::resume_unwind(Box::new(payload))
+}
+
+impl RuntimeError {
+    // points at where it happened, with a note for every call on the way there.
+    // `path` is what ran, for when there's nothing better
+    pub fn diagnostic(&self, path: &str) -> Diagnostic {
+        let mut diagnostic = Diagnostic::new(Severity::Error, format!("runtime error: {}", self.message));
+
+        diagnostic.file = Some(path.to_string());
+
+        if let Some(Frame { file: Some(ref file), ref pos, .. }) = self.trace.first() {
+            diagnostic.file = Some(file.clone());
+            diagnostic.span = pos.clone();
+        }
+
+        for (callee, frame) in self.trace.iter().zip(self.trace.iter().skip(1)) {
+            let name = callee.function.as_deref().unwrap_or("<anonymous>");
+
+            let mut note = Diagnostic::new(Severity::Note, format!("in `{}`, called here", name));
+            note.file = frame.file.clone();
+            note.span = frame.pos.clone();
+
+            diagnostic.notes.push(note)
+        }
+
+        diagnostic
+    }
+}
+
+// whatever a run panicked with, as a diagnostic
+pub fn diagnostic(payload: &(dyn Any + Send), path: &str) -> Diagnostic {
+    match payload.downcast_ref::<RuntimeError>() {
+        Some(error) => error.diagnostic(path),
+        None => RuntimeError { message: panic_message(payload), trace: Vec::new() }.diagnostic(path),
+    }
+}
+
+thread_local! {
+    // the natives of the engine running on this thread, lent for as long as it runs
+    static FUNCTIONS: RefCell<HashMap<String, Native>> = RefCell::new(HashMap::new());
+}
+
+// what the VM is given for every native an engine registers. It finds the real
+// one by name and turns its error into the unwinding that stops the VM
+pub fn dispatch(heap: &mut Heap<Object>, args: &[Value]) -> Value {
+    let function = match args[0].decode() {
+        Variant::Obj(handle) => match unsafe { heap.get_unchecked(handle) } {
+            Object::NativeFunction(ref native) => FUNCTIONS.with(|functions| functions.borrow().get(&native.name).copied()),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    let result = match function {
+        Some(function) => function(heap, args),
+        None => raise(format!("`{}` isn't a native of this engine", args[0].with_heap(heap))),
+    };
+
+    match result {
+        Ok(value) => value,
+        Err(error) => stop(error),
+    }
+}
+
+// runs `f` on `vm` with `natives` lent out to `dispatch`. A `RuntimeError` or a
+// `VmError` it unwinds with comes back as a `RuntimeError` with its trace filled
+// in from where the VM stopped, anything else untouched
+pub fn traced<T, F: FnOnce(&mut VM) -> T>(
+    vm: &mut VM,
+    spans: &[(String, Pos)],
+    natives: &mut HashMap<String, Native>,
+    f: F,
+) -> Result<T, Box<dyn Any + Send>> {
+    let outer = FUNCTIONS.with(|functions| functions.replace(mem::take(natives)));
+
+    let result = quietly(|| f(vm));
+
+    *natives = FUNCTIONS.with(|functions| functions.replace(outer));
+
+    result.map_err(|payload| {
+        let message = match payload.downcast::<RuntimeError>() {
+            Ok(error) => error.message,
+            Err(payload) => match payload.downcast::<VmError>() {
+                Ok(error) => error.message,
+                Err(payload) => return payload,
+            },
+        };
+
+        let trace = vm.trace().into_iter().map(|(chunk, line)| {
+            let span = line.checked_sub(1).and_then(|id| spans.get(id));
+
+            Frame {
+                function: function_name(&chunk),
+                file: span.map(|span| span.0.clone()),
+                pos: span.map(|span| span.1.clone()),
+            }
+        }).collect();
+
+        Box::new(RuntimeError { message, trace }) as Box<dyn Any + Send>
+    })
+}
+
+// what the user would call the function a chunk was compiled from. `fun`
+// expressions and interface methods have names made up for them, and grabbed
+// modules put theirs in front
+fn function_name(chunk: &str) -> Option<String> {
+    if chunk == "<zub>" {
+        return None
+    }
+
+    if chunk.starts_with("<anon-fn") {
+        return Some("<anonymous>".to_string())
+    }
+
+    let name = chunk.rsplit("::").next().unwrap_or(chunk);
+
+    Some(name.trim_start_matches('<').trim_end_matches('>').to_string())
+}
@@ -1,5 +1,6 @@
-use std::fs::File;
-use std::io::prelude::*;
+use std::fs;
+use std::io;
+use std::path::Path;
 
 use std::fmt;
 
@@ -21,16 +22,13 @@ pub struct Source {
 }
 
 impl Source {
-    pub fn new(path: String) -> Self {
-        let mut source = File::open(path.as_str()).unwrap();
-        let mut content = String::new();
+    pub fn new(path: String) -> io::Result<Self> {
+        let content = fs::read_to_string(&path)?;
 
-        source.read_to_string(&mut content).unwrap();
-
-        Source {
+        Ok(Source {
             file: FilePath(path),
             lines: content.lines().map(|x| x.to_string()).collect(),
-        }
+        })
     }
 
     pub fn from(path: &str, lines: Vec<String>) -> Self {
@@ -40,3 +38,42 @@ impl Source {
         }
     }
 }
+
+// the `.hug` files under some paths, searching directories but skipping hidden ones
+pub fn hug_files(paths: &[String]) -> Vec<String> {
+    fn walk(dir: &Path, found: &mut Vec<String>) {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            if path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.')) {
+                continue
+            }
+
+            if path.is_dir() {
+                walk(&path, found)
+            } else if path.extension().is_some_and(|ext| ext == "hug") {
+                found.push(path.display().to_string())
+            }
+        }
+    }
+
+    let mut found = Vec::new();
+
+    for path in paths.iter() {
+        if Path::new(path).is_dir() {
+            walk(Path::new(path), &mut found)
+        } else {
+            found.push(path.clone())
+        }
+    }
+
+    found.sort();
+    found.dedup();
+
+    found
+}
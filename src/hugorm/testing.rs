@@ -0,0 +1,159 @@
+use std::any::Any;
+use std::fs;
+use std::path::Path;
+
+use super::engine::Engine;
+use super::error::{self, Diagnostic, Severity};
+use super::lexer::*;
+use super::parser::*;
+use super::prelude::assert::{self, Failure};
+use super::runtime;
+use super::source::*;
+
+// `hugorm test`, every test gets an engine of its own
+
+pub struct Outcome {
+    pub file: String,
+    pub name: String, // the `test fun`, or the file itself when it has none
+    pub failures: Vec<Diagnostic>, // nothing when it passed
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+// files given directly always count, directories are searched for `*_test.hug`
+// and anything else declaring a `test fun`
+pub fn discover(paths: &[String]) -> Vec<String> {
+    let mut found = Vec::new();
+
+    for path in paths.iter() {
+        if Path::new(path).is_dir() {
+            found.extend(hug_files(std::slice::from_ref(path)).into_iter().filter(|file| {
+                file.ends_with("_test.hug") || fs::read_to_string(file).is_ok_and(|content| !test_names(file, &content).is_empty())
+            }))
+        } else {
+            found.push(path.clone())
+        }
+    }
+
+    found.sort();
+    found.dedup();
+
+    found
+}
+
+pub fn run_file(path: &str) -> Vec<Outcome> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(why) => {
+            let mut diagnostic = Diagnostic::new(Severity::Error, format!("failed to read: {}", why));
+            diagnostic.file = Some(path.to_string());
+
+            return vec!(outcome(path, path, vec!(diagnostic)))
+        }
+    };
+
+    let root = Path::new(path).parent().map(|dir| dir.display().to_string()).unwrap_or_default();
+
+    // a file that doesn't compile is one failure, not one per test in it
+    let mut engine = test_engine(&root);
+
+    if let Err(diagnostics) = engine.compile_str(path, &content) {
+        return vec!(outcome(path, path, diagnostics))
+    }
+
+    let tests = test_names(path, &content);
+
+    if tests.is_empty() {
+        return vec!(outcome(path, path, run(path, &content, &root, None)))
+    }
+
+    tests.iter()
+        .map(|name| outcome(path, name, run(path, &content, &root, Some(name))))
+        .collect()
+}
+
+fn outcome(file: &str, name: &str, failures: Vec<Diagnostic>) -> Outcome {
+    Outcome {
+        file: file.to_string(),
+        name: name.to_string(),
+        failures,
+    }
+}
+
+fn test_engine(root: &str) -> Engine {
+    let mut engine = Engine::new();
+
+    engine.set_root(root);
+    assert::include_assert(&mut engine);
+
+    engine
+}
+
+// the top level `test fun`s, in order
+fn test_names(path: &str, content: &str) -> Vec<String> {
+    let source = Source::from(path, content.lines().map(|x| x.into()).collect::<Vec<String>>());
+    let tokens = Lexer::default(content.chars().collect(), &source).filter_map(Result::ok).collect();
+
+    let ast = Parser::new(tokens, &source).parse().unwrap_or_default();
+
+    error::drain();
+
+    ast.iter().filter_map(|statement| match statement.node {
+        StatementNode::Test(ref fun) => match fun.node {
+            StatementNode::Function(ref name, ..) => Some(name.clone()),
+            _ => None,
+        },
+        _ => None,
+    }).collect()
+}
+
+// runs the file's top level, then the test itself
+fn run(path: &str, content: &str, root: &str, test: Option<&str>) -> Vec<Diagnostic> {
+    let lines = content.lines().map(|x| x.into()).collect::<Vec<String>>();
+
+    let mut engine = test_engine(root);
+
+    let mut steps = vec!((path.to_string(), content.to_string()));
+
+    if let Some(name) = test {
+        steps.push(("<test>".to_string(), format!("{}()", name)))
+    }
+
+    for (file, code) in steps.iter() {
+        let ir = match engine.compile_str(file, code) {
+            Ok((ir, _)) => ir,
+            Err(diagnostics) => return diagnostics,
+        };
+
+        if let Err(payload) = engine.exec(&ir) {
+            return vec!(failure(path, &lines, payload))
+        }
+    }
+
+    Vec::new()
+}
+
+fn failure(path: &str, lines: &[String], payload: Box<dyn Any + Send>) -> Diagnostic {
+    let failure = match payload.downcast_ref::<Failure>() {
+        Some(failure) => failure,
+        None => return runtime::diagnostic(&*payload, path),
+    };
+
+    let mut diagnostic = Diagnostic::new(Severity::Error, failure.message.clone());
+
+    // the assertion can be in a helper the test file grabbed
+    let line = if failure.file == path {
+        lines.get(failure.line.wrapping_sub(1)).cloned()
+    } else {
+        fs::read_to_string(&failure.file).ok().and_then(|content| content.lines().nth(failure.line.wrapping_sub(1)).map(String::from))
+    };
+
+    diagnostic.span = Some(Pos((failure.line, line.unwrap_or_default()), failure.slice));
+    diagnostic.file = Some(failure.file.clone());
+
+    diagnostic
+}
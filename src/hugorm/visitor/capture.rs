@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+
+use super::*;
+
+// zub closes every open upvalue whenever any function returns, so a captured
+// variable stops being shared the moment something changes it afterwards.
+// Variables that are both captured and changed get boxed in a one-element list
+// instead, the list never changes, only what's inside it does.
+
+#[derive(Default)]
+struct Uses {
+    declared: HashSet<String>, // by the function itself
+    mutated: HashSet<String>,  // anywhere, nested functions included
+    captured: HashSet<String>, // mentioned by nested functions
+}
+
+// which of a function's own variables have to live in a box
+pub fn boxed(params: &[String], body: &[Statement]) -> HashSet<String> {
+    let mut uses = Uses::default();
+
+    uses.declared.extend(params.iter().cloned());
+
+    for statement in body.iter() {
+        uses.statement(statement, false, false)
+    }
+
+    uses.declared.iter()
+        .filter(|name| uses.captured.contains(*name) && uses.mutated.contains(*name))
+        .cloned()
+        .collect()
+}
+
+impl Uses {
+    fn declare(&mut self, name: &str, nested: bool, in_block: bool) {
+        if !nested {
+            self.declared.insert(name.to_string());
+
+            // hoisted out of the block, so declaring it in there is setting it
+            if in_block {
+                self.mutated.insert(name.to_string());
+            }
+        }
+    }
+
+    fn statements(&mut self, body: &[Statement], nested: bool, in_block: bool) {
+        for statement in body.iter() {
+            self.statement(statement, nested, in_block)
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement, nested: bool, in_block: bool) {
+        use self::StatementNode::*;
+
+        match statement.node {
+            Expression(ref expr) => self.expression(expr, nested),
+
+            Declaration(ref name, ref right) => {
+                self.declare(name, nested, in_block);
+
+                if let Some(ref right) = *right {
+                    self.expression(right, nested)
+                }
+            }
+
+            Const(ref name, ref right) => {
+                self.declare(name, nested, in_block);
+                self.expression(right, nested)
+            }
+
+            ConstFunction(ref fun) | Test(ref fun) => self.statement(fun, nested, in_block),
+
+            Assignment(ref left, ref right) => {
+                if let ExpressionNode::Identifier(ref name) = left.node {
+                    self.mutated.insert(name.clone());
+
+                    if nested {
+                        self.captured.insert(name.clone());
+                    }
+                } else {
+                    self.expression(left, nested)
+                }
+
+                self.expression(right, nested)
+            }
+
+            Function(_, _, ref body) => self.statements(body, true, false),
+
+            Return(ref value) => if let Some(ref value) = *value {
+                self.expression(value, nested)
+            },
+
+            Interface(_, ref body) => self.statements(body, nested, in_block),
+
+            If(ref cond, ref body, ref else_) => {
+                self.expression(cond, nested);
+                self.statements(body, nested, true);
+
+                for (cond, body) in else_.iter() {
+                    if let Some(ref cond) = *cond {
+                        self.expression(cond, nested)
+                    }
+
+                    self.statements(body, nested, true)
+                }
+            }
+
+            While(ref cond, ref body) => {
+                self.expression(cond, nested);
+                self.statements(body, nested, true)
+            }
+
+            For(ref name, ref iterable, ref body) => {
+                // set once per element
+                self.declare(name, nested, true);
+
+                self.expression(iterable, nested);
+                self.statements(body, nested, true)
+            }
+
+            Block(ref body) => self.statements(body, nested, true),
+
+            Use(..) | Break => (),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression, nested: bool) {
+        use self::ExpressionNode::*;
+
+        match expression.node {
+            Identifier(ref name) => if nested {
+                self.captured.insert(name.clone());
+            },
+
+            Neg(ref expr) | Not(ref expr) => self.expression(expr, nested),
+
+            Binary(ref left, _, ref right) | With(ref left, ref right) => {
+                self.expression(left, nested);
+                self.expression(right, nested)
+            }
+
+            Call(ref callee, ref args) => {
+                self.expression(callee, nested);
+
+                for arg in args.iter() {
+                    self.expression(arg, nested)
+                }
+            }
+
+            Array(ref content) => for element in content.iter() {
+                self.expression(element, nested)
+            },
+
+            Dict(ref content) => for (_, value) in content.iter() {
+                self.expression(value, nested)
+            },
+
+            AnonFunction(_, _, ref body) => self.statements(body, true, false),
+
+            Nil | Int(_) | Float(_) | Str(_) | Bool(_) | Empty | EOF => (),
+        }
+    }
+}
@@ -0,0 +1,567 @@
+use std::collections::{HashMap, HashSet};
+
+use super::super::error::{self, Response::*};
+use super::*;
+
+// warnings about code that compiles fine but probably isn't what was meant, run
+// once the visitor is happy. `# lint: allow <rule>, ...` turns rules off for the
+// whole file and `# lint: warn <rule>, ...` turns them back on, `all` being all of them
+
+pub const RULES: &[&str] = &[
+    "unused-variable",
+    "unused-parameter",
+    "shadowed",
+    "unreachable",
+    "constant-condition",
+    "empty-body",
+    "unknown-method",
+    "kebab-end",
+];
+
+struct Local {
+    name: String,
+    pos: Pos,
+    parameter: bool,
+    nested: bool, // declared in a block out in the file, where nothing else can reach it
+    used: bool,
+}
+
+// what we know a `{...} with Interface` has
+#[derive(Clone)]
+struct Members {
+    interfaces: Vec<String>,
+    names: HashSet<String>,
+}
+
+// a function, or the file itself
+#[derive(Default)]
+struct Frame {
+    locals: Vec<Local>,
+    receivers: HashMap<String, Members>,
+}
+
+struct Linter<'a> {
+    source: &'a Source,
+    allowed: HashSet<&'static str>,
+    frames: Vec<Frame>,
+    interfaces: HashMap<String, HashSet<String>>,
+    blocks: usize, // how many bodies deep it is
+}
+
+pub fn lint(ast: &[Statement], comments: &[Token], source: &Source) {
+    let mut linter = Linter {
+        source,
+        allowed: pragmas(comments, source),
+        frames: vec!(Frame::default()),
+        interfaces: HashMap::new(),
+        blocks: 0,
+    };
+
+    // the visitor's warnings go first, the lints after them top to bottom
+    let before = error::drain();
+
+    linter.statements(ast);
+
+    // the file's own variables may be used by whatever grabs it, the ones in
+    // its loops and branches are only for the file
+    let file = linter.frames.pop().unwrap();
+    linter.unused(&file, |local| local.nested);
+
+    let mut linted = error::drain();
+    linted.sort_by_key(|d| d.span.as_ref().map(|span| ((span.0).0, (span.1).0)));
+
+    for diagnostic in before.into_iter().chain(linted) {
+        error::emit(diagnostic)
+    }
+}
+
+// later pragmas win
+fn pragmas(comments: &[Token], source: &Source) -> HashSet<&'static str> {
+    let mut allowed = HashSet::new();
+
+    for comment in comments.iter() {
+        let text = comment.lexeme.trim_start_matches('#').trim();
+
+        let rest = match text.strip_prefix("lint:") {
+            Some(rest) => rest.trim(),
+            None => continue,
+        };
+
+        let pos = Pos(comment.line.clone(), comment.slice);
+        let (action, names) = rest.split_once(' ').unwrap_or((rest, ""));
+
+        if action != "allow" && action != "warn" {
+            response!(
+                Weird(format!("expected `allow` or `warn` after `lint:`, found `{}`", action)),
+                source.file,
+                pos
+            );
+
+            continue
+        }
+
+        for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let rules = if name == "all" {
+                RULES.to_vec()
+            } else if let Some(rule) = RULES.iter().find(|rule| **rule == name) {
+                vec!(*rule)
+            } else {
+                response!(
+                    Weird(format!("no lint called `{}`", name)),
+                    source.file,
+                    pos,
+                    Help(format!("the lints are {}", RULES.join(", ")))
+                );
+
+                continue
+            };
+
+            for rule in rules {
+                if action == "allow" {
+                    allowed.insert(rule);
+                } else {
+                    allowed.remove(rule);
+                }
+            }
+        }
+    }
+
+    allowed
+}
+
+// the counter `loop N:` adds to the end of the body
+fn desugared(statement: &Statement) -> bool {
+    match statement.node {
+        StatementNode::Assignment(ref left, _) => matches!(left.node, ExpressionNode::Identifier(ref name) if name.starts_with('$')),
+        _ => false,
+    }
+}
+
+// everything a function declares, wherever in its body, as it's all hoisted
+fn declarations(body: &[Statement], found: &mut Vec<(String, Pos)>) {
+    use self::StatementNode::*;
+
+    for statement in body.iter() {
+        let pos = &statement.pos;
+
+        match statement.node {
+            Declaration(ref name, _) | Const(ref name, _) | Function(ref name, ..) => {
+                found.push((name.clone(), pos.find(name, (pos.1).0).unwrap_or_else(|| pos.clone())))
+            }
+
+            ConstFunction(ref fun) | Test(ref fun) => declarations(std::slice::from_ref(fun), found),
+
+            For(ref name, _, ref body) => {
+                found.push((name.clone(), pos.find(name, (pos.1).0).unwrap_or_else(|| pos.clone())));
+                declarations(body, found)
+            }
+
+            If(_, ref body, ref else_) => {
+                declarations(body, found);
+
+                for (_, body) in else_.iter() {
+                    declarations(body, found)
+                }
+            }
+
+            While(_, ref body) | Block(ref body) => declarations(body, found),
+
+            _ => (),
+        }
+    }
+}
+
+impl<'a> Linter<'a> {
+    fn warn(&self, rule: &'static str, message: String, pos: &Pos) {
+        if self.allowed.contains(rule) {
+            return
+        }
+
+        response!(
+            Weird(message),
+            self.source.file,
+            pos,
+            Help(format!("`# lint: allow {}` if that's on purpose", rule))
+        );
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn declare(&mut self, name: &str, pos: &Pos, parameter: bool) {
+        if name.starts_with('$') {
+            return
+        }
+
+        let outer = self.frames[..self.frames.len() - 1].iter().rev()
+            .find_map(|frame| frame.locals.iter().find(|local| local.name == name));
+
+        if let Some(outer) = outer {
+            let message = format!("`{}` shadows the one declared on line {}", name, (outer.pos.0).0);
+            self.warn("shadowed", message, pos)
+        }
+
+        let nested = self.frames.len() == 1 && self.blocks > 0;
+
+        self.frame().locals.push(Local {
+            name: name.to_string(),
+            pos: pos.clone(),
+            parameter,
+            nested,
+            used: false,
+        })
+    }
+
+    fn use_name(&mut self, name: &str) {
+        for frame in self.frames.iter_mut().rev() {
+            if frame.locals.iter().any(|local| local.name == name) {
+                for local in frame.locals.iter_mut().filter(|local| local.name == name) {
+                    local.used = true
+                }
+
+                return
+            }
+        }
+    }
+
+    // remembers (or forgets) what a variable is known to have
+    fn receive(&mut self, name: &str, value: &Expression) {
+        match self.members(value) {
+            Some(members) => self.frame().receivers.insert(name.to_string(), members),
+            None => self.frame().receivers.remove(name),
+        };
+    }
+
+    fn members(&self, expression: &Expression) -> Option<Members> {
+        use self::ExpressionNode::*;
+
+        match expression.node {
+            With(ref left, ref right) => {
+                let interface = match right.node {
+                    Identifier(ref name) => name,
+                    _ => return None,
+                };
+
+                let methods = self.interfaces.get(interface)?;
+
+                let mut members = match left.node {
+                    Dict(ref content) => Members {
+                        interfaces: Vec::new(),
+                        names: content.iter().map(|(key, _)| key.clone()).collect(),
+                    },
+
+                    _ => self.members(left)?,
+                };
+
+                members.interfaces.push(interface.clone());
+                members.names.extend(methods.iter().cloned());
+
+                Some(members)
+            }
+
+            Identifier(ref name) => self.frames.iter().rev().find_map(|frame| frame.receivers.get(name)).cloned(),
+
+            _ => None,
+        }
+    }
+
+    fn condition(&self, cond: &Expression) {
+        use self::ExpressionNode::*;
+
+        let always = match cond.node {
+            Bool(value) => value,
+            Not(ref inner) => match inner.node {
+                Bool(value) => !value,
+                _ => return,
+            },
+
+            _ => return,
+        };
+
+        self.warn("constant-condition", format!("this condition is always {}", always), &cond.pos)
+    }
+
+    fn body(&self, body: &[Statement], pos: &Pos) {
+        if body.iter().all(desugared) {
+            self.warn("empty-body", "this body is empty".to_string(), pos)
+        }
+    }
+
+    fn function(&mut self, pos: &Pos, params: &[String], body: &[Statement], after: usize) {
+        self.frames.push(Frame::default());
+
+        let mut column = after;
+
+        for param in params.iter() {
+            let found = pos.find(param, column).unwrap_or_else(|| pos.clone());
+            column = (found.1).1 + 1;
+
+            self.declare(param, &found, true)
+        }
+
+        let mut hoisted = Vec::new();
+        declarations(body, &mut hoisted);
+
+        for (name, pos) in hoisted.iter() {
+            self.declare(name, pos, false)
+        }
+
+        self.statements(body);
+
+        let frame = self.frames.pop().unwrap();
+
+        self.unused(&frame, |_| true)
+    }
+
+    // warns about the locals of `frame` nothing used, of those `check` asks about
+    fn unused<F: Fn(&Local) -> bool>(&self, frame: &Frame, check: F) {
+        for local in frame.locals.iter() {
+            if local.used || local.name.starts_with('_') || !check(local) {
+                continue
+            }
+
+            // hoisting may have seen the same name more than once
+            if frame.locals.iter().any(|other| other.name == local.name && other.used) {
+                continue
+            }
+
+            if local.parameter {
+                self.warn("unused-parameter", format!("unused parameter `{}`", local.name), &local.pos)
+            } else {
+                self.warn("unused-variable", format!("unused variable `{}`", local.name), &local.pos)
+            }
+        }
+    }
+
+    // the body of a branch or loop
+    fn block(&mut self, body: &[Statement]) {
+        self.blocks += 1;
+        self.statements(body);
+        self.blocks -= 1
+    }
+
+    fn statements(&mut self, body: &[Statement]) {
+        let mut done = false;
+        let mut warned = false;
+
+        for statement in body.iter() {
+            if done && !warned && !desugared(statement) {
+                self.warn("unreachable", "this is never reached".to_string(), &statement.pos);
+                warned = true
+            }
+
+            self.statement(statement);
+
+            if matches!(statement.node, StatementNode::Return(_) | StatementNode::Break) {
+                done = true
+            }
+        }
+    }
+
+    // declarations in functions were hoisted already, the file's are taken as they come
+    fn declare_global(&mut self, name: &str, pos: &Pos) {
+        if self.frames.len() == 1 {
+            let found = pos.find(name, (pos.1).0).unwrap_or_else(|| pos.clone());
+            self.declare(name, &found, false)
+        }
+    }
+
+    fn kebab(&self, name: &str, pos: &Pos) {
+        if name.ends_with('-') {
+            self.warn("kebab-end", "kebab-case at identifier end is not cool".to_string(), pos)
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        use self::StatementNode::*;
+
+        let pos = &statement.pos;
+
+        match statement.node {
+            Expression(ref expr) => self.expression(expr),
+
+            Declaration(ref name, ref right) => {
+                self.kebab(name, pos);
+
+                if let Some(ref right) = *right {
+                    self.expression(right);
+                }
+
+                self.declare_global(name, pos);
+
+                match *right {
+                    Some(ref right) => self.receive(name, right),
+                    None => {
+                        self.frame().receivers.remove(name);
+                    }
+                }
+            }
+
+            Const(ref name, ref right) => {
+                self.kebab(name, pos);
+                self.expression(right);
+                self.declare_global(name, pos);
+                self.receive(name, right)
+            }
+
+            ConstFunction(ref fun) | Test(ref fun) => self.statement(fun),
+
+            Assignment(ref left, ref right) => {
+                self.expression(right);
+
+                match left.node {
+                    ExpressionNode::Identifier(ref name) => self.receive(name, right),
+
+                    ExpressionNode::Binary(ref receiver, Operator::Index, ref key) => {
+                        self.expression(left);
+
+                        // setting a field gives the value that field
+                        if let (ExpressionNode::Identifier(ref name), ExpressionNode::Str(ref key)) = (&receiver.node, &key.node) {
+                            for frame in self.frames.iter_mut().rev() {
+                                if let Some(members) = frame.receivers.get_mut(name) {
+                                    members.names.insert(key.clone());
+                                    break
+                                }
+                            }
+                        }
+                    }
+
+                    _ => self.expression(left),
+                }
+            }
+
+            Function(ref name, ref params, ref body) => {
+                self.kebab(name, pos);
+                self.declare_global(name, pos);
+                self.body(body, pos);
+
+                let after = pos.find(name, (pos.1).0).map_or((pos.1).0, |found| (found.1).1 + 1);
+
+                self.function(pos, params, body, after)
+            }
+
+            Interface(ref name, ref body) => {
+                let methods = body.iter().filter_map(|statement| match statement.node {
+                    Function(ref name, ..) => Some(name.clone()),
+                    _ => None,
+                }).collect();
+
+                self.interfaces.insert(name.clone(), methods);
+                self.declare_global(name, pos);
+                self.body(body, pos);
+
+                for statement in body.iter() {
+                    if let Function(ref method, ref params, ref body) = statement.node {
+                        let pos = &statement.pos;
+                        let after = pos.find(method, (pos.1).0).map_or((pos.1).0, |found| (found.1).1 + 1);
+
+                        self.body(body, pos);
+                        self.function(pos, params, body, after)
+                    }
+                }
+            }
+
+            Return(ref value) => if let Some(ref value) = *value {
+                self.expression(value)
+            },
+
+            If(ref cond, ref body, ref else_) => {
+                self.condition(cond);
+                self.expression(cond);
+                self.body(body, pos);
+                self.block(body);
+
+                for (cond, body) in else_.iter() {
+                    match *cond {
+                        Some(ref cond) => {
+                            self.condition(cond);
+                            self.expression(cond);
+                            self.body(body, &cond.pos)
+                        }
+
+                        None => self.body(body, pos),
+                    }
+
+                    self.block(body)
+                }
+            }
+
+            While(ref cond, ref body) => {
+                // `loop:` is a `while true:` the parser wrote, right where the statement starts
+                if cond.pos != *pos {
+                    self.condition(cond)
+                }
+
+                self.expression(cond);
+                self.body(body, pos);
+                self.block(body)
+            }
+
+            // loop variables out in the file are meant for the loop, nothing shadows them
+            For(_, ref iterable, ref body) => {
+                self.expression(iterable);
+                self.body(body, pos);
+                self.block(body)
+            }
+
+            Block(ref body) => self.block(body),
+
+            Use(..) | Break => (),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        use self::ExpressionNode::*;
+
+        match expression.node {
+            Identifier(ref name) => self.use_name(name),
+
+            Binary(ref left, Operator::Index, ref right) => {
+                self.expression(left);
+
+                if !matches!(right.node, Str(_)) {
+                    self.expression(right)
+                }
+            }
+
+            Neg(ref expr) | Not(ref expr) => self.expression(expr),
+
+            Binary(ref left, _, ref right) | With(ref left, ref right) => {
+                self.expression(left);
+                self.expression(right)
+            }
+
+            Call(ref callee, ref args) => {
+                if let Binary(ref receiver, Operator::Index, ref key) = callee.node {
+                    if let (Str(ref method), Some(members)) = (&key.node, self.members(receiver)) {
+                        if !members.names.contains(method) {
+                            let message = format!("`{}` isn't a method of `{}`", method, members.interfaces.join("` or `"));
+                            self.warn("unknown-method", message, &key.pos)
+                        }
+                    }
+                }
+
+                self.expression(callee);
+
+                for arg in args.iter() {
+                    self.expression(arg)
+                }
+            }
+
+            Array(ref content) => for element in content.iter() {
+                self.expression(element)
+            },
+
+            Dict(ref content) => for (_, value) in content.iter() {
+                self.expression(value)
+            },
+
+            AnonFunction(_, ref params, ref body) => {
+                self.function(&expression.pos, params, body, (expression.pos.1).0)
+            }
+
+            Nil | Int(_) | Float(_) | Str(_) | Bool(_) | Empty | EOF => (),
+        }
+    }
+}
@@ -1,9 +1,13 @@
 pub mod symtab;
+#[allow(clippy::module_inception)]
 pub mod visitor;
+pub mod capture;
+pub mod lint;
 
 use super::lexer::*;
 use super::parser::*;
 use super::source::*;
+use super::error::Diagnostic;
 
 pub use self::symtab::*;
 pub use self::visitor::*;
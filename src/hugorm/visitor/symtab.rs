@@ -1,15 +1,38 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
 
-use std::rc::Rc;
+use zub::ir::Binding;
 
 use super::visitor::*;
 use super::super::parser::*;
 
+// a local interface has its methods compiled into every `with` using it, a grabbed
+// one points at the functions its module already compiled
 #[derive(Debug, Clone)]
+pub enum Interface {
+    Local(Vec<Statement>),
+    Grabbed(Vec<(String, Binding)>),
+}
+
+impl Interface {
+    pub fn methods(&self) -> Vec<String> {
+        match *self {
+            Interface::Local(ref body) => body.iter()
+                .filter_map(|statement| match statement.node {
+                    StatementNode::Function(ref name, ..) => Some(name.clone()),
+                    _ => None,
+                })
+                .collect(),
+
+            Interface::Grabbed(ref methods) => methods.iter().map(|(name, _)| name.clone()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct Frame {
     pub table: RefCell<HashMap<String, Type>>,
-    pub interfaces: HashMap<String, Vec<Statement>>,
+    pub interfaces: HashMap<String, Interface>,
 }
 
 impl Frame {
@@ -28,19 +51,15 @@ impl Frame {
     }
 
     pub fn get(&self, name: &String) -> Option<Type> {
-        if let Some(v) = self.table.borrow().get(name) {
-            Some(v.clone())
-        } else {
-            None
-        }
+        self.table.borrow().get(name).cloned()
     }
 
-    pub fn insert_interface(&mut self, name: String, interface: Vec<Statement>) {
+    pub fn insert_interface(&mut self, name: String, interface: Interface) {
         self.interfaces.insert(name, interface);
     }
 
-    pub fn get_interface(&self, name: String) -> Option<&Vec<Statement>> {
-        self.interfaces.get(&name)
+    pub fn get_interface(&self, name: &str) -> Option<&Interface> {
+        self.interfaces.get(name)
     }
 
     pub fn assign(&mut self, name: String, t: Type) {
@@ -63,7 +82,7 @@ pub struct SymTab {
     pub cached_frames: Vec<Frame>,
     pub last: Frame,       // last frame
     pub cache_mode: bool,
-    pub foreign_imports: HashMap<String, HashMap<String, Type>>,
+    pub foreign_imports: HashMap<String, Exports>,
 }
 
 impl SymTab {
@@ -165,11 +184,22 @@ impl SymTab {
         }
     }
 
-    pub fn get_foreign_module(&self, id: &String) -> Option<&HashMap<String, Type>> {
+    pub fn get_foreign_module(&self, id: &String) -> Option<&Exports> {
         self.foreign_imports.get(id)
     }
 
-    pub fn import(&mut self, id: String, origin: HashMap<String, Type>) {
+    // the innermost one called `name`
+    pub fn get_interface(&self, name: &str) -> Option<&Interface> {
+        self.stack.iter().rev().find_map(|frame| frame.get_interface(name))
+    }
+
+    pub fn import(&mut self, id: String, origin: Exports) {
         self.foreign_imports.insert(id, origin);
     }
 }
+
+impl Default for SymTab {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,21 +1,36 @@
-use std::collections::HashMap;
-use std::fmt::{self, Display, Formatter, Write};
 use std::rc::Rc;
 
 use super::super::error::Response::*;
+use super::super::error::{self, Severity};
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::mem;
 
 use super::*;
+use super::capture;
+use super::super::manifest::{self, Manifest};
+use super::super::prelude::{assert, base};
 
-use std::fs::File;
-use std::io::prelude::*;
-use std::path::Path;
-use std::mem;
+use std::fs;
+use std::io;
+use std::env;
+use std::path::{Path, PathBuf};
 
 use zub::ir::{ IrBuilder, ExprNode, Binding, IrFunctionBody, IrFunction, Expr, TypeInfo, BinaryOp, Literal };
 
 pub type VarPos = Binding;
 
+// what a module hands to whoever grabs it
+#[derive(Debug, Clone, Default)]
+pub struct Exports {
+    pub names: HashMap<String, Type>,
+    pub interfaces: HashMap<String, Interface>,
+}
+
+// what each grabbed module exports, by its path
+pub type Modules = HashMap<String, Exports>;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum TypeNode {
     Int,
@@ -28,6 +43,21 @@ pub enum TypeNode {
     Func(usize),
 }
 
+impl fmt::Display for TypeNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TypeNode::Int => write!(f, "int"),
+            TypeNode::Float => write!(f, "float"),
+            TypeNode::Bool => write!(f, "bool"),
+            TypeNode::Str => write!(f, "string"),
+            TypeNode::Any => write!(f, "any"),
+            TypeNode::Char => write!(f, "char"),
+            TypeNode::Nil => write!(f, "nil"),
+            TypeNode::Func(params) => write!(f, "fun/{}", params),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum TypeMode {
     Undeclared,
@@ -39,7 +69,9 @@ pub enum TypeMode {
 pub struct Type {
     pub node: TypeNode,
     pub mode: TypeMode,
-    pub meta: Option<VarPos>
+    pub meta: Option<VarPos>,
+    pub pos: Option<Pos>, // where it was declared
+    pub boxed: bool,      // lives in a one-element list, see `capture`
 }
 
 impl Type {
@@ -48,6 +80,8 @@ impl Type {
             node,
             mode,
             meta: None,
+            pos: None,
+            boxed: false,
         }
     }
 
@@ -58,11 +92,16 @@ impl Type {
     pub fn set_offset(&mut self, offset: VarPos) {
         self.meta = Some(offset)
     }
+
+    pub fn set_pos(&mut self, pos: Pos) {
+        self.pos = Some(pos)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Inside {
     Loop,
+    Branch,
     Function,
     Nothing,
 }
@@ -76,6 +115,22 @@ pub struct Visitor<'a> {
     pub builder: IrBuilder,
     pub repl: bool,
     pub root: String,
+    pub boxes: Vec<HashSet<String>>, // per function, what `capture` says to box
+    pub spans: Vec<(String, Pos)>, // what the `At`s in the IR point at, one off
+    pub namespace: Option<String>, // the module being compiled, when it was grabbed
+    pub modules: Modules, // what every module grabbed so far exports
+    pub grabbing: Vec<Grab>, // the modules being compiled right now, outermost first
+    pub manifests: HashMap<PathBuf, Result<Manifest, Diagnostic>>, // every `hugorm.toml` read so far, by path
+    pub search: Vec<PathBuf>, // where else modules are looked for, after the project
+}
+
+// a `grab` in the middle of being compiled
+#[derive(Debug, Clone)]
+pub struct Grab {
+    pub module: String,
+    pub path: String, // canonical, what `modules` is keyed by
+    pub file: String, // where the `grab` is
+    pub pos: Pos,
 }
 
 impl<'a> Visitor<'a> {
@@ -89,6 +144,13 @@ impl<'a> Visitor<'a> {
             builder: IrBuilder::new(),
             repl: false,
             root,
+            boxes: Vec::new(),
+            spans: Vec::new(),
+            namespace: None,
+            modules: HashMap::new(),
+            grabbing: Vec::new(),
+            manifests: HashMap::new(),
+            search: Vec::new(),
         }
     }
 
@@ -101,7 +163,14 @@ impl<'a> Visitor<'a> {
             function_depth: 0,
             builder: IrBuilder::new(),
             repl: false,
-            root
+            root,
+            boxes: Vec::new(),
+            spans: Vec::new(),
+            namespace: None,
+            modules: HashMap::new(),
+            grabbing: Vec::new(),
+            manifests: HashMap::new(),
+            search: Vec::new(),
         }
     }
 
@@ -109,11 +178,11 @@ impl<'a> Visitor<'a> {
         self.assign(name.to_string(), Type::from(t))
     }
 
-    pub fn visit(&mut self, ast: &Vec<Statement>) -> Result<(), ()> {
+    pub fn visit(&mut self, ast: &[Statement]) -> Result<(), Diagnostic> {
         self.symtab.push();
 
         for statement in ast.iter() {
-            self.visit_statement(&statement)?
+            self.visit_statement(statement)?
         }
 
         self.symtab.pop();
@@ -125,45 +194,86 @@ impl<'a> Visitor<'a> {
         self.builder.build()
     }
 
-    pub fn visit_statement(&mut self, statement: &Statement) -> Result<(), ()> {
+    // `ir`, with runtime errors in it pointing at `pos`
+    fn at(&mut self, ir: ExprNode, pos: &Pos) -> ExprNode {
+        self.spans.push((self.source.file.0.clone(), pos.clone()));
+
+        self.builder.at(self.spans.len(), ir)
+    }
+
+    pub fn visit_statement(&mut self, statement: &Statement) -> Result<(), Diagnostic> {
         use self::StatementNode::*;
 
         let position = statement.pos.clone();
 
         match statement.node {
-            Use(ref path) => {
-                let local_root = Path::new(&self.source.file.0).parent().unwrap().display().to_string();
-                // &self.root.clone()
-                let module = self.find_module(path, &local_root, &statement)?;
-
-                let mut file = match File::open(&module) {
-                    Err(why) => panic!("failed to open {}: {}", module, why),
-                    Ok(file) => file,
-                };
+            Use(ref module, ref alias, ref names) => {
+                if self.function_depth > 0 || self.depth > 0 {
+                    return Err(response!(
+                        Wrong("`grab` only works at the top level of a file"),
+                        self.source.file,
+                        statement.pos
+                    ))
+                }
 
-                let mut content = String::new();
+                let exports = self.visit_module(module, statement)?;
 
-                match file.read_to_string(&mut content) {
-                    Err(why) => panic!("failed to read {}: {}", module, why),
-                    Ok(_) => {
-                        let source = Source::new(module);
-                        let lexer = Lexer::default(content.chars().collect(), &source);
+                if names.is_empty() {
+                    // `grab a.b.c` is known as `c`
+                    let name = alias.clone().unwrap_or_else(|| module.rsplit('.').next().unwrap().to_string());
 
-                        let mut tokens = Vec::new();
+                    self.visit_rebind(&name, &position)?;
 
-                        for token_result in lexer {
-                            if let Ok(token) = token_result {
-                                tokens.push(token)
-                            } else {
-                                panic!("weird unexpected lexer error")
-                            }
-                        }
+                    let binding = self.binding(&name);
+
+                    let mut t = Type::new(TypeNode::Any, TypeMode::Immutable);
+                    t.set_offset(binding.clone());
+                    t.set_pos(position.clone());
+
+                    self.assign(name.to_owned(), t);
+
+                    // `foo.x` goes straight for the variable, this is for passing `foo` around
+                    let mut keys = Vec::new();
+                    let mut values = Vec::new();
+
+                    let mut exported = exports.names.iter().collect::<Vec<_>>();
+                    exported.sort_by(|a, b| a.0.cmp(b.0));
+
+                    for (name, t) in exported {
+                        keys.push(self.builder.string(name));
+                        values.push(self.builder.var(t.meta.clone().unwrap()))
+                    }
+
+                    let dict = self.builder.dict(keys, values);
+                    self.bind(binding.clone(), dict);
 
-                        let parsed = Parser::new(tokens, &source).parse()?;
+                    self.symtab.import(binding.name, exports);
+                } else {
+                    for name in names.iter() {
+                        if let Some(interface) = exports.interfaces.get(name) {
+                            self.symtab.current_frame_mut().insert_interface(name.clone(), interface.clone());
 
-                        for statement in parsed.iter() {
-                            self.visit_statement(&statement)?
+                            continue
                         }
+
+                        let export = match exports.names.get(name) {
+                            Some(export) => export.clone(),
+                            None => return Err(self.not_exported(module, name, &position)),
+                        };
+
+                        self.visit_rebind(name, &position)?;
+
+                        let binding = self.binding(name);
+
+                        let mut t = export.clone();
+                        t.mode = TypeMode::Regular;
+                        t.set_offset(binding.clone());
+                        t.set_pos(position.clone());
+
+                        self.assign(name.to_owned(), t);
+
+                        let value = self.builder.var(export.meta.unwrap());
+                        self.bind(binding, value)
                     }
                 }
 
@@ -205,20 +315,23 @@ impl<'a> Visitor<'a> {
 
                     Ok(())
                 } else {
-                    return Err(response!(
+                    Err(response!(
                         Wrong("can't return outside of function"),
                         self.source.file,
                         statement.pos
-                    ));
+                    ))
                 }
             },
 
             Function(ref name, ref params, ref body) => {
+                self.visit_rebind(name, &position)?;
+
                 let mut t = Type::from(TypeNode::Func(params.len()));
 
-                let mut binding = Binding::local(name, self.depth, self.function_depth);
+                let binding = self.binding(name);
 
                 t.set_offset(binding.clone());
+                t.set_pos(position.clone());
 
                 self.assign(name.to_owned(), t);
 
@@ -229,12 +342,7 @@ impl<'a> Visitor<'a> {
                 self.push_scope();
                 self.inside.push(Inside::Function);
 
-                for param in params.iter() {
-                    let mut t = Type::from(TypeNode::Any);
-                    t.set_offset(Binding::local(param.as_str(), self.depth, self.function_depth));
-
-                    self.assign(param.clone(), t)
-                }
+                self.visit_params(params, body);
 
                 for statement in body.iter() {
                     self.visit_statement(statement)?;
@@ -243,6 +351,7 @@ impl<'a> Visitor<'a> {
 
                 self.inside.pop();
                 self.pop_scope();
+                self.boxes.pop();
                 self.function_depth -= 1;
 
                 self.builder.ret(None);
@@ -252,18 +361,23 @@ impl<'a> Visitor<'a> {
                 self.builder = old_current;
 
                 let func_body = IrFunctionBody {
-                    params: params.iter().cloned().map(|x|
+                    params: params.iter().map(|x|
                         Binding::local(x.as_str(), binding.depth.unwrap_or(0) + 1, binding.function_depth + 1)).collect::<Vec<Binding>>(),
                     method: false,
                     inner: body
                 };
 
                 let ir_func = IrFunction {
-                    var: binding,
+                    var: binding.clone(),
                     body: Rc::new(RefCell::new(func_body))
                 };
 
-                self.builder.emit(Expr::Function(ir_func).node(TypeInfo::nil()));
+                // zub defines a global before compiling the closure, so globals go through a plain bind
+                if self.in_block() || binding.depth.is_none() {
+                    self.bind(binding, Expr::AnonFunction(ir_func).node(TypeInfo::nil()))
+                } else {
+                    self.builder.emit(Expr::Function(ir_func).node(TypeInfo::nil()))
+                }
                 
                 Ok(())
             },
@@ -276,12 +390,16 @@ impl<'a> Visitor<'a> {
                     interface.push(fun.clone())
                 }
 
-                self.symtab.current_frame_mut().insert_interface(name.clone(), interface);
+                self.symtab.current_frame_mut().insert_interface(name.clone(), symtab::Interface::Local(interface));
 
                 Ok(())
             }
 
             While(ref cond, ref body) => {
+                if !self.in_block() {
+                    self.hoist(statement)
+                }
+
                 self.visit_expression(cond)?;
 
                 if [TypeNode::Bool, TypeNode::Any].contains(&self.type_expression(cond)?.node) {
@@ -315,7 +433,7 @@ impl<'a> Visitor<'a> {
 
                     Ok(())
                 } else {
-                    return Err(response!(
+                    Err(response!(
                         Wrong("can't have non-boolean condition"),
                         self.source.file,
                         position
@@ -323,7 +441,119 @@ impl<'a> Visitor<'a> {
                 }
             }
 
+            For(ref name, ref iterable, ref body) => {
+                self.visit_rebind(name, &position)?;
+
+                if !self.in_block() {
+                    self.hoist(statement)
+                }
+
+                self.visit_expression(iterable)?;
+
+                let t = self.type_expression(iterable)?.node;
+
+                if let TypeNode::Int | TypeNode::Float | TypeNode::Bool | TypeNode::Func(_) = t {
+                    return Err(response!(
+                        Wrong(format!("can't iterate over `{:?}`", t)),
+                        self.source.file,
+                        iterable.pos
+                    ))
+                }
+
+                let hidden = Self::for_names(&position);
+
+                let items = self.binding(&hidden[0]);
+                let custom = self.binding(&hidden[1]);
+                let index = self.binding(&hidden[2]);
+                let var = self.binding(name);
+
+                // lists, dicts and strings are walked by index over `$iter`,
+                // objects with `has-next` and `next` are asked for each element
+                let iterable_ir = self.compile_expression(iterable)?;
+                let iter_ir = self.builder.call(self.builder.var(Binding::global("$iter")), vec!(iterable_ir), None);
+                let iter_ir = self.at(iter_ir, &iterable.pos);
+                self.bind(items.clone(), iter_ir);
+
+                let custom_ir = self.builder.call(self.builder.var(Binding::global("$iterator?")), vec!(self.builder.var(items.clone())), None);
+                self.bind(custom.clone(), custom_ir);
+
+                let zero = self.builder.number(0.0);
+                self.bind(index.clone(), zero);
+
+                let boxed = self.is_boxed(name);
+
+                let nil = Expr::Literal(Literal::Nil).node(TypeInfo::nil());
+
+                if boxed {
+                    self.bind_boxed(var.clone(), nil)
+                } else {
+                    self.bind(var.clone(), nil)
+                }
+
+                let has_next = self.method_call(&items, "has-next");
+                let len = self.builder.call(self.builder.var(Binding::global("len")), vec!(self.builder.var(items.clone())), None);
+                let in_bounds = self.builder.binary(self.builder.var(index.clone()), BinaryOp::Lt, len);
+
+                let cond = self.builder.ternary(self.builder.var(custom.clone()), has_next, Some(in_bounds));
+
+                let old_current = self.builder.clone();
+                self.builder = IrBuilder::new();
+
+                self.push_scope();
+                self.depth -= 1; // same as while
+
+                self.inside.push(Inside::Loop);
+
+                let mut t = Type::from(TypeNode::Any);
+                t.set_offset(var.clone());
+                t.set_pos(position.clone());
+                t.boxed = boxed;
+
+                self.assign(name.to_owned(), t);
+
+                let next = self.method_call(&items, "next");
+                let element = self.builder.binary(self.builder.var(items.clone()), BinaryOp::Index, self.builder.var(index.clone()));
+                let current = self.builder.ternary(self.builder.var(custom), next, Some(element));
+
+                if boxed {
+                    let set = self.builder.set_element(self.builder.var(var), self.builder.number(0.0), current);
+                    self.builder.emit(set)
+                } else {
+                    self.builder.mutate(self.builder.var(var), current);
+                    self.builder.emit(Expr::Pop.node(TypeInfo::nil()))
+                }
+
+                let one = self.builder.number(1.0);
+                let increment = self.builder.binary(self.builder.var(index.clone()), BinaryOp::Add, one);
+
+                self.builder.mutate(self.builder.var(index), increment);
+                self.builder.emit(Expr::Pop.node(TypeInfo::nil()));
+
+                for statement in body.iter() {
+                    self.visit_statement(statement)?;
+                }
+
+                self.inside.pop();
+
+                self.depth += 1;
+                self.pop_scope();
+
+                let body = Expr::Block(self.builder.build()).node(TypeInfo::nil());
+
+                self.builder = old_current;
+
+                self.builder.emit(
+                    Expr::While(cond, body).node(TypeInfo::nil())
+                );
+
+                Ok(())
+            }
+
             If(ref cond, ref body, ref else_) => {
+                if !self.in_block() {
+                    self.hoist(statement)
+                }
+
                 self.visit_expression(cond)?;
 
                 if [TypeNode::Bool, TypeNode::Any].contains(&self.type_expression(cond)?.node) {
@@ -335,10 +565,14 @@ impl<'a> Visitor<'a> {
                     self.push_scope();
                     self.depth -= 1; // brother bruh
 
+                    self.inside.push(Inside::Branch);
+
                     for statement in body.iter() {
                         self.visit_statement(statement)?;
                     }
 
+                    self.inside.pop();
+
                     self.depth += 1; // brother bruh again
                     self.pop_scope();
 
@@ -346,13 +580,19 @@ impl<'a> Visitor<'a> {
 
                     self.builder = old_current;
 
-                    let mut else_blocks = Expr::Literal(Literal::Nil);
+                    // an empty block keeps the stack balanced when no branch is taken
+                    let mut else_blocks = Expr::Block(Vec::new());
+
+                    self.inside.push(Inside::Branch);
 
                     for (i, els) in else_.iter().enumerate() {
                         let old_current = self.builder.clone();
                         self.builder = IrBuilder::new();
 
                         self.push_scope();
+                        self.depth -= 1;
+
+                        let elif = els.0.is_some();
 
                         if let Some(ref cond) = els.0 {
                             let pos = cond.pos.clone();
@@ -363,17 +603,13 @@ impl<'a> Visitor<'a> {
                             );
 
                             self.visit_statement(&elif)?;
-
-                            self.pop_scope();
-
-                            break // 9000 IQ
-
                         } else {
                             for statement in els.1.iter() {
                                 self.visit_statement(statement)?;
                             }
                         }
 
+                        self.depth += 1;
                         self.pop_scope();
 
                         let body = self.builder.build();
@@ -381,14 +617,20 @@ impl<'a> Visitor<'a> {
                         self.builder = old_current;
 
                         else_blocks = Expr::Block(body);
+
+                        if elif {
+                            break // 9000 IQ
+                        }
                     }
 
+                    self.inside.pop();
+
                     self.builder.emit(Expr::If(cond, body, Some(else_blocks.node(TypeInfo::nil()))).node(TypeInfo::nil() ));
 
                     Ok(())
 
                 } else {
-                    return Err(response!(
+                    Err(response!(
                         Wrong("can't have non-boolean condition"),
                         self.source.file,
                         position
@@ -402,7 +644,7 @@ impl<'a> Visitor<'a> {
 
                     Ok(())
                 } else {
-                    return Err(response!(
+                    Err(response!(
                         Wrong("you need a loop to break out of here"),
                         self.source.file,
                         position
@@ -410,194 +652,689 @@ impl<'a> Visitor<'a> {
                 }
             }
 
-            Const(..) => return Err(response!(
-                Wrong("constants are not implemented yet"),
-                self.source.file,
-                position
-            )),
+            Const(ref name, ref right) => {
+                self.visit_rebind(name, &position)?;
+                self.visit_expression(right)?;
 
-            ConstFunction(ref fun) => return Err(response!(
-                Wrong("constants are not implemented yet"),
-                self.source.file,
-                position
-            )),
+                let binding = self.binding(name.as_str());
 
-            _ => {
-                return Err(response!(
-                    Wrong("what the actual fuck"),
-                    self.source.file,
-                    position
-                ))
-            }
-        }
-    }
+                let mut t = self.type_expression(right)?;
 
-    #[inline]
-    fn find_module(&mut self, path: &String, root: &String, statement: &Statement) -> Result<String, ()> {
-        let my_folder = Path::new(&root);
+                t.mode = TypeMode::Immutable;
+                t.set_offset(binding.clone());
+                t.set_pos(position);
+                t.boxed = self.is_boxed(name);
 
-        let file_path = format!("{}/{}.hug", my_folder.to_str().unwrap(), path);
-        let module = Path::new(&file_path);
+                let boxed = t.boxed;
 
-        let init_path = format!("{}/{}/mod.hug", my_folder.to_str().unwrap(), path);
+                self.assign(name.to_owned(), t);
 
-        let module = if !module.exists() {
-            let module = Path::new(&init_path);
-        
-            if !module.exists() {
-                return Err(response!(
-                    Wrong(format!(
-                        "no such module `{0}`, needed either `{0}.hug` or `{0}/init.hug`",
-                        path
-                    )),
-                    self.source.file,
-                    statement.pos
-                ));
-            } else {
-                module
-            }
-        } else {
-            module
-        };
+                let right_ir = self.compile_expression(right)?;
 
-        Ok(module.display().to_string())
-    }
+                if boxed {
+                    self.bind_boxed(binding, right_ir)
+                } else {
+                    self.bind(binding, right_ir)
+                }
 
-    fn compile_expression(&mut self, expression: &Expression) -> Result<ExprNode, ()> {
-        use self::ExpressionNode::*;
+                Ok(())
+            }
 
-        let result = match expression.node {
-            Float(ref n) => self.builder.number(*n),
-            Int(ref n) => self.builder.number(*n as f64),
-            Str(ref s) => self.builder.string(s),
-            Bool(ref b) => self.builder.bool(*b),
+            ConstFunction(ref fun) => {
+                if let Function(ref name, ..) = fun.node {
+                    self.visit_statement(fun)?;
 
-            Identifier(ref n) =>  {
-                if let Some(binding) = self.symtab.fetch(n) {
-                    if let Some(mut binding) = binding.meta {
-                        binding = Binding::local(n, self.depth, binding.function_depth);
+                    let mut t = self.symtab.fetch(name).unwrap();
 
-                        self.builder.var(binding)
-                    } else {
-                        let binding = Binding::global(n);
+                    t.mode = TypeMode::Immutable;
 
-                        self.builder.var(binding)
-                    }
+                    self.assign(name.to_owned(), t);
 
+                    Ok(())
                 } else {
-                    return Err(response!(
-                        Wrong(format!("no such variable `{}`", n)),
+                    Err(response!(
+                        Wrong("only functions can follow `const` like this"),
                         self.source.file,
-                        expression.pos
-                    ));
+                        fun.pos
+                    ))
                 }
             }
 
-            Call(ref callee, ref args) => {
-                let mut args_ir = Vec::new();
-
-                for arg in args.iter() {
-                    args_ir.push(self.compile_expression(arg)?)
+            Test(ref fun) => {
+                if self.function_depth > 0 || self.in_block() {
+                    return Err(response!(
+                        Wrong("test functions have to be at the top level"),
+                        self.source.file,
+                        statement.pos
+                    ))
                 }
 
-                let callee_ir = self.compile_expression(callee)?;
+                if let Function(_, ref params, _) = fun.node {
+                    if !params.is_empty() {
+                        return Err(response!(
+                            Wrong("test functions can't take parameters"),
+                            self.source.file,
+                            fun.pos
+                        ))
+                    }
+                }
 
-                self.builder.call(callee_ir, args_ir, None)
+                self.visit_statement(fun)
             }
+        }
+    }
 
-            Binary(ref left, ref op, ref right) => {
-                let left_ir = self.compile_expression(left)?;
+    // compiles the module on its own, with nothing but the builtins to see, and
+    // hands back what it exports: its top-level names and interfaces, minus the ones
+    // starting with `_`. A module only runs the first time it's grabbed
+    fn visit_module(&mut self, path: &str, statement: &Statement) -> Result<Exports, Diagnostic> {
+        let module = self.find_module(path, statement)?;
 
-                let right_ir = if op == &Index {
-                    match right.node {
-                        Str(ref n) => {
-                            Expr::Literal(
-                                Literal::String(n.clone())
-                            ).node(TypeInfo::nil())
-                        }
+        let canonical = fs::canonicalize(&module).map(|path| path.display().to_string()).unwrap_or_else(|_| module.clone());
 
-                        _ => self.compile_expression(right)?
-                    }
-                } else {
-                    self.compile_expression(right)?
-                };
+        if let Some(exports) = self.modules.get(&canonical) {
+            return Ok(exports.clone())
+        }
 
-                use self::Operator::*;
+        let grab = Grab {
+            module: path.to_string(),
+            path: canonical.clone(),
+            file: self.source.file.0.clone(),
+            pos: statement.pos.clone(),
+        };
 
-                let op_ir = match op {
-                    Add   => BinaryOp::Add,
-                    Sub   => BinaryOp::Sub,
-                    Mul   => BinaryOp::Mul,
-                    Div   => BinaryOp::Div,
-                    Mod   => BinaryOp::Rem,
-                    And   => BinaryOp::And,
-                    Or    => BinaryOp::Or,
-                    Eq    => BinaryOp::Equal,
-                    NEq   => BinaryOp::NEqual,
-                    Lt    => BinaryOp::Lt,
-                    LtEq  => BinaryOp::LtEqual,
-                    Gt    => BinaryOp::Gt,
-                    GtEq  => BinaryOp::GtEqual,
-                    Index => BinaryOp::Index,
-                    Pow   => BinaryOp::Pow, 
-                    Concat => BinaryOp::Add, // :)
+        if let Some(start) = self.grabbing.iter().position(|grab| grab.path == canonical) {
+            return Err(Self::cycle(&self.grabbing[start..], &grab))
+        }
+
+        let content = match fs::read_to_string(&module) {
+            Ok(content) => content,
+
+            Err(why) => {
+                let why = if why.kind() == io::ErrorKind::InvalidData {
+                    "it isn't valid UTF-8".to_string()
+                } else {
+                    why.to_string()
                 };
 
-                self.builder.binary(left_ir, op_ir, right_ir)
+                return Err(response!(
+                    Wrong(format!("can't read module `{}` at `{}`: {}", path, module, why)),
+                    self.source.file,
+                    statement.pos
+                ))
             }
+        };
 
-            Array(ref content) => {
-                let mut cont_ir = Vec::new();
+        let source = Source::from(&module, content.lines().map(|x| x.into()).collect::<Vec<String>>());
+        let lexer = Lexer::default(content.chars().collect(), &source);
 
-                for element in content.iter() {
-                    cont_ir.push(self.compile_expression(element)?)
-                }
+        let mut tokens = Vec::new();
+        let mut lexed = true;
 
-                self.builder.list(cont_ir)
+        // the lexer and parser say what's wrong in the module itself
+        for token_result in lexer {
+            match token_result {
+                Ok(token) => tokens.push(token),
+                Err(_) => lexed = false,
             }
+        }
 
-            Dict(ref content) => {
-                let mut keys = Vec::new();
-                let mut vals = Vec::new();
+        // nothing the parser has to say about a broken file is worth hearing
+        let parsed = if lexed { Parser::new(tokens, &source).parse().ok() } else { None };
 
-                for (key, val) in content.iter() {
-                    keys.push(
-                        Expr::Literal(
-                            Literal::String(key.clone())
-                        ).node(TypeInfo::nil())
-                    );
-                    vals.push(self.compile_expression(val)?);
-                }
+        let parsed = match parsed {
+            Some(parsed) => parsed,
 
-                self.builder.dict(keys, vals)
-            }
+            None => return Err(response!(
+                Wrong(format!("can't grab `{}`, it has errors in it", path)),
+                self.source.file,
+                statement.pos
+            )),
+        };
 
-            With(ref dict, ref interface) => {
-                if let Dict(ref content) = dict.node {
-                    let mut new_content = content.clone();
-                    if let Identifier(ref name) = interface.node {
-                        if let Some(interface) = self.symtab.current_frame().get_interface(name.clone()) {
-                            for fun in interface.iter() {
-                                if let StatementNode::Function(ref name, ref params, ref body) = fun.node {
-                                    let closure = Expression::new(
-                                        ExpressionNode::AnonFunction("<bob>".to_string(), params.clone(), body.clone()),
-                                        expression.pos.clone()
-                                    );
+        let builtins = self.symtab.stack[0].table.borrow().iter()
+            .filter(|(_, t)| t.meta.is_none())
+            .map(|(name, t)| (name.clone(), t.clone()))
+            .collect();
+
+        let mut visitor = Visitor::from(&source, SymTab::from(builtins), self.root.clone());
+
+        visitor.namespace = Some(canonical.clone());
+        visitor.spans = mem::take(&mut self.spans);
+        visitor.modules = mem::take(&mut self.modules);
+        visitor.grabbing = mem::take(&mut self.grabbing);
+        visitor.manifests = mem::take(&mut self.manifests);
+        visitor.search = self.search.clone();
+
+        visitor.grabbing.push(grab);
+
+        let visited = parsed.iter().try_for_each(|statement| visitor.visit_statement(statement));
+
+        visitor.grabbing.pop();
+
+        self.spans = mem::take(&mut visitor.spans);
+        self.modules = mem::take(&mut visitor.modules);
+        self.grabbing = mem::take(&mut visitor.grabbing);
+        self.manifests = mem::take(&mut visitor.manifests);
+
+        visited?;
+
+        for ir in visitor.build() {
+            self.builder.emit(ir)
+        }
+
+        let top = &visitor.symtab.stack[0];
+
+        let names = top.table.borrow().iter()
+            .filter(|(name, t)| t.meta.is_some() && !name.starts_with('_') && !name.starts_with('$'))
+            .map(|(name, t)| (name.clone(), t.clone()))
+            .collect();
+
+        // the methods of an interface are top-level functions of the module too
+        let interfaces = top.interfaces.iter()
+            .filter(|(name, _)| !name.starts_with('_'))
+            .map(|(name, interface)| {
+                let interface = match *interface {
+                    Interface::Local(_) => Interface::Grabbed(
+                        interface.methods().into_iter()
+                            .filter_map(|method| top.get(&method).and_then(|t| t.meta).map(|binding| (method, binding)))
+                            .collect()
+                    ),
+
+                    // grabbed from yet another module, and passed on
+                    Interface::Grabbed(_) => interface.clone(),
+                };
+
+                (name.clone(), interface)
+            })
+            .collect();
+
+        let exports = Exports { names, interfaces };
+
+        self.modules.insert(canonical, exports.clone());
+
+        Ok(exports)
+    }
+
+    // `chain` starts with the grab of the module `grab` is grabbing again
+    fn cycle(chain: &[Grab], grab: &Grab) -> Diagnostic {
+        let names = chain.iter().chain(Some(grab)).map(|grab| grab.module.as_str()).collect::<Vec<_>>();
+
+        let mut diagnostic = Diagnostic::new(
+            Severity::Error,
+            format!("modules grabbing each other in a circle: {}", names.join(" -> "))
+        );
+
+        diagnostic.file = Some(grab.file.clone());
+        diagnostic.span = Some(grab.pos.clone());
+
+        for grab in chain.iter() {
+            let mut note = Diagnostic::new(Severity::Note, format!("`{}` grabbed here", grab.module));
+
+            note.file = Some(grab.file.clone());
+            note.span = Some(grab.pos.clone());
+
+            diagnostic.notes.push(note)
+        }
+
+        error::emit(diagnostic.clone());
+
+        diagnostic
+    }
+
+    fn not_exported(&self, module: &str, name: &str, pos: &Pos) -> Diagnostic {
+        if name.starts_with('_') {
+            response!(
+                Wrong(format!("`{}` is private to module `{}`", name, module)),
+                self.source.file,
+                pos,
+                Help("names starting with `_` stay inside their module")
+            )
+        } else {
+            response!(
+                Wrong(format!("module `{}` has no `{}`", module, name)),
+                self.source.file,
+                pos
+            )
+        }
+    }
+
+    // what a variable exports, when it's a grabbed module
+    fn grabbed(&self, t: &Type) -> Option<&Exports> {
+        match t.meta {
+            Some(ref binding) if binding.depth.is_none() => self.symtab.get_foreign_module(&binding.name),
+            _ => None,
+        }
+    }
+
+    // what `with` is given: an interface, either declared or grabbed, or one in a grabbed module
+    fn interface(&self, expression: &Expression) -> Result<Interface, Diagnostic> {
+        use self::ExpressionNode::*;
+
+        match expression.node {
+            Identifier(ref name) => match self.symtab.get_interface(name) {
+                Some(interface) => Ok(interface.clone()),
+
+                None => Err(response!(
+                    Wrong(format!("no interface called `{}`", name)),
+                    self.source.file,
+                    expression.pos
+                )),
+            },
+
+            Binary(ref left, Operator::Index, ref right) => {
+                if let (Identifier(ref module), Str(ref name)) = (&left.node, &right.node) {
+                    if let Some(exports) = self.symtab.fetch(module).as_ref().and_then(|t| self.grabbed(t)) {
+                        return match exports.interfaces.get(name) {
+                            Some(interface) => Ok(interface.clone()),
+                            None => Err(self.not_exported(module, name, &expression.pos)),
+                        }
+                    }
+                }
+
+                Err(response!(
+                    Wrong("only interfaces go after `with`"),
+                    self.source.file,
+                    expression.pos
+                ))
+            }
+
+            _ => Err(response!(
+                Wrong("only interfaces go after `with`"),
+                self.source.file,
+                expression.pos
+            )),
+        }
+    }
+
+    // whether `callee` is a native from `base::OPTIONAL`, and not something named like it
+    fn optional(&self, callee: &Expression) -> bool {
+        match callee.node {
+            ExpressionNode::Identifier(ref name) => base::OPTIONAL.contains(&name.as_str()) && self.symtab.fetch(name).is_some_and(|t| t.meta.is_none()),
+            _ => false,
+        }
+    }
+
+    // what `module.name` is, when `module` was grabbed
+    fn member(&self, expression: &Expression) -> Result<Option<Type>, Diagnostic> {
+        use self::ExpressionNode::*;
+
+        if let Binary(ref left, Operator::Index, ref right) = expression.node {
+            if let (Identifier(ref module), Str(ref name)) = (&left.node, &right.node) {
+                let t = match self.symtab.fetch(module) {
+                    Some(t) => t,
+                    None => return Ok(None),
+                };
+
+                if let Some(exports) = self.grabbed(&t) {
+                    return match exports.names.get(name) {
+                        Some(t) => Ok(Some(t.clone())),
+                        None => Err(self.not_exported(module, name, &expression.pos)),
+                    }
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    // where `grab` looks, in order: next to the file grabbing, the project root and
+    // the folder of the `hugorm.toml` it's under, the engine's search path, and the
+    // standard library next to the binary
+    fn module_dirs(&self, here: &Path, manifest: Option<&Manifest>) -> Vec<PathBuf> {
+        let mut dirs = vec!(here.to_path_buf());
+
+        if !self.root.is_empty() {
+            dirs.push(PathBuf::from(&self.root))
+        }
+
+        if let Some(manifest) = manifest {
+            dirs.push(manifest.root.clone())
+        }
+
+        dirs.extend(self.search.iter().cloned());
+
+        if let Some(std) = env::current_exe().ok().and_then(|exe| exe.parent().map(|dir| dir.join("std"))) {
+            dirs.push(std)
+        }
+
+        // the file grabbing often sits in the root. An empty folder is the
+        // current one, but only canonicalizes when it's spelled `.`
+        let mut seen = HashSet::new();
+
+        dirs.retain(|dir| {
+            let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir.as_path() };
+
+            seen.insert(fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf()))
+        });
+
+        dirs
+    }
+
+    // a manifest is read once per run, however many grabs go through it. A broken
+    // one is still reported for every grab that needs it
+    fn manifest(&mut self, path: &Path) -> Result<Manifest, Diagnostic> {
+        if let Some(loaded) = self.manifests.get(path) {
+            if let Err(ref diagnostic) = *loaded {
+                error::emit(diagnostic.clone())
+            }
+
+            return loaded.clone()
+        }
+
+        let loaded = manifest::load(path);
+
+        self.manifests.insert(path.to_path_buf(), loaded.clone());
+
+        loaded
+    }
+
+    // `a.b.c` is either `a/b/c.hug` or `a/b/c/mod.hug`, in the first folder that has one.
+    // When `a` is a dependency in `hugorm.toml` it's `b/c.hug` or `b/c/mod.hug` in there,
+    // and plain `a` is its entry
+    fn find_module(&mut self, path: &str, statement: &Statement) -> Result<String, Diagnostic> {
+        let here = Path::new(&self.source.file.0).parent().map(Path::to_path_buf).unwrap_or_default();
+
+        // `-e` and the REPL are wherever they were started
+        let project = if here.as_os_str().is_empty() { env::current_dir().unwrap_or_default() } else { here.clone() };
+
+        let manifest = match manifest::find(&project) {
+            Some(path) => Some(self.manifest(&path)?),
+            None => None,
+        };
+
+        let relative = path.split('.').collect::<PathBuf>();
+
+        let file = relative.with_extension("hug");
+        let package = relative.join("mod.hug");
+
+        let dependency = manifest.as_ref().and_then(|manifest| manifest.dependency(path.split('.').next().unwrap()));
+
+        if let Some(dependency) = dependency {
+            let inside = path.split('.').skip(1).collect::<PathBuf>();
+
+            let candidates = if inside.as_os_str().is_empty() {
+                let own = dependency.path.join(manifest::FILE);
+
+                let entry = if own.is_file() {
+                    self.manifest(&own)?.entry_path()
+                } else {
+                    dependency.path.join("mod.hug")
+                };
+
+                vec!(entry)
+            } else {
+                vec!(dependency.path.join(inside.with_extension("hug")), dependency.path.join(inside.join("mod.hug")))
+            };
+
+            if let Some(found) = candidates.iter().find(|candidate| candidate.is_file()) {
+                return Ok(found.display().to_string())
+            }
+
+            let wanted = candidates.iter().map(|candidate| format!("`{}`", candidate.display())).collect::<Vec<_>>();
+
+            return Err(response!(
+                Wrong(format!("dependency `{}` has no module `{}`", dependency.name, path)),
+                self.source.file,
+                statement.pos,
+                Help(format!("needed {}", wanted.join(" or ")))
+            ))
+        }
+
+        let dirs = self.module_dirs(&here, manifest.as_ref());
+
+        for dir in dirs.iter() {
+            for candidate in [dir.join(&file), dir.join(&package)] {
+                if candidate.is_file() {
+                    return Ok(candidate.display().to_string())
+                }
+            }
+        }
+
+        let looked = dirs.iter()
+            .map(|dir| if dir.as_os_str().is_empty() { "`.`".to_string() } else { format!("`{}`", dir.display()) })
+            .collect::<Vec<_>>();
+
+        Err(response!(
+            Wrong(format!(
+                "no such module `{}`, needed either `{}` or `{}`",
+                path, file.display(), package.display()
+            )),
+            self.source.file,
+            statement.pos,
+            Help(format!("looked in {}", looked.join(", ")))
+        ))
+    }
+
+    fn compile_expression(&mut self, expression: &Expression) -> Result<ExprNode, Diagnostic> {
+        use self::ExpressionNode::*;
+
+        let result = match expression.node {
+            Float(ref n) => self.builder.number(*n),
+            Int(ref n) => self.builder.number(*n as f64),
+            Str(ref s) => self.builder.string(s),
+            Bool(ref b) => self.builder.bool(*b),
+
+            Identifier(ref n) =>  {
+                if let Some(t) = self.symtab.fetch(n) {
+                    let var = self.variable(n, &t);
+
+                    if t.boxed {
+                        self.builder.binary(var, BinaryOp::Index, self.builder.number(0.0))
+                    } else {
+                        var
+                    }
+                } else {
+                    return Err(response!(
+                        Wrong(format!("no such variable `{}`", n)),
+                        self.source.file,
+                        expression.pos
+                    ));
+                }
+            }
+
+            Call(ref callee, ref args) => {
+                let mut args_ir = Vec::new();
+
+                for arg in args.iter() {
+                    args_ir.push(self.compile_expression(arg)?)
+                }
+
+                if self.optional(callee) && args.is_empty() {
+                    args_ir.push(Expr::Literal(Literal::Nil).node(TypeInfo::nil()))
+                }
+
+                // assertions get told where they are, so failures can point at them
+                if let Identifier(ref name) = callee.node {
+                    if assert::POSITIONED.contains(&name.as_str()) && self.symtab.fetch(name).is_some_and(|t| t.meta.is_none()) {
+                        let Pos((line, _), (start, end)) = expression.pos;
+
+                        args_ir.push(self.builder.string(&format!("{}:{}:{}:{}", self.source.file.0, line, start, end)))
+                    }
+                }
+
+                let callee_ir = self.compile_expression(callee)?;
+
+                let call_ir = self.builder.call(callee_ir, args_ir, None);
+
+                self.at(call_ir, &expression.pos)
+            }
+
+            Binary(ref left, ref op, ref right) => {
+                if let Some(t) = self.member(expression)? {
+                    return Ok(self.builder.var(t.meta.unwrap()))
+                }
+
+                let left_ir = self.compile_expression(left)?;
+
+                let right_ir = if op == &Index {
+                    match right.node {
+                        Str(ref n) => {
+                            Expr::Literal(
+                                Literal::String(n.clone())
+                            ).node(TypeInfo::nil())
+                        }
+
+                        _ => self.compile_expression(right)?
+                    }
+                } else {
+                    self.compile_expression(right)?
+                };
+
+                use self::Operator::*;
+
+                let left_t = self.type_expression(left)?.node;
+                let right_t = self.type_expression(right)?.node;
+
+                // the VM does plain float arithmetic, and does nothing at all with what isn't
+                // a number. so ints go through natives that truncate and check for overflow,
+                // and sides the type checker couldn't see through natives that check them
+                let plain = |t: &TypeNode| [TypeNode::Int, TypeNode::Float].contains(t) || *op == Add && *t == TypeNode::Str;
+
+                let arithmetic = [Add, Sub, Mul, Div, Mod, Pow].contains(op);
+                let known = plain(&left_t) && plain(&right_t);
+
+                // what's returned or thrown away isn't typed anywhere else
+                if arithmetic {
+                    self.type_expression(expression)?;
+                }
+
+                let ints = left_t == TypeNode::Int && right_t == TypeNode::Int
+                    // the checker only lets an untyped side divide with an int
+                    || [Div, Mod].contains(op) && [&left_t, &right_t].contains(&&TypeNode::Int);
+
+                let native = if arithmetic && ints {
+                    Some(match op {
+                        Add => "$int-add",
+                        Sub => "$int-sub",
+                        Mul => "$int-mul",
+                        Div => "$int-div",
+                        Mod => "$int-mod",
+                        _   => "$int-pow",
+                    })
+                } else if arithmetic && !known {
+                    Some(match op {
+                        Add => "$num-add",
+                        Sub => "$num-sub",
+                        Mul => "$num-mul",
+                        Div => "$num-div",
+                        Mod => "$num-mod",
+                        _   => "$num-pow",
+                    })
+                } else {
+                    None
+                };
+
+                // only what can go wrong needs to say where it is
+                let located = !([And, Or, Eq, NEq].contains(op) || known && native.is_none());
+
+                if let Some(native) = native {
+                    let call_ir = self.builder.call(self.builder.var(Binding::global(native)), vec!(left_ir, right_ir), None);
+
+                    return Ok(self.at(call_ir, &expression.pos))
+                }
+
+                let op_ir = match op {
+                    Add   => BinaryOp::Add,
+                    Sub   => BinaryOp::Sub,
+                    Mul   => BinaryOp::Mul,
+                    Div   => BinaryOp::Div,
+                    Mod   => BinaryOp::Rem,
+                    And   => BinaryOp::And,
+                    Or    => BinaryOp::Or,
+                    Eq    => BinaryOp::Equal,
+                    NEq   => BinaryOp::NEqual,
+                    Lt    => BinaryOp::Lt,
+                    LtEq  => BinaryOp::LtEqual,
+                    Gt    => BinaryOp::Gt,
+                    GtEq  => BinaryOp::GtEqual,
+                    Index => BinaryOp::Index,
+                    Pow   => BinaryOp::Pow, 
+                    Concat => BinaryOp::Add, // :)
+                };
+
+                let binary_ir = self.builder.binary(left_ir, op_ir, right_ir);
+
+                if located {
+                    self.at(binary_ir, &expression.pos)
+                } else {
+                    binary_ir
+                }
+            }
+
+            Array(ref content) => {
+                let mut cont_ir = Vec::new();
+
+                for element in content.iter() {
+                    cont_ir.push(self.compile_expression(element)?)
+                }
+
+                self.builder.list(cont_ir)
+            }
+
+            Dict(ref content) => {
+                let mut keys = Vec::new();
+                let mut vals = Vec::new();
+
+                for (key, val) in content.iter() {
+                    keys.push(
+                        Expr::Literal(
+                            Literal::String(key.clone())
+                        ).node(TypeInfo::nil())
+                    );
+                    vals.push(self.compile_expression(val)?);
+                }
+
+                self.builder.dict(keys, vals)
+            }
+
+            With(ref dict, ref interface) => {
+                if let Dict(ref content) = dict.node {
+                    let mut new_content = content.clone();
+
+                    match self.interface(interface)? {
+                        Interface::Local(ref body) => {
+                            let interface_name = match interface.node {
+                                Identifier(ref name) => name.clone(),
+                                _ => unreachable!(),
+                            };
+
+                            for fun in body.iter() {
+                                if let StatementNode::Function(ref name, ref params, ref body) = fun.node {
+                                    let closure = Expression::new(
+                                        ExpressionNode::AnonFunction(format!("<{}.{}>", interface_name, name), params.clone(), body.clone()),
+                                        expression.pos.clone()
+                                    );
+
+                                    new_content.push((name.clone(), closure));
+                                }
+                            }
 
-                                    new_content.push((name.clone(), closure));
-                                }
-                            }
                             let dict = Expression::new(
                                 ExpressionNode::Dict(new_content),
                                 expression.pos.clone()
                             );
 
                             self.compile_expression(&dict)?
-                        } else {
-                            panic!("grr")
                         }
-                    } else {
-                        unreachable!()
+
+                        // already compiled where they were declared
+                        Interface::Grabbed(ref methods) => {
+                            let mut keys = Vec::new();
+                            let mut values = Vec::new();
+
+                            for (key, value) in new_content.iter() {
+                                keys.push(self.builder.string(key));
+                                values.push(self.compile_expression(value)?)
+                            }
+
+                            for (name, binding) in methods.iter() {
+                                keys.push(self.builder.string(name));
+                                values.push(self.builder.var(binding.clone()))
+                            }
+
+                            self.builder.dict(keys, values)
+                        }
                     }
                 } else {
                     unreachable!()
@@ -619,12 +1356,7 @@ impl<'a> Visitor<'a> {
                 self.push_scope();
                 self.inside.push(Inside::Function);
 
-                for param in params.iter() {
-                    let mut t = Type::from(TypeNode::Any);
-                    t.set_offset(Binding::local(param.as_str(), self.depth, self.function_depth));
-
-                    self.assign(param.clone(), t)
-                }
+                self.visit_params(params, body);
 
                 for statement in body.iter() {
                     self.visit_statement(statement)?;
@@ -633,6 +1365,7 @@ impl<'a> Visitor<'a> {
 
                 self.inside.pop();
                 self.pop_scope();
+                self.boxes.pop();
                 self.function_depth -= 1;
 
                 self.builder.ret(None);
@@ -642,7 +1375,7 @@ impl<'a> Visitor<'a> {
                 self.builder = old_current;
 
                 let func_body = IrFunctionBody {
-                    params: params.iter().cloned().map(|x|
+                    params: params.iter().map(|x|
                         Binding::local(x.as_str(), binding.depth.unwrap_or(0) + 1, binding.function_depth + 1)).collect::<Vec<Binding>>(),
                     method: false,
                     inner: body
@@ -674,7 +1407,7 @@ impl<'a> Visitor<'a> {
         Ok(result)
     }
 
-    pub fn visit_expression(&mut self, expression: &Expression) -> Result<(), ()> {
+    pub fn visit_expression(&mut self, expression: &Expression) -> Result<(), Diagnostic> {
         use self::ExpressionNode::*;
 
         match expression.node {
@@ -682,7 +1415,9 @@ impl<'a> Visitor<'a> {
                 let caller_t = self.type_expression(caller)?.node;
 
                 if let TypeNode::Func(ref params) = caller_t {
-                    if *params != args.len() {
+                    let optional = self.optional(caller) as usize;
+
+                    if args.len() > *params || args.len() + optional < *params {
                         return Err(response!(
                             Wrong(format!("wrong amount of arguments, expected {} but got {}", params, args.len())),
                             self.source.file,
@@ -699,6 +1434,11 @@ impl<'a> Visitor<'a> {
                     }
                 }
 
+                for arg in args.iter() {
+                    self.visit_expression(arg)?;
+                    self.type_expression(arg)?;
+                }
+
                 Ok(())
             },
 
@@ -722,7 +1462,7 @@ impl<'a> Visitor<'a> {
         }
     }
 
-    pub fn type_expression(&mut self, expression: &Expression) -> Result<Type, ()> {
+    pub fn type_expression(&mut self, expression: &Expression) -> Result<Type, Diagnostic> {
         use self::ExpressionNode::*;
 
         let t = match expression.node {
@@ -734,6 +1474,10 @@ impl<'a> Visitor<'a> {
                 use self::Operator::*;
 
                 if op == &Index {
+                    if let Some(t) = self.member(expression)? {
+                        return Ok(t)
+                    }
+
                     let a = self.type_expression(left)?.node;
                     let b = self.type_expression(right)?.node;
 
@@ -753,160 +1497,66 @@ impl<'a> Visitor<'a> {
                     return Ok(Type::from(TypeNode::Any))
                 }
 
-                match (
-                    self.type_expression(left)?.node,
-                    op,
-                    self.type_expression(right)?.node,
-                ) {
-                    (ref a, ref op, ref b) => match **op {
-                        Add | Sub | Mul | Div | Mod => {
-                            if [a, b] != [&TypeNode::Nil, &TypeNode::Nil] {
-                                // real hack here
-                                if a == b || [a, b].contains(&&TypeNode::Any) {
-                                    match a {
-                                        TypeNode::Float | TypeNode::Int | TypeNode::Any => match b {
-                                            TypeNode::Float | TypeNode::Int | TypeNode::Any => {
-                                                Type::from(a.clone())
-                                            }
-
-                                            _ => {
-                                                return Err(response!(
-                                                    Wrong(format!(
-                                                        "can't perform operation `{:?} {} {:?}`",
-                                                        a, op, b
-                                                    )),
-                                                    self.source.file,
-                                                    expression.pos
-                                                ))
-                                            }
-                                        },
-
-                                        _ => {
-                                            return Err(response!(
-                                                Wrong(format!(
-                                                    "can't perform operation `{:?} {} {:?}`",
-                                                    a, op, b
-                                                )),
-                                                self.source.file,
-                                                expression.pos
-                                            ))
-                                        }
-                                    }
-                                } else {
-                                    return Err(response!(
-                                        Wrong(format!(
-                                            "can't perform operation `{:?} {} {:?}`",
-                                            a, op, b
-                                        )),
-                                        self.source.file,
-                                        expression.pos
-                                    ));
-                                }
-                            } else {
-                                return Err(response!(
-                                    Wrong(format!("can't perform operation `{:?} {} {:?}`", a, op, b)),
-                                    self.source.file,
-                                    expression.pos
-                                ));
-                            }
-                        }
+                let a = &self.type_expression(left)?.node;
+                let b = &self.type_expression(right)?.node;
 
-                        Pow => match a {
-                            TypeNode::Float | TypeNode::Int | TypeNode::Any => match b {
-                                TypeNode::Float | TypeNode::Int | TypeNode::Any => Type::from(a.clone()),
-
-                                _ => {
-                                    return Err(response!(
-                                        Wrong(format!(
-                                            "can't perform operation `{:?} {} {:?}`",
-                                            a, op, b
-                                        )),
-                                        self.source.file,
-                                        expression.pos
-                                    ))
-                                }
-                            },
-
-                            _ => {
-                                return Err(response!(
-                                    Wrong(format!("can't perform operation `{:?} {} {:?}`", a, op, b)),
-                                    self.source.file,
-                                    expression.pos
-                                ))
-                            }
-                        },
-
-                        And | Or => {
-                            if a == b && *a == TypeNode::Bool || *a == TypeNode::Any {
-                                Type::from(TypeNode::Bool)
-                            } else {
-                                return Err(response!(
-                                    Wrong(format!("can't perform operation `{:?} {} {:?}`", a, op, b)),
-                                    self.source.file,
-                                    expression.pos
-                                ));
-                            }
-                        }
+                // whatever doesn't make it into an arm below can't be done
+                let numbers = [TypeNode::Float, TypeNode::Int, TypeNode::Any];
 
-                        Concat => {
-                            if [TypeNode::Str, TypeNode::Any].contains(a)  {
-                                match *b {
-                                    TypeNode::Nil => return Err(response!(
-                                        Wrong(format!("can't perform operation `{:?} {} {:?}`", a, op, b)),
-                                        self.source.file,
-                                        expression.pos
-                                    )),
+                let untyped = *a == TypeNode::Any && *b == TypeNode::Any;
 
-                                    _ => Type::from(TypeNode::Str),
-                                }
-                            } else {
-                                return Err(response!(
-                                    Wrong(format!("can't perform operation `{:?} {} {:?}`", a, op, b)),
-                                    self.source.file,
-                                    expression.pos
-                                ));
-                            }
-                        }
+                match *op {
+                    // `+` joins two strings, like the VM does
+                    Add if [a, b].contains(&&TypeNode::Str) && [a, b].iter().all(|t| [TypeNode::Str, TypeNode::Any].contains(t)) => {
+                        Type::from(TypeNode::Str)
+                    }
 
-                        Eq | NEq => {
-                            if [a, b].contains(&&TypeNode::Nil) {
-                                return Err(response!(
-                                    Wrong(format!("can't perform operation `{:?} {} {:?}`", a, op, b)),
-                                    self.source.file,
-                                    expression.pos
-                                ));
-                            }
+                    // whether these truncate depends on having ints, so an int side decides it for an
+                    // untyped one, and with neither there's nothing to go by
+                    Div | Mod if untyped => {
+                        return Err(response!(
+                            Wrong(format!("can't tell whether `{}` is on ints or floats here", op)),
+                            self.source.file,
+                            expression.pos,
+                            Help("use `int()` or `float()` on a side")
+                        ))
+                    }
 
-                            Type::from(TypeNode::Bool)
-                        },
-
-                        Lt | Gt | LtEq | GtEq => {
-                            let ts = [TypeNode::Any, TypeNode::Float, TypeNode::Int];
-                            if ts.contains(a) && ts.contains(b) {
-                                Type::from(TypeNode::Bool)
-                            } else {
-                                return Err(response!(
-                                    Wrong(format!("can't perform operation `{:?} {} {:?}`", a, op, b)),
-                                    self.source.file,
-                                    expression.pos
-                                ));
-                            }
-                        }
+                    Div | Mod if [a, b].contains(&&TypeNode::Int) && [a, b].contains(&&TypeNode::Any) => Type::from(TypeNode::Int),
 
-                        _ => {
-                            return Err(response!(
-                                Wrong(format!("can't perform operation `{:?} {} {:?}`", a, op, b)),
-                                self.source.file,
-                                expression.pos
-                            ))
-                        }
-                    },
+                    // real hack here
+                    Add | Sub | Mul | Div | Mod if (a == b || [a, b].contains(&&TypeNode::Any)) && numbers.contains(a) && numbers.contains(b) => {
+                        // a side that could be either makes the result either
+                        Type::from(if a == b { a.clone() } else { TypeNode::Any })
+                    }
+
+                    Pow if numbers.contains(a) && numbers.contains(b) => {
+                        Type::from(if a == b { a.clone() } else { TypeNode::Any })
+                    }
+
+                    And | Or if a == b && *a == TypeNode::Bool || *a == TypeNode::Any => Type::from(TypeNode::Bool),
+
+                    Concat if [TypeNode::Str, TypeNode::Any].contains(a) && *b != TypeNode::Nil => Type::from(TypeNode::Str),
+
+                    Eq | NEq if ![a, b].contains(&&TypeNode::Nil) => Type::from(TypeNode::Bool),
+
+                    Lt | Gt | LtEq | GtEq if numbers.contains(a) && numbers.contains(b) => Type::from(TypeNode::Bool),
+
+                    _ => {
+                        return Err(response!(
+                            Wrong(format!("can't perform operation `{:?} {} {:?}`", a, op, b)),
+                            self.source.file,
+                            expression.pos
+                        ))
+                    }
                 }
             },
 
             Neg(ref expr) => self.type_expression(expr)?,
             Not(_) => Type::from(TypeNode::Bool),
 
+            AnonFunction(_, ref params, _) => Type::from(TypeNode::Func(params.len())),
+
             Identifier(ref n) => match self.symtab.fetch(n) {
                 Some(t) => t,
                 None    => return Err(response!(
@@ -916,7 +1566,16 @@ impl<'a> Visitor<'a> {
                 ))
             },
 
-            Call(ref caller, ref args) => Type::from(TypeNode::Any),
+            Call(ref callee, _) => match callee.node {
+                // the builtins that always give back the same kind of number
+                Identifier(ref name) if self.symtab.fetch(name).is_some_and(|t| t.meta.is_none()) => match name.as_str() {
+                    "int" | "len"   => Type::from(TypeNode::Int),
+                    "float" | "sum" => Type::from(TypeNode::Float),
+                    _               => Type::from(TypeNode::Any),
+                },
+
+                _ => Type::from(TypeNode::Any),
+            },
 
             _ => Type::from(TypeNode::Nil),
         };
@@ -924,63 +1583,125 @@ impl<'a> Visitor<'a> {
         Ok(t)
     }
 
-    fn visit_variable(&mut self, variable: &StatementNode, pos: &Pos) -> Result<(), ()> {
-        use self::ExpressionNode::*;
-
-        if let &StatementNode::Declaration(ref name, ref right) = variable {
-            if name.as_str().chars().last().unwrap() == '-' {
-                response!(
-                    Weird("kebab-case at identifier end is not cool"),
-                    self.source.file,
-                    pos
-                )
-            }
+    fn visit_variable(&mut self, variable: &StatementNode, pos: &Pos) -> Result<(), Diagnostic> {
+        if let StatementNode::Declaration(ref name, ref right) = *variable {
+            self.visit_rebind(name, pos)?;
 
             if right.is_none() {
                 let mut t = Type::from(TypeNode::Nil);
 
-                t.set_offset(Binding::local(name.as_str(), self.depth, self.function_depth));
-                
+                t.set_offset(self.binding(name.as_str()));
+                t.set_pos(pos.clone());
+                t.boxed = self.is_boxed(name);
+
+                let boxed = t.boxed;
+
                 self.assign(name.to_owned(), t);
                 let right_ir = self.builder.number(0.0);
-                let binding = Binding::local(name, self.depth, self.function_depth);
+                let binding = self.binding(name);
 
-                self.builder.bind(binding, right_ir);
+                if boxed {
+                    self.bind_boxed(binding, right_ir)
+                } else {
+                    self.bind(binding, right_ir)
+                }
 
             } else {
-                let binding = if let Some(ref t) = self.symtab.fetch(name) {
-                    t.meta.clone().unwrap()
-                } else {
-                    Binding::local(name.as_str(), self.depth, self.function_depth)
-                };
+                let binding = self.binding(name.as_str());
 
                 let mut t = self.type_expression(right.as_ref().unwrap())?;
 
+                t.mode = TypeMode::Regular;
                 t.set_offset(binding.clone());
+                t.set_pos(pos.clone());
+                t.boxed = self.is_boxed(name);
+
+                let boxed = t.boxed;
 
                 self.assign(name.to_owned(), t);
 
                 let right_ir = self.compile_expression(&right.clone().unwrap())?;
 
-                self.builder.bind(binding, right_ir);
+                if boxed {
+                    self.bind_boxed(binding, right_ir)
+                } else {
+                    self.bind(binding, right_ir)
+                }
             }
         }
 
         Ok(())
     }
 
-    fn visit_ass(&mut self, ass: &StatementNode, pos: &Pos) -> Result<(), ()> {
+    // constants can't be declared again within the function that owns them
+    fn visit_rebind(&mut self, name: &String, pos: &Pos) -> Result<(), Diagnostic> {
+        if let Some(t) = self.symtab.fetch(name) {
+            if t.mode != TypeMode::Immutable {
+                return Ok(())
+            }
+
+            if let Some(ref binding) = t.meta {
+                if binding.function_depth == self.function_depth {
+                    return Err(response!(
+                        Wrong(format!("can't declare constant `{}` again", name)),
+                        self.source.file,
+                        pos,
+                        Note(format!("`{}` was declared constant here", name)),
+                        self.source.file,
+                        t.pos.unwrap()
+                    ))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn visit_ass(&mut self, ass: &StatementNode, pos: &Pos) -> Result<(), Diagnostic> {
         use self::ExpressionNode::*;
 
-        if let &StatementNode::Assignment(ref name, ref right) = ass {  
+        if let StatementNode::Assignment(ref name, ref right) = *ass {  
             match name.node {          
                 Identifier(ref name) => if let Some(left_t) = self.symtab.fetch(name) {
-                        let binding = left_t.meta.unwrap().clone();
-        
-                        let mut t = self.type_expression(&right)?;
+                        let binding = match left_t.meta {
+                            Some(ref binding) if left_t.mode != TypeMode::Immutable => binding.clone(),
+
+                            Some(_) => return Err(response!(
+                                Wrong(format!("can't assign twice to constant `{}`", name)),
+                                self.source.file,
+                                pos,
+                                Note(format!("`{}` was declared constant here", name)),
+                                self.source.file,
+                                left_t.pos.unwrap()
+                            )),
+
+                            None => return Err(response!(
+                                Wrong(format!("can't assign to builtin `{}`", name)),
+                                self.source.file,
+                                pos
+                            ))
+                        };
+
+                        let mut t = self.type_expression(right)?;
+
+                        t.mode = TypeMode::Regular;
                         t.set_offset(binding);
-        
-                        self.assign(name.to_owned(), t)
+                        t.pos = left_t.pos.clone();
+                        t.boxed = left_t.boxed;
+
+                        self.assign(name.to_owned(), t);
+
+                        if left_t.boxed {
+                            self.visit_expression(right)?;
+
+                            let var = self.variable(name, &left_t);
+                            let right_ir = self.compile_expression(right)?;
+
+                            let set = self.builder.set_element(var, self.builder.number(0.0), right_ir);
+                            self.builder.emit(set);
+
+                            return Ok(())
+                        }
                     } else {
                         return Err(response!(
                             Wrong(format!("can't assign non-existent `{}`", name)),
@@ -990,6 +1711,14 @@ impl<'a> Visitor<'a> {
                     },
 
                 Binary(ref left, ref op, ref index) if *op == Operator::Index => {
+                    if self.member(name)?.is_some() {
+                        return Err(response!(
+                            Wrong("can't assign to a grabbed module from outside of it"),
+                            self.source.file,
+                            pos
+                        ))
+                    }
+
                     let left_ir = self.compile_expression(left)?;
                     let index_ir = self.compile_expression(index)?;
                     let right_ir = self.compile_expression(right)?;
@@ -1008,14 +1737,209 @@ impl<'a> Visitor<'a> {
             let left_ir = self.compile_expression(name)?;
             let right_ir = self.compile_expression(right)?;
 
-            self.builder.mutate(left_ir, right_ir)
+            self.builder.mutate(left_ir, right_ir);
+
+            // setting a variable leaves its new value on the stack
+            self.builder.emit(Expr::Pop.node(TypeInfo::nil()))
         }
 
         Ok(())
     }
 
-    fn assign_str(&mut self, name: &str, t: Type) {
-        self.symtab.assign_str(name, t)
+    // binds a fresh variable, or writes into its hoisted slot when inside a block
+    fn bind(&mut self, binding: Binding, value: ExprNode) {
+        if self.in_block() {
+            let var = self.builder.var(binding);
+
+            self.builder.mutate(var, value);
+            self.builder.emit(Expr::Pop.node(TypeInfo::nil()))
+        } else {
+            self.builder.bind(binding, value)
+        }
+    }
+
+    fn in_block(&self) -> bool {
+        for inside in self.inside.iter().rev() {
+            match *inside {
+                Inside::Loop | Inside::Branch => return true,
+                Inside::Function => return false,
+                Inside::Nothing => (),
+            }
+        }
+
+        false
+    }
+
+    // zub only pops locals when a function ends, so a `let` that runs
+    // conditionally or once per iteration would leave the stack out of sync
+    // with the compiler's local slots. Every declaration inside a block is
+    // given its slot up front, right before the outermost block statement.
+    // Variables belong to the whole function, so a name it already declared
+    // is that same variable and keeps its slot
+    fn hoist(&mut self, statement: &Statement) {
+        use self::StatementNode::*;
+
+        let mut names = Vec::new();
+
+        match statement.node {
+            While(_, ref body) | For(_, _, ref body) => Self::block_declarations(body, &mut names),
+
+            If(_, ref body, ref else_) => {
+                Self::block_declarations(body, &mut names);
+
+                for (_, body) in else_.iter() {
+                    Self::block_declarations(body, &mut names)
+                }
+            }
+
+            _ => (),
+        }
+
+        for name in names {
+            let declared = self.symtab.fetch(&name)
+                .and_then(|t| t.meta)
+                .is_some_and(|binding| binding.function_depth == self.function_depth);
+
+            if declared {
+                continue
+            }
+
+            let binding = self.binding(&name);
+            let mut nil = Expr::Literal(Literal::Nil).node(TypeInfo::nil());
+
+            if self.is_boxed(&name) {
+                nil = self.builder.list(vec!(nil))
+            }
+
+            self.builder.bind(binding, nil)
+        }
+    }
+
+    fn block_declarations(body: &[Statement], names: &mut Vec<String>) {
+        use self::StatementNode::*;
+
+        let declare = |name: &String, names: &mut Vec<String>| {
+            if !names.contains(name) {
+                names.push(name.clone())
+            }
+        };
+
+        for statement in body.iter() {
+            match statement.node {
+                Declaration(ref name, _) | Function(ref name, ..) => declare(name, names),
+
+                For(ref name, _, ref body) => {
+                    declare(name, names);
+
+                    for hidden in Self::for_names(&statement.pos).iter() {
+                        declare(hidden, names)
+                    }
+
+                    Self::block_declarations(body, names)
+                }
+
+                While(_, ref body) | Block(ref body) => Self::block_declarations(body, names),
+
+                If(_, ref body, ref else_) => {
+                    Self::block_declarations(body, names);
+
+                    for (_, body) in else_.iter() {
+                        Self::block_declarations(body, names)
+                    }
+                }
+
+                _ => (),
+            }
+        }
+    }
+
+    // hidden variables of a for-loop: the items, whether they're a custom iterator, and the index
+    fn for_names(pos: &Pos) -> [String; 3] {
+        let line = (pos.0).0;
+        let column = (pos.1).0;
+
+        [
+            format!("$for-items-{}-{}", line, column),
+            format!("$for-custom-{}-{}", line, column),
+            format!("$for-index-{}-{}", line, column),
+        ]
+    }
+
+    fn method_call(&self, object: &Binding, method: &str) -> ExprNode {
+        let callee = self.builder.binary(self.builder.var(object.clone()), BinaryOp::Index, self.builder.string(method));
+
+        self.builder.call(callee, Vec::new(), None)
+    }
+
+    // top-level things live in the VM's globals, so they outlast a single run.
+    // zub counts a local's depth from the top of the function declaring it (which
+    // is one flat scope), while the function depth is what decides upvalues
+    fn binding(&self, name: &str) -> Binding {
+        if self.function_depth == 0 {
+            self.global(name)
+        } else {
+            Binding::local(name, 1, self.function_depth)
+        }
+    }
+
+    // the variable itself, as seen from here
+    fn variable(&self, name: &str, t: &Type) -> ExprNode {
+        match t.meta {
+            Some(ref binding) if binding.depth.is_some() => {
+                self.builder.var(Binding::local(name, self.depth, binding.function_depth))
+            }
+
+            Some(_) => self.builder.var(self.global(name)),
+
+            None => self.builder.var(Binding::global(name)),
+        }
+    }
+
+    // a grabbed module's globals are kept apart from everyone else's
+    fn global(&self, name: &str) -> Binding {
+        match self.namespace {
+            Some(ref namespace) => Binding::global(&format!("{}::{}", namespace, name)),
+            None => Binding::global(name),
+        }
+    }
+
+    fn is_boxed(&self, name: &str) -> bool {
+        self.function_depth > 0 && self.boxes.last().is_some_and(|boxes| boxes.contains(name))
+    }
+
+    fn visit_params(&mut self, params: &[String], body: &[Statement]) {
+        self.boxes.push(capture::boxed(params, body));
+
+        for param in params.iter() {
+            let binding = Binding::local(param.as_str(), self.depth, self.function_depth);
+
+            let mut t = Type::from(TypeNode::Any);
+            t.set_offset(binding.clone());
+
+            if self.is_boxed(param) {
+                t.boxed = true;
+
+                let var = self.builder.var(binding);
+                let boxed = self.builder.list(vec!(var.clone()));
+
+                self.builder.mutate(var, boxed);
+                self.builder.emit(Expr::Pop.node(TypeInfo::nil()))
+            }
+
+            self.assign(param.clone(), t)
+        }
+    }
+
+    // like `bind`, for the boxed ones
+    fn bind_boxed(&mut self, binding: Binding, value: ExprNode) {
+        if self.in_block() {
+            // hoisting already made the box
+            let set = self.builder.set_element(self.builder.var(binding), self.builder.number(0.0), value);
+            self.builder.emit(set)
+        } else {
+            let boxed = self.builder.list(vec!(value));
+            self.builder.bind(binding, boxed)
+        }
     }
 
     fn assign(&mut self, name: String, t: Type) {
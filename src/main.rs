@@ -1,345 +1,307 @@
+#![allow(clippy::result_large_err)] // diagnostics are chunky, and that is fine
+
 extern crate colored;
 extern crate rustyline;
 extern crate rustyline_derive;
 extern crate zub;
-#[cfg(unix)]
-extern crate gag;
 extern crate statrs;
-
-#[cfg(unix)]
-use gag::BufferRedirect;
-use std::io::Read;
-
-mod hugorm;
-use crate::hugorm::lexer::*;
-use crate::hugorm::source::*;
-use crate::hugorm::parser::*;
-use crate::hugorm::visitor::*;
-use crate::hugorm::prelude::math;
-
-use zub::vm::*;
-use zub::compiler::*;
-use zub::ir::*;
-
-use std::io;
-use std::rc::Rc;
+extern crate serde_json;
+
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal};
+
+use hugorm::source::*;
+use hugorm::error::{Diagnostic, Render, Severity};
+use hugorm::runtime;
+use hugorm::prelude::base;
+use hugorm::cli::{self, Command, Script};
+use hugorm::repl::{self, Session};
+use hugorm::Engine;
+use hugorm::testing;
+use hugorm::lsp;
+use hugorm::formatter;
+use hugorm::manifest;
 
 use colored::Colorize;
 
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::rc::Rc;
 
+use rustyline::completion::Completer;
 use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
 use rustyline::Editor;
 use rustyline::{hint::Hinter, Context};
-use rustyline_derive::{Completer, Helper, Highlighter, Validator};
+use rustyline_derive::Helper;
 
-use std::io::prelude::*;
 use std::path::Path;
-use std::fs::File;
-use std::fs::metadata;
 
-fn run(path: &str, content: &str, root: String) {
-    let source = Source::from(path, content.lines().map(|x| x.into()).collect::<Vec<String>>());
-    let lexer = Lexer::default(content.chars().collect(), &source);
+// on stderr, so they don't end up wherever the program's output is going
+fn report(diagnostics: &[Diagnostic]) {
+    let render = Render::from_env();
 
-    let mut tokens = Vec::new();
-
-    for token_res in lexer {
-        if let Ok(token) = token_res {
-            tokens.push(token)
-        } else {
-            return
-        }
+    for diagnostic in diagnostics.iter() {
+        eprint!("{}", render.render(diagnostic))
     }
+}
 
-    let mut parser = Parser::new(tokens, &source);
-
-    match parser.parse() {
-        Ok(ast) => {
-            let mut visitor = Visitor::new(&source, root);
-
-            visitor.set_global("print", TypeNode::Func(1));
-            visitor.set_global("input", TypeNode::Func(0));
-            visitor.set_global("len", TypeNode::Func(1));
-
-            match visitor.visit(&ast) {
-                Ok(_) => {
-                    visitor.symtab.pop(); // gotta cachce root scope
-
-                    fn print(heap: &mut Heap<Object>, args: &[Value]) -> Value {
-                        println!("{}", args[1].with_heap(heap));
-                        Value::nil()
-                    }
-
-                    fn prompt(heap: &mut Heap<Object>, args: &[Value]) -> Value {
-                        let mut input = String::new();
-
-                        match io::stdin().read_line(&mut input) {
-                            Ok(n) => {
-                                Value::object(heap.insert_temp(Object::String(input)))
-                            }
-
-                            Err(error) => {
-                                println!("error: {}", error);
-                                Value::nil()
-                            },
-                        }
-                    }
-
-                    fn len(heap: &mut Heap<Object>, args: &[Value]) -> Value {
-                        if let Variant::Obj(handle) = args[1].decode() {
-                            if let Object::List(ref list) = unsafe { heap.get_unchecked(handle) } {
-                                Value::float(list.content.len() as f64)
-                            } else {
-                                Value::nil()
-                            }
-                        } else {
-                            Value::nil()
-                        }
-                    }
-
-                    let mut vm = VM::new();
-                    vm.add_native("print", print, 1);
-                    vm.add_native("len", len, 1);
-
-                    let ir = visitor.build();
-
-                    vm.exec(&ir, false);
-                },
-                _ => (),
-            }
-        },
+// where a script's modules are looked up from, the project it's in when it's in one
+fn root(script: &Script) -> String {
+    let dir = match *script {
+        Script::File(ref path) => Path::new(path).parent()
+            .map(|dir| dir.display().to_string())
+            .filter(|dir| !dir.is_empty())
+            .unwrap_or_else(|| ".".to_string()),
 
-        _ => ()
+        _ => ".".to_string(),
+    };
+
+    match manifest::find(Path::new(&dir)).and_then(|path| path.parent().map(Path::to_path_buf)) {
+        Some(project) => project.display().to_string(),
+        None => dir,
     }
 }
 
-#[derive(Completer, Helper, Validator, Highlighter)]
-struct HugHinter {
-    hints: HashSet<String>
+fn read(script: &Script) -> Result<String, Diagnostic> {
+    let content = match *script {
+        Script::File(ref path) => fs::read_to_string(path),
+        Script::Code(ref code) => Ok(code.clone()),
+        Script::Stdin => io::read_to_string(io::stdin()),
+    };
+
+    content.map_err(|why| {
+        let mut diagnostic = Diagnostic::new(Severity::Error, format!("failed to read: {}", why));
+        diagnostic.file = Some(script.name());
+
+        diagnostic
+    })
 }
 
-impl Hinter for HugHinter {
-    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
-        if pos < line.len() {
-            return None;
+fn engine(script: &Script, args: &[String]) -> Engine {
+    let mut engine = Engine::new();
+
+    engine.set_root(&root(script));
+    engine.set_args(args);
+
+    engine
+}
+
+// the exit code, `exit` gets the last word
+fn run(script: &Script, args: &[String]) -> i32 {
+    let path = script.name();
+
+    let content = match read(script) {
+        Ok(content) => content,
+        Err(diagnostic) => {
+            report(&[diagnostic]);
+            return cli::COMPILE_ERROR
         }
+    };
 
-        self.hints
-            .iter()
-            .filter_map(|hint| {
-                if pos > 0 && hint.starts_with(&line[..pos]) {
-                    Some(format!("{}", hint[pos..].to_owned()))
-                } else {
-                    None
-                }
-            })
-            .next()
+    let mut engine = engine(script, args);
+
+    let ir = match engine.compile_str(&path, &content) {
+        Ok((ir, warnings)) => {
+            report(&warnings);
+            ir
+        },
+
+        Err(diagnostics) => {
+            report(&diagnostics);
+            return cli::COMPILE_ERROR
+        }
+    };
+
+    if let Err(payload) = engine.exec(&ir) {
+        if let Some(&base::Exit(code)) = payload.downcast_ref::<base::Exit>() {
+            return code
+        }
+
+        report(&[runtime::diagnostic(&*payload, &path)]);
+
+        return cli::RUNTIME_ERROR
     }
+
+    0
 }
 
-fn hugorm_hints() -> HashSet<String> {
-    let mut set = HashSet::new();
+// `hugorm run` without a program, whatever the closest `hugorm.toml` says to run
+fn project(args: &[String]) -> i32 {
+    let cwd = env::current_dir().unwrap_or_default();
 
-    set.insert("fun".to_string());
-    set.insert("print".to_string());
-    set.insert("if".to_string());
-    set.insert("elif".to_string());
-    set.insert("else".to_string());
-    set.insert("let".to_string());
+    let path = match manifest::find(&cwd) {
+        Some(path) => path,
 
-    set
-}
+        None => {
+            let diagnostic = Diagnostic::new(
+                Severity::Error,
+                format!("no `{}` in `{}` or any folder above it", manifest::FILE, cwd.display())
+            );
 
-#[cfg(unix)]
-fn repl(root: String) {
-    let hinter = HugHinter {
-        hints: hugorm_hints()
+            report(&[diagnostic]);
+
+            return cli::COMPILE_ERROR
+        }
     };
 
-    let mut rl = Editor::<HugHinter>::new();
-    rl.set_helper(Some(hinter));
+    match manifest::load(&path) {
+        Ok(manifest) => run(&Script::File(manifest.entry_path().display().to_string()), args),
 
-    let header = format!("{} {} {}", "Hugorm REPL".bold(), "|".green(), "Interactive gangster terminal".yellow().bold());
-    println!("{}", header);
-    println!("{}", "-------------------------------------------".green());
+        Err(diagnostic) => {
+            report(&[diagnostic]);
+            cli::COMPILE_ERROR
+        }
+    }
+}
 
+// `hugorm check [paths]`, compiles without running anything. false when something didn't
+fn check(paths: &[String]) -> bool {
+    let paths = if paths.is_empty() { vec!(".".to_string()) } else { paths.to_vec() };
 
-    let source = Source::from("<repl>", Vec::new());
+    let mut ok = true;
 
-    fn print(heap: &mut Heap<Object>, args: &[Value]) -> Value {
-        println!("{}", args[1].with_heap(heap));
-        Value::nil()
-    }
+    for file in hug_files(&paths).iter() {
+        let script = Script::File(file.clone());
 
-    fn len(heap: &mut Heap<Object>, args: &[Value]) -> Value {
-        if let Variant::Obj(handle) = args[1].decode() {
-            if let Object::List(ref list) = unsafe { heap.get_unchecked(handle) } {
-                Value::float(list.content.len() as f64)
-            } else {
-                Value::nil()
+        let content = match read(&script) {
+            Ok(content) => content,
+            Err(diagnostic) => {
+                report(&[diagnostic]);
+                ok = false;
+                continue
+            }
+        };
+
+        match engine(&script, &[]).compile_str(file, &content) {
+            Ok((_, warnings)) => report(&warnings),
+            Err(diagnostics) => {
+                report(&diagnostics);
+                ok = false
             }
-        } else {
-            Value::nil()
         }
     }
 
-    let mut vm = VM::new();
-    vm.add_native("print", print, 1);
-    vm.add_native("len", len, 1);
+    ok
+}
+
+// completes, hints, colors and knows when an entry isn't done, all by asking the session
+#[derive(Helper)]
+struct HugHelper {
+    session: Rc<RefCell<Session>>,
+}
 
-    let mut visitor = Visitor::new(&source, root);
+impl Completer for HugHelper {
+    type Candidate = String;
 
-    visitor.set_global("print", TypeNode::Func(1));
-    visitor.set_global("len", TypeNode::Func(1));
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.session.borrow().complete(line, pos))
+    }
+}
 
-    let mut last_len = 0usize;
+impl Hinter for HugHelper {
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None
+        }
 
-    let caret_normal = format!("{}", ">> ".green());
-    let caret_buffer = ".. ".to_string();
-    let mut caret = caret_normal.clone();
+        let (start, candidates) = self.session.borrow().complete(line, pos);
 
-    let mut in_buffer = false;
-    let mut debug = false;
+        if start == pos {
+            return None
+        }
 
-    let mut line_buffer = String::new(); // for multiline stuff
+        candidates.first().map(|candidate| candidate[pos - start..].to_string())
+    }
+}
 
-    loop {
-        let readline = rl.readline(caret.as_str());
+impl Highlighter for HugHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        Cow::Owned(repl::highlight(line))
+    }
 
-        match readline {
-            Ok(line) => {
-                if line.len() == 0 {
-                    if in_buffer {
-                        in_buffer = false;
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(hint.dimmed().to_string())
+    }
 
-                        caret = caret_normal.clone();
-                    } else {
-                        continue
-                    }
-                }
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        true
+    }
+}
+
+impl Validator for HugHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        if repl::incomplete(ctx.input()) {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
 
-                let mut line = line;
+// `file` is run first, for `hugorm -i file.hug`. The exit code is whatever `exit` got
+fn repl(root: String, file: Option<&str>) -> i32 {
+    let session = Rc::new(RefCell::new(Session::new(root)));
 
-                if line.len() > 0 && line.trim().chars().last().unwrap() == ':' || in_buffer {
-                    line_buffer.push_str(&line);
-                    line_buffer.push('\n');
+    let mut rl = Editor::<HugHelper>::new();
+    rl.set_helper(Some(HugHelper { session: session.clone() }));
 
-                    caret = caret_buffer.clone();
+    let history = repl::history_path();
 
-                    in_buffer = true;
+    if let Some(ref history) = history {
+        let _ = rl.load_history(history);
+    }
 
-                    continue
-                } else if line_buffer.len() > 0 {
-                    line = format!("{}\n{}", line_buffer, line);
-                    line_buffer = String::new()
-                }
+    let header = format!("{} {} {}", "Hugorm REPL".bold(), "|".green(), "Interactive gangster terminal".yellow().bold());
+    println!("{}", header);
+    println!("{}", "-------------------------------------------".green());
 
+    report(&session.borrow_mut().start());
 
-                match line.replace(" ", "").as_str() {
-                    "@math" => {
-                        math::include_math(&mut visitor, &mut vm);
-                        continue
-                    },
+    if let Some(file) = file {
+        report(&session.borrow_mut().load(file))
+    }
 
-                    "@debug" => {
-                        debug = true;
-                        continue
-                    },
+    // piped in, there's no editor to keep reading until an entry is done. A line
+    // at a time, so `input` gets the ones after the entry that called it
+    if !io::stdin().is_terminal() {
+        let mut lines = std::iter::from_fn(|| {
+            let mut line = String::new();
 
-                    _ => ()
-                }
+            match io::stdin().read_line(&mut line) {
+                Ok(0) | Err(_) => None,
+                Ok(_) => Some(line.trim_end_matches(['\n', '\r']).to_string()),
+            }
+        });
 
-                rl.add_history_entry(line.as_str());
+        while session.borrow().exited.is_none() {
+            match repl::read_entry(&mut lines) {
+                Some(entry) => report(&session.borrow_mut().handle(&entry)),
+                None => break,
+            }
+        }
 
-                let source = Source::from("<repl>", line.lines().map(|x| x.into()).collect::<Vec<String>>());
-                let lexer = Lexer::default(line.chars().collect(), &source);
+        let exited = session.borrow().exited;
 
-                let mut tokens = Vec::new();
+        return exited.unwrap_or(0)
+    }
 
-                for token_res in lexer {
-                    if let Ok(token) = token_res {
-                        tokens.push(token)
-                    } else {
-                        return
-                    }
-                }
+    let caret = format!("{}", ">> ".green());
 
-                let mut parser = Parser::new(tokens, &source);
-
-                match parser.parse() {
-                    Ok(ast) => {
-                        let mut repl_ast = Vec::new();
-
-                        if ast.len() == 1 {
-                            if let StatementNode::Expression(ref expr) = ast[0].node {
-                                let pos = ast[0].pos.clone();
-
-                                repl_ast.push(
-                                    Statement::new(
-                                        StatementNode::Declaration(
-                                            "$".to_string(), // to capture print return no fucks
-                                            Some(
-                                                Expression::new(
-                                                    ExpressionNode::Call(
-                                                        Rc::new(
-                                                            Expression::new(
-                                                                ExpressionNode::Identifier(
-                                                                    String::from("print")
-                                                                ),
-                                                                pos.clone()
-                                                            )
-                                                        ),
-                                                        vec!(expr.clone())
-                                                    ),
-                                                    pos.clone()
-                                                )
-                                            )
-                                        ),
-                                        pos.clone()
-                                    )
-                                )
-                            } else {
-                                repl_ast = ast
-                            }
-                        } else {
-                            repl_ast = ast
-                        }
-
-                        match visitor.visit(&repl_ast) {
-                            Ok(_) => {
-                                if debug {
-                                    let ir = visitor.build();
-
-                                    vm.exec(&ir, false);
-
-                                    visitor.symtab.stack.push(visitor.symtab.last.clone());
-
-                                } else {
-                                    let mut buffer = BufferRedirect::stdout().unwrap();
-                                    let ir = visitor.build();
-
-                                    vm.exec(&ir, false);
-
-                                    visitor.symtab.stack.push(visitor.symtab.last.clone());
-
-                                    let mut output = String::new();
-                                    let new_len = buffer.read_to_string(&mut output).unwrap();
-
-                                    drop(buffer);
-
-                                    print!("{}", &output[last_len .. new_len]);
-
-                                    last_len = new_len;
-                                }
-                            }
-
-                            _ => continue
-                        }
-                    },
-
-                    _ => continue
+    while session.borrow().exited.is_none() {
+        match rl.readline(&caret) {
+            Ok(entry) => {
+                if entry.trim().is_empty() {
+                    continue
                 }
+
+                rl.add_history_entry(entry.as_str());
+
+                let diagnostics = session.borrow_mut().handle(&entry);
+
+                report(&diagnostics)
             },
 
             Err(ReadlineError::Interrupted) => {
@@ -353,45 +315,159 @@ fn repl(root: String) {
             },
 
             Err(err) => {
-                println!("Error: {:?}", err);
+                eprintln!("Error: {:?}", err);
                 break
             }
         }
     }
+
+    if let Some(ref history) = history {
+        let saved = history.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|_| {
+            rl.save_history(history).map_err(|why| std::io::Error::other(why.to_string()))
+        });
+
+        if let Err(why) = saved {
+            eprintln!("{} failed to save history to {}: {}", "error:".red().bold(), history.display(), why)
+        }
+    }
+
+    let exited = session.borrow().exited;
+
+    exited.unwrap_or(0)
 }
 
-fn run_file(path: &str, root: String) {
-    let display = Path::new(path).display();
+// `hugorm test [paths]`, false when anything failed
+fn test(paths: &[String]) -> bool {
+    let paths = if paths.is_empty() { vec!(".".to_string()) } else { paths.to_vec() };
 
-    let mut file = match File::open(&path) {
-        Err(why) => panic!("failed to open {}: {}", display, why),
-        Ok(file) => file,
-    };
+    let mut outcomes = Vec::new();
 
-    let mut s = String::new();
+    for file in testing::discover(&paths).iter() {
+        for outcome in testing::run_file(file) {
+            let status = if outcome.passed() { "ok".green() } else { "FAILED".red() };
 
-    match file.read_to_string(&mut s) {
-        Err(why) => panic!("failed to read {}: {}", display, why),
-        Ok(_) => run(&path, &s, root),
+            if outcome.name == outcome.file {
+                println!("test {} ... {}", outcome.file, status)
+            } else {
+                println!("test {} :: {} ... {}", outcome.file, outcome.name, status)
+            }
+
+            outcomes.push(outcome)
+        }
     }
+
+    let failed = outcomes.iter().filter(|outcome| !outcome.passed()).collect::<Vec<_>>();
+
+    if !failed.is_empty() {
+        eprintln!("\nfailures:");
+
+        for outcome in failed.iter() {
+            report(&outcome.failures)
+        }
+    }
+
+    let result = if failed.is_empty() { "ok".green() } else { "FAILED".red() };
+
+    println!(
+        "\ntest result: {}. {} passed; {} failed",
+        result,
+        outcomes.len() - failed.len(),
+        failed.len()
+    );
+
+    failed.is_empty()
 }
 
-fn main() {
-    let args = std::env::args().collect::<Vec<String>>();
-    let root = Path::new(&args[0].to_string()).parent().unwrap().display().to_string();
+// `hugorm fmt [--check] [paths]`, false when something wasn't formatted or couldn't be
+fn fmt(check: bool, paths: &[String]) -> bool {
+    let paths = if paths.is_empty() { vec!(".".to_string()) } else { paths.to_vec() };
+
+    let mut ok = true;
+
+    for file in hug_files(&paths).iter() {
+        let content = match std::fs::read_to_string(file) {
+            Ok(content) => content,
+            Err(why) => {
+                eprintln!("{} failed to read {}: {}", "error:".red().bold(), file, why);
+                ok = false;
+                continue
+            }
+        };
+
+        let formatted = match formatter::format(file, &content) {
+            Ok(formatted) => formatted,
+            Err(diagnostics) => {
+                report(&diagnostics);
+                ok = false;
+                continue
+            }
+        };
 
-    if args.len() == 1 {
-        #[cfg(unix)]
-        {
-            repl(root)
+        if formatted == content {
+            continue
         }
-        #[cfg(not(unix))]
-        {
-            println!("Oops! REPL is not available on non-unix platforms!");
+
+        if check {
+            println!("would reformat {}", file);
+            ok = false
+        } else if let Err(why) = std::fs::write(file, formatted) {
+            eprintln!("{} failed to write {}: {}", "error:".red().bold(), file, why);
+            ok = false
+        } else {
+            println!("formatted {}", file)
         }
-    } else {
-        for arg in args[1..].iter() {
-            run_file(arg, root.clone())
+    }
+
+    ok
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+
+    let options = match cli::parse(&args) {
+        Ok(options) => options,
+        Err(why) => {
+            eprintln!("{} {}\n\n{}", "error:".red().bold(), why, cli::USAGE);
+            std::process::exit(cli::USAGE_ERROR)
         }
+    };
+
+    if !options.color {
+        colored::control::set_override(false)
     }
+
+    let failed = |ok: bool| if ok { 0 } else { 1 };
+
+    let code = match options.command {
+        Command::Run(ref script, ref args) => run(script, args),
+        Command::Project(ref args) => project(args),
+
+        Command::Repl(ref file) => {
+            let root = file.as_ref().map_or(".".to_string(), |file| root(&Script::File(file.clone())));
+
+            repl(root, file.as_deref())
+        },
+
+        Command::Check(ref paths) => failed(check(paths)),
+        Command::Fmt(check, ref paths) => failed(fmt(check, paths)),
+        Command::Test(ref paths) => failed(test(paths)),
+
+        Command::Lsp => {
+            let stdin = io::stdin();
+
+            failed(matches!(lsp::run(stdin.lock(), io::stdout()), Ok(true)))
+        },
+
+        Command::Help => {
+            print!("{}", cli::USAGE);
+            0
+        },
+
+        Command::Version => {
+            println!("hugorm {}", env!("CARGO_PKG_VERSION"));
+            0
+        },
+    };
+
+    std::process::exit(code)
 }
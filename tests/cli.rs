@@ -0,0 +1,200 @@
+extern crate hugorm;
+
+mod common;
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command as Process, Output, Stdio};
+
+use hugorm::cli::{self, Command, Script};
+
+use common::Project;
+
+fn parse(args: &[&str]) -> Result<cli::Options, String> {
+    cli::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>())
+}
+
+fn strings(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+fn hugorm(args: &[&str], stdin: &str) -> Output {
+    hugorm_in(&std::env::current_dir().unwrap(), args, stdin)
+}
+
+fn hugorm_in(dir: &Path, args: &[&str], stdin: &str) -> Output {
+    let mut child = Process::new(env!("CARGO_BIN_EXE_hugorm"))
+        .args(args)
+        .current_dir(dir)
+        .env("HUGORM_DIAGNOSTICS", "plain")
+        .env("HUGORM_INIT", "")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn parses_subcommands() {
+    assert_eq!(parse(&[]).unwrap().command, Command::Repl(None));
+    assert_eq!(parse(&["repl", "a.hug"]).unwrap().command, Command::Repl(Some("a.hug".into())));
+    assert_eq!(parse(&["-i", "a.hug"]).unwrap().command, Command::Repl(Some("a.hug".into())));
+    assert_eq!(parse(&["check"]).unwrap().command, Command::Check(Vec::new()));
+    assert_eq!(parse(&["fmt", "--check", "src"]).unwrap().command, Command::Fmt(true, strings(&["src"])));
+    assert_eq!(parse(&["test", "--no-color", "a", "b"]).unwrap(), cli::Options {
+        command: Command::Test(strings(&["a", "b"])),
+        color: false,
+    });
+    assert_eq!(parse(&["--help"]).unwrap().command, Command::Help);
+    assert_eq!(parse(&["run"]).unwrap().command, Command::Project(Vec::new()));
+    assert_eq!(parse(&["run", "--no-color", "--", "a", "-b"]).unwrap(), cli::Options {
+        command: Command::Project(strings(&["a", "-b"])),
+        color: false,
+    });
+}
+
+#[test]
+fn passes_everything_after_the_program_along() {
+    assert_eq!(
+        parse(&["a.hug", "-e", "--no-color"]).unwrap(),
+        cli::Options { command: Command::Run(Script::File("a.hug".into()), strings(&["-e", "--no-color"])), color: true }
+    );
+
+    assert_eq!(
+        parse(&["--no-color", "run", "-e", "print(1)", "x"]).unwrap(),
+        cli::Options { command: Command::Run(Script::Code("print(1)".into()), strings(&["x"])), color: false }
+    );
+
+    assert_eq!(parse(&["-", "1"]).unwrap().command, Command::Run(Script::Stdin, strings(&["1"])));
+    assert_eq!(parse(&["--", "-weird.hug"]).unwrap().command, Command::Run(Script::File("-weird.hug".into()), Vec::new()));
+}
+
+#[test]
+fn refuses_nonsense() {
+    assert!(parse(&["-e"]).is_err());
+    assert!(parse(&["--bogus"]).is_err());
+    assert!(parse(&["fmt", "--fast"]).is_err());
+    assert!(parse(&["lsp", "x"]).is_err());
+}
+
+#[test]
+fn runs_code_from_arguments_and_stdin() {
+    let output = hugorm(&["-e", "print(len(args))\nprint(args[1])", "a", "b"], "");
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "2\nb\n");
+    assert!(output.status.success());
+
+    let output = hugorm(&["-", "x"], "print(args[0])\n");
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "x\n");
+    assert!(output.status.success());
+}
+
+#[test]
+fn exits_with_an_error_when_something_goes_wrong() {
+    assert_eq!(hugorm(&["-e", "let = 1"], "").status.code(), Some(1));
+    assert_eq!(hugorm(&["-e", "let l = [1]\nprint(l[\"x\"])"], "").status.code(), Some(3));
+    assert_eq!(hugorm(&["does/not/exist.hug"], "").status.code(), Some(1));
+    assert_eq!(hugorm(&["--bogus"], "").status.code(), Some(2));
+
+    let output = hugorm(&["-e", "print(\"before\")\nlet l = [1]\nprint(l[\"x\"])"], "");
+
+    // the program's output stays apart from what went wrong
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "before\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("runtime error"));
+}
+
+#[test]
+fn reads_piped_repl_input_an_entry_at_a_time() {
+    let output = hugorm(&["repl"], "fun twice(x):\n    let y = x * 2\n    return y\n\nprint(twice(21))\nprint(input())\nfed\n");
+
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("42\nfed\n"), "{}", String::from_utf8_lossy(&output.stdout));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert!(output.status.success());
+}
+
+#[test]
+fn checks_without_running() {
+    let dir = Project::new(&[]);
+    let path = dir.write("check.hug", "print(\"ran\")\n");
+
+    let output = hugorm(&["check", &path.display().to_string()], "");
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    assert!(output.status.success());
+
+    dir.write("check.hug", "print(nope)\n");
+
+    assert_eq!(hugorm(&["check", &path.display().to_string()], "").status.code(), Some(1));
+}
+
+#[test]
+fn exits_with_whatever_exit_got() {
+    let output = hugorm(&["-e", "print(1)\nexit(7)\nprint(2)"], "");
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+    assert_eq!(output.status.code(), Some(7));
+
+    assert_eq!(hugorm(&["-e", "exit(0)\nlet l = [1]\nprint(l[\"x\"])"], "").status.code(), Some(0));
+}
+
+#[test]
+fn exit_codes_fit_in_a_byte() {
+    for code in ["300", "2.7", "-1"] {
+        let output = hugorm(&["-e", &format!("exit({})", code)], "");
+
+        assert_eq!(output.status.code(), Some(3), "exit({})", code);
+        assert!(String::from_utf8_lossy(&output.stderr).contains(&format!("can't exit with {}", code)));
+    }
+
+    assert_eq!(hugorm(&["-e", "exit(255)"], "").status.code(), Some(255));
+}
+
+#[test]
+fn reads_the_environment() {
+    std::env::set_var("HUGORM_CLI_TEST", "hug");
+
+    let output = hugorm(&["-e", "print(env(\"HUGORM_CLI_TEST\"))\nprint(env(\"HUGORM_CLI_UNSET\"))"], "");
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hug\nnil\n");
+}
+
+#[test]
+fn runs_the_project_it_is_in() {
+    let dir = Project::new(&[
+        ("app/hugorm.toml", "[package]\nname = \"app\"\nversion = \"0.1.0\"\nentry = \"src/main.hug\"\n\n[dependencies]\nhelpers = \"../helpers\"\n"),
+        ("app/src/main.hug", "grab helpers\nprint(helpers.greeting)\nfor arg in args:\n    print(arg)\n"),
+        ("helpers/mod.hug", "let greeting = \"hi\"\n"),
+    ]);
+
+    let output = hugorm_in(&dir.join("app/src"), &["run"], "");
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hi\n");
+    assert!(output.status.success());
+
+    let output = hugorm_in(&dir.join("app"), &["run", "--", "a", "--b"], "");
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hi\na\n--b\n");
+
+    assert_eq!(hugorm_in(&dir, &["run"], "").status.code(), Some(1));
+}
+
+#[test]
+fn grabs_from_the_std_folder_next_to_the_binary() {
+    let std = Path::new(env!("CARGO_BIN_EXE_hugorm")).parent().unwrap().join("std");
+
+    fs::create_dir_all(&std).unwrap();
+    fs::write(std.join("cli_std_probe.hug"), "let greeting = \"from std\"\n").unwrap();
+
+    let output = hugorm(&["-e", "grab cli_std_probe\nprint(cli_std_probe.greeting)"], "");
+
+    let _ = fs::remove_file(std.join("cli_std_probe.hug"));
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "from std\n");
+}
@@ -0,0 +1,271 @@
+extern crate hugorm;
+
+use hugorm::Engine;
+
+fn run(code: &str) -> Engine {
+    let mut engine = Engine::new();
+
+    if let Err(diagnostics) = engine.eval_str("<test>", code) {
+        panic!("failed to compile:\n{:#?}", diagnostics)
+    }
+
+    engine
+}
+
+fn number(engine: &Engine, name: &str) -> f64 {
+    engine.get_global(name).unwrap_or_else(|| panic!("no global `{}`", name)).as_float()
+}
+
+fn string(engine: &Engine, name: &str) -> String {
+    let value = engine.get_global(name).unwrap_or_else(|| panic!("no global `{}`", name));
+
+    format!("{}", value.with_heap(engine.heap()))
+}
+
+#[test]
+fn counter_keeps_its_own_state() {
+    let engine = run(r#"
+fun make-counter():
+  let n = 0
+  fun inc():
+    n += 1
+    return n
+  return inc
+
+let a = make-counter()
+let b = make-counter()
+a()
+a()
+b()
+let from-a = a()
+let from-b = b()
+"#);
+
+    assert_eq!(number(&engine, "from-a"), 3.0);
+    assert_eq!(number(&engine, "from-b"), 2.0);
+}
+
+#[test]
+fn factory_captures_params() {
+    let engine = run(r#"
+fun adder(x):
+  return fun(y):
+    return x + y
+
+let add5 = adder(5)
+let add7 = adder(7)
+let result = add5(10) + add7(10)
+"#);
+
+    assert_eq!(number(&engine, "result"), 32.0);
+}
+
+#[test]
+fn captured_params_can_change() {
+    let engine = run(r#"
+fun counter(start):
+  return fun():
+    start += 1
+    return start
+
+let c = counter(10)
+c()
+let result = c()
+"#);
+
+    assert_eq!(number(&engine, "result"), 12.0);
+}
+
+#[test]
+fn changes_are_seen_both_ways() {
+    let engine = run(r#"
+fun f():
+  let n = 0
+  let bump = fun():
+    n += 1
+  bump()
+  bump()
+  let seen = n
+  n = 10
+  bump()
+  return seen * 100 + n
+
+let result = f()
+"#);
+
+    assert_eq!(number(&engine, "result"), 211.0);
+}
+
+#[test]
+fn nested_closures_reach_outer_locals() {
+    let engine = run(r#"
+fun outer():
+  let a = 1
+  fun middle():
+    fun inner():
+      a += 10
+      return a
+    return inner
+  let g = middle()
+  g()
+  return a + g()
+
+let result = outer()
+"#);
+
+    assert_eq!(number(&engine, "result"), 32.0);
+}
+
+#[test]
+fn local_functions_can_recurse() {
+    let engine = run(r#"
+fun fact-maker():
+  fun fact(n):
+    if n < 2:
+      return 1
+    return n * fact(n - 1)
+  return fact
+
+let result = fact-maker()(5)
+"#);
+
+    assert_eq!(number(&engine, "result"), 120.0);
+}
+
+#[test]
+fn callbacks_update_the_caller() {
+    let engine = run(r#"
+fun each(xs, f):
+  for x in xs:
+    f(x)
+
+fun total(xs):
+  let sum = 0
+  each(xs, fun(x):
+    sum += x
+  )
+  return sum
+
+let result = total([1, 2, 3, 4])
+"#);
+
+    assert_eq!(number(&engine, "result"), 10.0);
+}
+
+// variables belong to the function, so closures made in a loop share them
+#[test]
+fn closures_in_loops_share_the_loop_variables() {
+    let engine = run(r#"
+fun make():
+  let hooks = {}
+  let count = 0
+  for name in ["a", "b", "c"]:
+    let label = name
+    hooks[name] = fun(): return label
+    count += 1
+  let bump = fun(): count += 1
+  bump()
+  return [hooks.a(), count]
+
+let result = make()
+let label = result[0]
+let count = result[1]
+"#);
+
+    assert_eq!(string(&engine, "label"), "c");
+    assert_eq!(number(&engine, "count"), 4.0);
+}
+
+#[test]
+fn closures_in_loops_see_each_step() {
+    let engine = run(r#"
+fun walk():
+  let seen = 0
+  let i = 0
+  while i < 4:
+    let step = i
+    let add = fun(): seen = seen + step
+    add()
+    i += 1
+  return seen
+
+let result = walk()
+"#);
+
+    assert_eq!(number(&engine, "result"), 6.0);
+}
+
+// no fresh binding per iteration either, the `for` variable is the function's too
+#[test]
+fn closures_in_loops_share_the_for_variable() {
+    let engine = run(r#"
+fun make():
+  let fs = [0, 0, 0]
+  let n = 0
+  for x in [1, 2, 3]:
+    fs[n] = fun(): return x
+    n += 1
+  return fs[0]() * 100 + fs[1]() * 10 + fs[2]()
+
+let result = make()
+"#);
+
+    assert_eq!(number(&engine, "result"), 333.0);
+}
+
+// a `let` in a block declares the function's variable again, whether or not the block runs
+#[test]
+fn declarations_in_blocks_are_the_functions_variables() {
+    let engine = run(r#"
+fun redeclare(run):
+  let x = 1
+  let seen = fun(): return x
+  if run:
+    let x = 2
+  return x * 10 + seen()
+
+let skipped = redeclare(false)
+let ran = redeclare(true)
+
+let y = 1
+let run = false
+while run:
+  let y = 2
+"#);
+
+    assert_eq!(number(&engine, "skipped"), 11.0);
+    assert_eq!(number(&engine, "ran"), 22.0);
+    assert_eq!(number(&engine, "y"), 1.0);
+}
+
+#[test]
+fn closures_in_dicts() {
+    let engine = run(r#"
+let foo = {
+    f: fun(a): return 10 + a
+}
+
+fun make-pair():
+  let n = 0
+  return {
+    inc: fun():
+      n += 1
+      return n
+    dec: fun():
+      n -= 1
+      return n
+  }
+
+fun extra():
+  return foo.f(10)
+
+let pair = make-pair()
+pair.inc()
+pair.inc()
+let pair-result = pair.dec()
+let foo-result = extra()
+"#);
+
+    assert_eq!(number(&engine, "foo-result"), 20.0);
+    assert_eq!(number(&engine, "pair-result"), 1.0);
+}
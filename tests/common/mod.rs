@@ -0,0 +1,73 @@
+// not every test file uses all of it
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, Write};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// a writer to hand `Engine::set_output`, which the test can still read from afterwards
+#[derive(Clone, Default)]
+pub struct Shared(pub Rc<RefCell<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// a folder of files for one test, named so that no other test (or run of the tests)
+// shares it, and removed again once the test is done with it
+pub struct Project(PathBuf);
+
+impl Project {
+    // `files` are paths in it and what's in them
+    pub fn new(files: &[(&str, &str)]) -> Self {
+        static MADE: AtomicUsize = AtomicUsize::new(0);
+
+        let name = format!("hugorm-test-{}-{}", process::id(), MADE.fetch_add(1, Ordering::SeqCst));
+        let project = Project(std::env::temp_dir().join(name));
+
+        let _ = fs::remove_dir_all(&project.0);
+        fs::create_dir_all(&project.0).unwrap();
+
+        for &(file, content) in files.iter() {
+            project.write(file, content);
+        }
+
+        project
+    }
+
+    // writes `file`, folders and all, and hands back where it ended up
+    pub fn write<C: AsRef<[u8]>>(&self, file: &str, content: C) -> PathBuf {
+        let path = self.0.join(file);
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+
+        path
+    }
+}
+
+impl Deref for Project {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for Project {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
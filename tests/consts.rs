@@ -0,0 +1,77 @@
+extern crate hugorm;
+
+use hugorm::lexer::Pos;
+use hugorm::{Diagnostic, Engine};
+
+// the one error compiling `code` gives, along with its note
+fn error(code: &str) -> Diagnostic {
+    let errors = Engine::new().compile_str("<test>", code).expect_err("it was supposed to fail");
+
+    errors.into_iter().find(|d| d.is_error()).unwrap()
+}
+
+// (line, start, end)
+fn span(diagnostic: &Diagnostic) -> (usize, usize, usize) {
+    let Pos((line, _), (start, end)) = diagnostic.span.clone().unwrap();
+
+    (line, start, end)
+}
+
+#[test]
+fn cant_be_assigned() {
+    let error = error("const a = 1\na = 2\n");
+
+    assert_eq!(error.message, "can't assign twice to constant `a`");
+    assert_eq!(span(&error), (2, 1, 1));
+
+    assert_eq!(error.notes[0].message, "`a` was declared constant here");
+    assert_eq!(span(&error.notes[0]), (1, 1, 11));
+}
+
+#[test]
+fn cant_be_compound_assigned() {
+    let error = error("const a = 1\na += 2\n");
+
+    assert_eq!(error.message, "can't assign twice to constant `a`");
+    assert_eq!(span(&error).0, 2);
+    assert_eq!(span(&error.notes[0]), (1, 1, 11));
+}
+
+#[test]
+fn cant_be_declared_again() {
+    for code in ["const a = 1\nconst a = 2\n", "const a = 1\nlet a = 2\n"] {
+        let error = error(code);
+
+        assert_eq!(error.message, "can't declare constant `a` again");
+        assert_eq!(span(&error), (2, 1, code.lines().nth(1).unwrap().len()));
+        assert_eq!(span(&error.notes[0]), (1, 1, 11));
+    }
+}
+
+#[test]
+fn const_functions_cant_be_assigned() {
+    let error = error("const fun g():\n    return 1\ng = 2\n");
+
+    assert_eq!(error.message, "can't assign twice to constant `g`");
+    assert_eq!(span(&error), (3, 1, 1));
+    assert_eq!(span(&error.notes[0]), (1, 7, 12));
+}
+
+#[test]
+fn stay_constant_in_nested_blocks() {
+    let error = error("fun f():\n    const a = 1\n    if true:\n        a = 3\n");
+
+    assert_eq!(error.message, "can't assign twice to constant `a`");
+    assert_eq!(span(&error), (4, 9, 9));
+    assert_eq!(span(&error.notes[0]), (2, 5, 15));
+}
+
+#[test]
+fn can_be_shadowed_in_a_function() {
+    let mut engine = Engine::new();
+
+    engine.eval_str("<test>", "const a = 1\nfun f():\n    const a = 2\n    return a + 1\nlet b = f()\n").unwrap();
+
+    assert_eq!(engine.get_global("a").unwrap().as_float(), 1.0);
+    assert_eq!(engine.get_global("b").unwrap().as_float(), 3.0);
+}
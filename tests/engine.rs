@@ -0,0 +1,134 @@
+extern crate hugorm;
+extern crate zub;
+
+mod common;
+
+use zub::vm::{Heap, Object, Value, Variant};
+
+use hugorm::runtime::RuntimeError;
+use hugorm::{Engine, Ran};
+
+use common::Shared;
+
+fn double(_heap: &mut Heap<Object>, args: &[Value]) -> Result<Value, RuntimeError> {
+    match args[1].decode() {
+        Variant::Float(n) => Ok(Value::float(n * 2.0)),
+        _ => Ok(Value::nil()),
+    }
+}
+
+#[test]
+fn calls_registered_natives() {
+    let mut engine = Engine::new();
+
+    engine.register_native("double", 1, double);
+    engine.eval_str("<test>", "let x = double(21)\n").unwrap();
+
+    assert_eq!(engine.get_global("x").unwrap().as_float(), 42.0);
+
+    // the type checker knows how many arguments it takes
+    let errors = engine.eval_str("<test>", "double(1, 2)\n").expect_err("it was supposed to fail");
+
+    assert_eq!(errors[0].message, "wrong amount of arguments, expected 1 but got 2");
+}
+
+#[test]
+fn globals_go_both_ways() {
+    let mut engine = Engine::new();
+
+    engine.set_global("n", Value::float(2.0));
+    engine.eval_str("<test>", "let m = n * 3\n").unwrap();
+
+    assert_eq!(engine.get_global("m").unwrap().as_float(), 6.0);
+    assert!(engine.get_global("nope").is_none());
+
+    // they're the embedder's to change, not the script's
+    let errors = engine.eval_str("<test>", "n = 5\n").expect_err("it was supposed to fail");
+
+    assert_eq!(errors[0].message, "can't assign to builtin `n`");
+
+    engine.set_global("n", Value::float(5.0));
+    engine.eval_str("<test>", "m = n + 1\n").unwrap();
+
+    assert_eq!(engine.get_global("m").unwrap().as_float(), 6.0);
+}
+
+#[test]
+fn compiling_runs_nothing() {
+    let output = Shared::default();
+
+    let mut engine = Engine::new();
+    engine.set_output(Some(Box::new(output.clone())));
+
+    let (ir, warnings) = engine.compile_str("<test>", "let y = 1\nprint(\"hi\")\nfun f(x):\n    return 1\n").unwrap();
+
+    assert!(!ir.is_empty());
+    assert_eq!(warnings.iter().map(|d| d.message.as_str()).collect::<Vec<_>>(), vec!("unused parameter `x`"));
+    assert!(output.0.borrow().is_empty());
+    assert!(engine.get_global("y").is_none());
+
+    // running it is up to the caller
+    let ran = engine.exec(&ir);
+
+    assert!(ran.is_ok());
+    assert_eq!(engine.get_global("y").unwrap().as_float(), 1.0);
+    assert_eq!(output.0.borrow().as_slice(), b"hi\n");
+}
+
+#[test]
+fn reports_compile_errors_without_running() {
+    let mut engine = Engine::new();
+
+    let errors = engine.compile_str("<test>", "let a = 1\nprint(nope)\n").expect_err("it was supposed to fail");
+
+    assert_eq!(errors[0].message, "no such variable `nope`");
+    assert!(engine.get_global("a").is_none());
+}
+
+#[test]
+fn exit_comes_back_as_a_value() {
+    let mut engine = Engine::new();
+
+    let ran = engine.eval_str("<test>", "let a = 1\nexit(4)\nlet b = 2\n").unwrap();
+
+    assert_eq!(ran, Ran { warnings: vec!(), exit: Some(4) });
+    assert_eq!(engine.get_global("a").unwrap().as_float(), 1.0);
+
+    // and the engine still works afterwards
+    let ran = engine.eval_str("<test>", "let c = a + 1\n").unwrap();
+
+    assert_eq!(ran.exit, None);
+    assert_eq!(engine.get_global("c").unwrap().as_float(), 2.0);
+}
+
+#[test]
+fn engines_keep_their_own_output() {
+    let (first, second) = (Shared::default(), Shared::default());
+
+    let mut a = Engine::new();
+    let mut b = Engine::new();
+
+    a.set_output(Some(Box::new(first.clone())));
+    b.set_output(Some(Box::new(second.clone())));
+
+    a.eval_str("<a>", "print(\"a\")\n").unwrap();
+    b.eval_str("<b>", "print(\"b\")\n").unwrap();
+    a.eval_str("<a>", "print(\"a again\")\n").unwrap();
+
+    assert_eq!(first.0.borrow().as_slice(), b"a\na again\n");
+    assert_eq!(second.0.borrow().as_slice(), b"b\n");
+}
+
+#[test]
+fn prompts_with_or_without_a_question() {
+    let output = Shared::default();
+
+    let mut engine = Engine::new();
+    engine.set_output(Some(Box::new(output.clone())));
+    engine.set_input(Some(Box::new(std::io::Cursor::new(b"worm\nhugorm\n".to_vec()))));
+
+    engine.eval_str("<prompt>", "let a = prompt()\nlet b = prompt(\"who? \")\nprint(a + b)\n").unwrap();
+
+    assert_eq!(output.0.borrow().as_slice(), b"> who? wormhugorm\n");
+    assert!(engine.eval_str("<prompt>", "prompt(1, 2)\n").is_err());
+}
@@ -0,0 +1,81 @@
+extern crate hugorm;
+
+mod common;
+
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use hugorm::{Engine, Render};
+
+use common::Shared;
+
+// every example in `examples/` against `tests/golden/<name>.stdout` and `.diagnostics`,
+// fed `tests/golden/<name>.stdin` when there is one. `HUGORM_BLESS=1` rewrites them
+
+fn run(path: &Path, stdin: String) -> (String, String) {
+    let output = Shared::default();
+
+    let mut engine = Engine::new();
+    engine.set_root("examples");
+    engine.set_output(Some(Box::new(output.clone())));
+    engine.set_input(Some(Box::new(Cursor::new(stdin.into_bytes()))));
+
+    let diagnostics = match engine.eval_file(&path.display().to_string()) {
+        Ok(ran) => ran.warnings,
+        Err(errors) => errors,
+    };
+
+    let rendered = diagnostics.iter().map(|d| Render::Plain.render(d)).collect::<String>();
+    let stdout = String::from_utf8(output.0.borrow().clone()).unwrap();
+
+    (stdout, rendered)
+}
+
+#[test]
+fn examples_match_golden_output() {
+    let bless = std::env::var("HUGORM_BLESS").is_ok_and(|v| v == "1");
+
+    let mut examples = fs::read_dir("examples").unwrap()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "hug"))
+        .collect::<Vec<_>>();
+
+    examples.sort();
+
+    assert!(!examples.is_empty());
+
+    let mut failed = Vec::new();
+
+    for example in examples.iter() {
+        let name = example.file_stem().unwrap().to_string_lossy().to_string();
+        let golden = Path::new("tests/golden").join(&name);
+
+        let stdin = fs::read_to_string(golden.with_extension("stdin")).unwrap_or_default();
+        let (stdout, diagnostics) = run(example, stdin);
+
+        for (kind, actual) in [("stdout", stdout), ("diagnostics", diagnostics)] {
+            let expected_path = golden.with_extension(kind);
+
+            if bless {
+                fs::write(&expected_path, &actual).unwrap();
+                continue
+            }
+
+            match fs::read_to_string(&expected_path) {
+                Ok(ref expected) if *expected == actual => (),
+                Ok(expected) => failed.push(format!(
+                    "{} {} differs\n--- expected\n{}\n--- actual\n{}",
+                    name, kind, expected, actual
+                )),
+                Err(_) => failed.push(format!(
+                    "{} has no {}, run with HUGORM_BLESS=1 to write it",
+                    name, expected_path.display()
+                )),
+            }
+        }
+    }
+
+    assert!(failed.is_empty(), "\n{}", failed.join("\n\n"))
+}
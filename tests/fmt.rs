@@ -0,0 +1,83 @@
+extern crate hugorm;
+
+use std::fs;
+
+use hugorm::formatter;
+use hugorm::Engine;
+use hugorm::source::hug_files;
+
+fn format(content: &str) -> String {
+    formatter::format("test.hug", content).unwrap_or_else(|errors| panic!("{:?}", errors))
+}
+
+#[test]
+fn examples_are_stable() {
+    let examples = hug_files(&["examples".to_string()]);
+
+    assert!(!examples.is_empty());
+
+    for example in examples.iter() {
+        let once = format(&fs::read_to_string(example).unwrap());
+
+        assert_eq!(format(&once), once, "{} isn't stable", example)
+    }
+}
+
+#[test]
+fn keeps_comments() {
+    let code = "# up top\nlet a = 1   # trailing\n\n\n\nfun f():\n    # inside\n    return a\n# the end\n";
+
+    assert_eq!(
+        format(code),
+        "# up top\nlet a = 1  # trailing\n\nfun f():\n    # inside\n    return a\n# the end\n"
+    );
+}
+
+#[test]
+fn prints_the_canonical_form() {
+    let code = "let b=[1,2,3]\nlet d = {x: 1,y: 2}\nunless b:\n  b -= 1\nloop 3: let n = -(2 * 3)\nlet p = (1 + 2) * 3\n";
+
+    assert_eq!(
+        format(code),
+        "let b = [1, 2, 3]\nlet d = {x: 1, y: 2}\nunless b:\n    b -= 1\nloop 3: let n = -(2 * 3)\nlet p = (1 + 2) * 3\n"
+    );
+}
+
+#[test]
+fn keeps_block_bodied_anonymous_functions() {
+    let code = "let g = map(b, fun(x):\n  let y = x + 1\n  return y\n)\n";
+
+    assert_eq!(format(code), "let g = map(b, fun(x):\n    let y = x + 1\n    return y\n)\n");
+}
+
+#[test]
+fn prints_every_kind_of_grab() {
+    assert_eq!(format("grab  foo\ngrab foo as  f\ngrab a ,b from foo\n"), "grab foo\ngrab foo as f\ngrab a, b from foo\n");
+}
+
+#[test]
+fn takes_block_indentation_from_past_a_leading_comment() {
+    let code = "fun f(x):\n    # docs\n    if x:\n        return 1\n    return 2\n\nif f(false):\n    print(1)\nelse:\n    # nothing\n    print(3)\n";
+
+    assert_eq!(format(code), code);
+    assert!(Engine::new().compile_str("test.hug", code).is_ok());
+
+    let odd = "let x = true\nif x:\n  print(1)\nelse:\n        # nothing\n        print(3)\n";
+
+    assert!(Engine::new().compile_str("test.hug", odd).is_ok());
+}
+
+#[test]
+fn keeps_trailing_comments_inside_brackets() {
+    let code = "let l = [1, # one\n  2, 3] # after list\nlet m = [\n  [1, 2], # inner\n  3\n] # outer\nlet d = {\n  a: 1 # a\n  b: 2\n}\n";
+
+    assert_eq!(
+        format(code),
+        "let l = [\n    1,  # one\n    2, 3\n]  # after list\nlet m = [\n    [1, 2],  # inner\n    3\n]  # outer\nlet d = {\n    a: 1  # a\n    b: 2\n}\n"
+    );
+}
+
+#[test]
+fn refuses_broken_code() {
+    assert!(formatter::format("test.hug", "let = 1\n").is_err())
+}
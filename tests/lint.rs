@@ -0,0 +1,120 @@
+extern crate hugorm;
+
+use hugorm::Engine;
+
+// the warnings, as `line: message`
+fn lint(code: &str) -> Vec<String> {
+    let mut engine = Engine::new();
+
+    match engine.compile_str("<test>", code) {
+        Ok((_, warnings)) => warnings.iter()
+            .map(|d| format!("{}: {}", (d.span.as_ref().unwrap().0).0, d.message))
+            .collect(),
+
+        Err(diagnostics) => panic!("failed to compile:\n{:#?}", diagnostics),
+    }
+}
+
+#[test]
+fn finds_unused_and_shadowed_names() {
+    let warnings = lint(r#"
+let x = 1
+
+fun f(a, b, _c):
+    let unused = 2
+    let x = 3
+    return a + x
+
+f(1, 2, 3)
+"#);
+
+    // top to bottom, not in the order the rules ran
+    assert_eq!(warnings, vec!(
+        "4: unused parameter `b`",
+        "5: unused variable `unused`",
+        "6: `x` shadows the one declared on line 2",
+    ));
+}
+
+#[test]
+fn finds_unused_names_in_the_files_loops_and_branches() {
+    let warnings = lint(r#"
+let exported = 1
+let run = false
+
+while false:
+    let never = 1
+
+if run:
+    let used = 2
+    print(used)
+"#);
+
+    assert_eq!(warnings, vec!(
+        "5: this condition is always false",
+        "6: unused variable `never`",
+    ));
+}
+
+#[test]
+fn finds_dead_and_pointless_code() {
+    let warnings = lint(r#"
+fun f():
+    if true:
+        return 1
+        print("never")
+    loop 3:
+        break
+    loop:
+        break
+    unless false:
+        print("hm")
+    return 0
+
+f()
+"#);
+
+    assert_eq!(warnings, vec!(
+        "3: this condition is always true",
+        "5: this is never reached",
+        "10: this condition is always true",
+    ));
+
+    assert_eq!(lint("let a = [1]\nfor x in a:\n"), vec!("2: this body is empty"));
+}
+
+#[test]
+fn checks_interface_methods() {
+    let warnings = lint(r#"
+interface Brr:
+    fun add(a):
+        return a + 100
+
+let foo = {x: fun(): 1} with Brr
+foo.add(1)
+foo.x()
+foo.nope(2)
+"#);
+
+    assert_eq!(warnings, vec!("9: `nope` isn't a method of `Brr`"));
+}
+
+#[test]
+fn pragmas_switch_rules() {
+    let warnings = lint(r#"
+# lint: allow all
+# lint: warn kebab-end
+# lint: allow nonsense
+
+fun f(a):
+    let b- = 1
+    return 2
+
+f(1)
+"#);
+
+    assert_eq!(warnings, vec!(
+        "4: no lint called `nonsense`",
+        "7: kebab-case at identifier end is not cool",
+    ));
+}
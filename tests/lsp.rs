@@ -0,0 +1,258 @@
+extern crate hugorm;
+extern crate serde_json;
+
+use std::io::Cursor;
+
+use hugorm::lsp;
+use serde_json::{json, Value};
+
+const URI: &str = "file:///tmp/hugorm-lsp/main.hug";
+
+const CODE: &str = "fun add-one(a, b):
+    let x = a + b
+    return x
+
+interface Brr:
+    fun add(a):
+        return a + 100
+
+let foo = {} with Brr
+let total = add-one(1, 2)
+foo.add(total)
+const limit = 3
+";
+
+// runs a whole session and hands back everything the server said
+fn session(messages: Vec<Value>) -> (bool, Vec<Value>) {
+    let mut input = Vec::new();
+
+    for message in messages.iter() {
+        lsp::write_message(&mut input, message).unwrap()
+    }
+
+    let mut output = Vec::new();
+    let clean = lsp::run(Cursor::new(input), &mut output).unwrap();
+
+    let mut replies = Vec::new();
+    let mut output = Cursor::new(output);
+
+    while let Some(reply) = lsp::read_message(&mut output).unwrap() {
+        replies.push(reply)
+    }
+
+    (clean, replies)
+}
+
+fn open(text: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": { "textDocument": { "uri": URI, "languageId": "hugorm", "version": 1, "text": text } },
+    })
+}
+
+fn request(id: u64, method: &str, line: u64, character: u64) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": method,
+        "params": {
+            "textDocument": { "uri": URI },
+            "position": { "line": line, "character": character },
+        },
+    })
+}
+
+fn result(replies: &[Value], id: u64) -> Value {
+    replies.iter().find(|reply| reply["id"] == json!(id)).unwrap_or_else(|| panic!("no reply to {}", id))["result"].clone()
+}
+
+fn diagnostics(replies: &[Value]) -> Vec<Value> {
+    replies.iter()
+        .filter(|reply| reply["method"] == "textDocument/publishDiagnostics")
+        .map(|reply| reply["params"]["diagnostics"].clone())
+        .collect()
+}
+
+#[test]
+fn initializes_and_shuts_down() {
+    let (clean, replies) = session(vec!(
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+        json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" }),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ));
+
+    let capabilities = &result(&replies, 1)["capabilities"];
+
+    assert_eq!(capabilities["textDocumentSync"], 1);
+    assert_eq!(capabilities["hoverProvider"], true);
+    assert!(clean);
+}
+
+#[test]
+fn counts_columns_in_utf16_unless_told_otherwise() {
+    let code = "let s = \"🐛\"\nprint(\"🐛\" + s + nope)\n";
+
+    let initialize = |encodings: Value| json!({
+        "jsonrpc": "2.0", "id": 1, "method": "initialize",
+        "params": { "capabilities": { "general": { "positionEncodings": encodings } } },
+    });
+
+    let (_, replies) = session(vec!(initialize(json!(["utf-16"])), open(code), request(2, "textDocument/definition", 1, 13)));
+
+    assert_eq!(result(&replies, 1)["capabilities"]["positionEncoding"], "utf-16");
+    assert_eq!(diagnostics(&replies)[0][0]["range"]["start"], json!({ "line": 1, "character": 17 }));
+    assert_eq!(result(&replies, 2)["range"]["start"], json!({ "line": 0, "character": 4 }));
+
+    let (_, replies) = session(vec!(initialize(json!(["utf-32", "utf-16"])), open(code), request(2, "textDocument/definition", 1, 12)));
+
+    assert_eq!(result(&replies, 1)["capabilities"]["positionEncoding"], "utf-32");
+    assert_eq!(diagnostics(&replies)[0][0]["range"]["start"], json!({ "line": 1, "character": 16 }));
+    assert_eq!(result(&replies, 2)["range"]["start"], json!({ "line": 0, "character": 4 }));
+}
+
+#[test]
+fn publishes_diagnostics_on_change() {
+    let (_, replies) = session(vec!(
+        open(CODE),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didChange",
+            "params": {
+                "textDocument": { "uri": URI, "version": 2 },
+                "contentChanges": [{ "text": "let a = 1\nprint(nope)\n" }],
+            },
+        }),
+    ));
+
+    let published = diagnostics(&replies);
+
+    assert_eq!(published.len(), 2);
+    assert!(published[0].as_array().unwrap().is_empty());
+
+    let error = &published[1][0];
+
+    assert_eq!(error["message"], "no such variable `nope`");
+    assert_eq!(error["severity"], 1);
+    assert_eq!(error["range"]["start"], json!({ "line": 1, "character": 6 }));
+    assert_eq!(error["range"]["end"], json!({ "line": 1, "character": 10 }));
+}
+
+#[test]
+fn survives_a_call_left_open_at_the_end() {
+    let change = |version: u64, text: &str| json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didChange",
+        "params": {
+            "textDocument": { "uri": URI, "version": version },
+            "contentChanges": [{ "text": text }],
+        },
+    });
+
+    let (_, replies) = session(vec!(
+        open("let a = 1\n"),
+        change(2, "let a = 1\nprint("),
+        change(3, "let a = 1\nprint(a,"),
+        request(4, "textDocument/hover", 0, 4),
+    ));
+
+    let published = diagnostics(&replies);
+
+    assert_eq!(published.len(), 3);
+    assert_eq!(published[1][0]["message"], "expected `)` but the file ended");
+    assert_eq!(published[2][0]["message"], "expected `)` but the file ended");
+    assert!(replies.iter().any(|reply| reply["id"] == json!(4)));
+}
+
+#[test]
+fn goes_to_definitions() {
+    let (_, replies) = session(vec!(
+        open(CODE),
+        request(1, "textDocument/definition", 9, 14), // add-one(
+        request(2, "textDocument/definition", 2, 11), // return x
+        request(3, "textDocument/definition", 10, 5), // foo.add
+        request(4, "textDocument/definition", 1, 12), // a + b
+    ));
+
+    assert_eq!(result(&replies, 1)["range"]["start"], json!({ "line": 0, "character": 4 }));
+    assert_eq!(result(&replies, 2)["range"]["start"], json!({ "line": 1, "character": 8 }));
+    assert_eq!(result(&replies, 3)["range"]["start"], json!({ "line": 5, "character": 8 }));
+    assert_eq!(result(&replies, 4)["range"]["start"], json!({ "line": 0, "character": 12 }));
+    assert_eq!(result(&replies, 1)["uri"], URI);
+}
+
+#[test]
+fn hovers_with_signatures_and_types() {
+    let (_, replies) = session(vec!(
+        open(CODE),
+        request(1, "textDocument/hover", 9, 14),
+        request(2, "textDocument/hover", 9, 5),
+        request(3, "textDocument/hover", 8, 5),
+        request(4, "textDocument/hover", 11, 7),
+        request(5, "textDocument/hover", 10, 5),
+    ));
+
+    let hover = |id| result(&replies, id)["contents"]["value"].as_str().unwrap().to_string();
+
+    assert!(hover(1).contains("fun add-one(a, b)"));
+    assert!(hover(2).contains("let total: any"));
+    assert!(hover(3).contains("let foo"));
+    assert!(hover(4).contains("const limit: int"));
+    assert!(hover(5).contains("from interface Brr"));
+}
+
+#[test]
+fn completes_names_keywords_and_methods() {
+    let (_, replies) = session(vec!(
+        open(CODE),
+        request(1, "textDocument/completion", 10, 0),
+        request(2, "textDocument/completion", 10, 4),
+    ));
+
+    let labels = |id| result(&replies, id).as_array().unwrap().iter()
+        .map(|item| item["label"].as_str().unwrap().to_string())
+        .collect::<Vec<String>>();
+
+    let names = labels(1);
+
+    for expected in ["add-one", "total", "Brr", "print", "while", "interface"].iter() {
+        assert!(names.contains(&expected.to_string()), "no `{}` in {:?}", expected, names)
+    }
+
+    assert!(!names.iter().any(|name| name.starts_with('$')));
+
+    assert_eq!(labels(2), vec!("add"));
+}
+
+#[test]
+fn lists_functions_and_interfaces() {
+    let (_, replies) = session(vec!(
+        open(CODE),
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "textDocument/documentSymbol",
+            "params": { "textDocument": { "uri": URI } },
+        }),
+    ));
+
+    let symbols = result(&replies, 1);
+
+    assert_eq!(symbols[0]["name"], "add-one");
+    assert_eq!(symbols[0]["kind"], 12);
+    assert_eq!(symbols[0]["range"]["end"]["line"], 2);
+    assert_eq!(symbols[1]["name"], "Brr");
+    assert_eq!(symbols[1]["children"][0]["name"], "add");
+    assert_eq!(symbols.as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn unknown_requests_get_an_error() {
+    let (clean, replies) = session(vec!(
+        json!({ "jsonrpc": "2.0", "id": 7, "method": "textDocument/rename", "params": {} }),
+    ));
+
+    assert_eq!(replies[0]["error"]["code"], -32601);
+    assert!(!clean);
+}
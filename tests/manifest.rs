@@ -0,0 +1,90 @@
+extern crate hugorm;
+
+mod common;
+
+use std::fs;
+
+use hugorm::manifest::{self, Dependency};
+
+use common::Project;
+
+// `manifest` is the one of `app`, `dirs` are empty folders next to it
+fn project(manifest: &str, dirs: &[&str]) -> Project {
+    let dir = Project::new(&[(&format!("app/{}", manifest::FILE), manifest)]);
+
+    for sub in dirs.iter() {
+        fs::create_dir_all(dir.join(sub)).unwrap()
+    }
+
+    dir
+}
+
+fn error(manifest: &str) -> (String, Option<usize>) {
+    let dir = project(manifest, &[]);
+    let error = manifest::load(&dir.join("app").join(manifest::FILE)).expect_err("it was supposed to fail");
+
+    (error.message, error.span.map(|span| (span.0).0))
+}
+
+#[test]
+fn reads_packages_and_dependencies() {
+    let dir = project(
+        "# what it is\n[package]\nname = \"app\"  # trailing\nversion = \"0.1.0\"\n\n[dependencies]\nhelpers = \"../helpers\"\ngeometry = { path = \"../geo#metry\" }\ncolors = { vendored = true }\n",
+        &["helpers", "geo#metry", "app/vendor/colors"],
+    );
+
+    let app = dir.join("app");
+    let manifest = manifest::load(&app.join(manifest::FILE)).unwrap();
+
+    assert_eq!(manifest.name, "app");
+    assert_eq!(manifest.version, "0.1.0");
+    assert_eq!(manifest.entry_path(), app.join("main.hug"));
+
+    assert_eq!(manifest.dependencies, vec!(
+        Dependency { name: "helpers".into(), path: app.join("../helpers") },
+        Dependency { name: "geometry".into(), path: app.join("../geo#metry") },
+        Dependency { name: "colors".into(), path: app.join("vendor").join("colors") },
+    ));
+}
+
+#[test]
+fn reads_any_toml() {
+    let dir = project(
+        "[package]\nname = 'app'\nversion = \"0.1.0\\u002dbeta\"\nentry = 'src\\main.hug'\n\n[dependencies.geometry]\npath = \"../geometry\"\n",
+        &["geometry"],
+    );
+
+    let app = dir.join("app");
+    let manifest = manifest::load(&app.join(manifest::FILE)).unwrap();
+
+    assert_eq!(manifest.name, "app");
+    assert_eq!(manifest.version, "0.1.0-beta");
+    assert_eq!(manifest.entry, "src\\main.hug");
+    assert_eq!(manifest.dependencies, vec!(Dependency { name: "geometry".into(), path: app.join("../geometry") }));
+}
+
+#[test]
+fn is_found_from_below() {
+    let dir = project("[package]\nname = \"app\"\nversion = \"1\"\n", &["app/src/deep"]);
+
+    let found = manifest::find(&dir.join("app/src/deep")).unwrap();
+
+    assert_eq!(found, fs::canonicalize(dir.join("app")).unwrap().join(manifest::FILE));
+    assert_eq!(manifest::find(&dir), None);
+}
+
+#[test]
+fn points_at_what_is_wrong() {
+    assert_eq!(error("[package]\nversion = \"1\"\n"), ("the manifest needs a `name` under `[package]`".into(), None));
+    assert_eq!(error("[package]\nname = \"a\"\n[dev]\n"), ("no section called `dev`, only `package` and `dependencies`".into(), Some(3)));
+    assert_eq!(error("[package]\nname = a\n"), ("invalid TOML value, did you mean to use a quoted string?".into(), Some(2)));
+    assert_eq!(error("[package]\nname = \"a\"\nauthor = \"me\"\n"), ("`[package]` doesn't take `author`".into(), Some(3)));
+    assert_eq!(error("[package]\nname = [\"a\"]\n"), ("`name` should be a string".into(), Some(2)));
+    assert_eq!(error("[dependencies]\nhelpers = { path = \"..\", vendored = true }\n").1, Some(2));
+    assert_eq!(error("[package]\nname = \"a\"\nversion = \"1\"\n[dependencies]\nsome.thing = \"..\"\n").1, Some(5));
+
+    let dir = project("[package]\nname = \"a\"\nversion = \"1\"\n[dependencies]\ngone = { path = \"../gone\" }\n", &[]);
+    let missing = manifest::load(&dir.join("app").join(manifest::FILE)).expect_err("it was supposed to fail");
+
+    assert_eq!(missing.message, format!("there's no folder for `gone` at `{}`", dir.join("app/../gone").display()));
+}
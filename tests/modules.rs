@@ -0,0 +1,235 @@
+extern crate hugorm;
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use hugorm::{Diagnostic, Engine};
+
+use common::{Project, Shared};
+
+fn run(dir: &Path, main: &str) -> Result<String, Vec<Diagnostic>> {
+    run_in(dir, "main.hug", main)
+}
+
+// `file` is somewhere in the project at `dir`, which is the root
+fn run_in(dir: &Path, file: &str, main: &str) -> Result<String, Vec<Diagnostic>> {
+    run_searching(dir, file, main, &[])
+}
+
+// with `search` in place of `HUGORM_PATH`
+fn run_searching(dir: &Path, file: &str, main: &str, search: &[PathBuf]) -> Result<String, Vec<Diagnostic>> {
+    let path = dir.join(file);
+
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, main).unwrap();
+
+    let output = Shared::default();
+
+    let mut engine = Engine::new();
+    engine.set_root(&dir.display().to_string());
+    engine.set_search(search.to_vec());
+    engine.set_output(Some(Box::new(output.clone())));
+
+    let result = engine.eval_file(&path.display().to_string());
+
+    result.map(|_| String::from_utf8(output.0.borrow().clone()).unwrap())
+}
+
+fn error(dir: &Path, main: &str) -> String {
+    let errors = run(dir, main).expect_err("it was supposed to fail");
+
+    errors.into_iter().find(|d| d.is_error()).unwrap().message
+}
+
+const SHAPES: &str = "let _made = 0\n\nfun square(x):\n    _made = _made + 1\n    return x * x\n\nfun made():\n    return _made\n\nlet name = \"shapes\"\n";
+
+#[test]
+fn grabs_into_a_namespace() {
+    let dir = Project::new(&[("shapes.hug", SHAPES)]);
+
+    assert_eq!(run(&dir, "grab shapes\nprint(shapes.square(3))\nprint(shapes.made())\nprint(shapes.name)\n").unwrap(), "9\n1\nshapes\n");
+    assert_eq!(run(&dir, "grab shapes as s\nprint(s.square(2))\n").unwrap(), "4\n");
+    assert_eq!(run(&dir, "grab square, name from shapes\nprint(square(5))\nprint(name)\n").unwrap(), "25\nshapes\n");
+}
+
+#[test]
+fn keeps_module_globals_to_themselves() {
+    let dir = Project::new(&[("shapes.hug", SHAPES), ("other.hug", "let name = \"other\"\n")]);
+
+    assert_eq!(run(&dir, "let name = \"main\"\ngrab shapes\ngrab other\nprint(name)\nprint(shapes.name)\nprint(other.name)\n").unwrap(), "main\nshapes\nother\n");
+    assert_eq!(error(&dir, "grab shapes\nprint(square(1))\n"), "no such variable `square`");
+}
+
+#[test]
+fn hides_names_starting_with_an_underscore() {
+    let dir = Project::new(&[("shapes.hug", SHAPES)]);
+
+    assert_eq!(error(&dir, "grab shapes\nprint(shapes._made)\n"), "`_made` is private to module `shapes`");
+    assert_eq!(error(&dir, "grab _made from shapes\n"), "`_made` is private to module `shapes`");
+    assert_eq!(error(&dir, "grab shapes\nprint(shapes.circle)\n"), "module `shapes` has no `circle`");
+}
+
+#[test]
+fn grabs_interfaces() {
+    let dir = Project::new(&[("brr.hug", "interface Brr:\n    fun add(a):\n        return a + 100\n")]);
+
+    assert_eq!(run(&dir, "grab brr\nlet foo = {x: 1} with brr.Brr\nprint(foo.add(foo.x))\n").unwrap(), "101\n");
+    assert_eq!(run(&dir, "grab Brr from brr\nlet foo = {} with Brr\nprint(foo.add(1))\n").unwrap(), "101\n");
+    assert_eq!(error(&dir, "grab brr\nlet foo = {} with brr.Grr\n"), "module `brr` has no `Grr`");
+    assert_eq!(error(&dir, "let foo = {} with Grr\n"), "no interface called `Grr`");
+}
+
+#[test]
+fn checks_what_it_grabbed() {
+    let dir = Project::new(&[("shapes.hug", SHAPES)]);
+
+    assert_eq!(error(&dir, "grab shapes\nshapes.square(1, 2)\n"), "wrong amount of arguments, expected 1 but got 2");
+    assert_eq!(error(&dir, "grab shapes\nshapes.name = \"circles\"\n"), "can't assign to a grabbed module from outside of it");
+    assert_eq!(error(&dir, "fun f():\n    grab shapes\n"), "`grab` only works at the top level of a file");
+    assert_eq!(error(&dir, "grab a, b\n"), "expected `from` after the names to grab");
+}
+
+#[test]
+fn runs_a_module_once() {
+    let dir = Project::new(&[("once.hug", "print(\"loaded\")\nlet v = 1\n"), ("two.hug", "grab once\nlet w = once.v + 1\n")]);
+
+    assert_eq!(run(&dir, "grab once\ngrab two\ngrab once as o\nprint(o.v + once.v + two.w)\n").unwrap(), "loaded\n4\n");
+}
+
+#[test]
+fn runs_a_module_once_across_evals() {
+    let dir = Project::new(&[("once.hug", "print(\"loaded\")\nlet v = 1\n")]);
+    let main = dir.join("main.hug").display().to_string();
+
+    let output = Shared::default();
+
+    let mut engine = Engine::new();
+    engine.set_root(&dir.display().to_string());
+    engine.set_output(Some(Box::new(output.clone())));
+
+    engine.eval_str(&main, "grab once\n").unwrap();
+    engine.eval_str(&main, "grab once as again\nprint(again.v)\n").unwrap();
+
+    assert_eq!(String::from_utf8(output.0.borrow().clone()).unwrap(), "loaded\n1\n");
+}
+
+#[test]
+fn shows_the_whole_circle() {
+    let dir = Project::new(&[("a.hug", "grab b\n"), ("b.hug", "\ngrab c\n"), ("c.hug", "grab a\n")]);
+
+    let errors = run(&dir, "let x = 1\ngrab a\n").expect_err("it was supposed to fail");
+    let error = errors.iter().find(|d| d.is_error()).unwrap();
+
+    assert_eq!(error.message, "modules grabbing each other in a circle: a -> b -> c -> a");
+    assert!(error.file.as_ref().unwrap().ends_with("c.hug"));
+
+    let notes = error.notes.iter()
+        .map(|note| (note.message.as_str(), Path::new(note.file.as_ref().unwrap()).file_name().unwrap().to_str().unwrap(), (note.span.as_ref().unwrap().0).0))
+        .collect::<Vec<_>>();
+
+    assert_eq!(notes, vec!(
+        ("`a` grabbed here", "main.hug", 2),
+        ("`b` grabbed here", "a.hug", 1),
+        ("`c` grabbed here", "b.hug", 2),
+    ));
+}
+
+#[test]
+fn finds_nested_modules() {
+    let dir = Project::new(&[("geo/consts.hug", "let pi = 3\n"), ("geo/shapes/mod.hug", "grab geo.consts\nlet tau = consts.pi * 2\n")]);
+
+    assert_eq!(run(&dir, "grab geo.shapes\ngrab pi from geo.consts\nprint(shapes.tau + pi)\n").unwrap(), "9\n");
+    assert_eq!(run(&dir, "grab geo.consts as c\nprint(c.pi)\n").unwrap(), "3\n");
+}
+
+#[test]
+fn looks_in_the_root_and_hugorm_path() {
+    let dir = Project::new(&[("util.hug", "let name = \"util\"\n"), ("elsewhere/far-away.hug", "let name = \"far\"\n")]);
+
+    assert_eq!(run_in(&dir, "deep/down/main.hug", "grab util\nprint(util.name)\n").unwrap(), "util\n");
+
+    assert_eq!(
+        run_searching(&dir, "main.hug", "grab far-away\nprint(far-away.name)\n", &[dir.join("elsewhere")]).unwrap(),
+        "far\n"
+    );
+
+    assert_eq!(error(&dir, "grab nowhere.to.be\n"), "no such module `nowhere.to.be`, needed either `nowhere/to/be.hug` or `nowhere/to/be/mod.hug`");
+}
+
+#[test]
+fn reports_modules_it_cant_load() {
+    let dir = Project::new(&[("lexy.hug", "let x = 1 ` 2\n"), ("parsy.hug", "let = 1\n")]);
+
+    fs::write(dir.join("binary.hug"), b"let x = 1\xff\xfe\n").unwrap();
+
+    assert_eq!(
+        error(&dir, "grab binary\n"),
+        format!("can't read module `binary` at `{}`: it isn't valid UTF-8", dir.join("binary.hug").display())
+    );
+
+    for module in ["lexy", "parsy"] {
+        let errors = run(&dir, &format!("let a = 1\ngrab {}\n", module)).expect_err("it was supposed to fail");
+
+        // what's wrong in the module, then the `grab` that ran into it
+        let last = errors.last().unwrap();
+
+        assert_eq!(last.message, format!("can't grab `{}`, it has errors in it", module));
+        assert_eq!((last.span.as_ref().unwrap().0).0, 2);
+        assert!(errors[0].file.as_ref().unwrap().ends_with(&format!("{}.hug", module)));
+    }
+}
+
+#[test]
+fn looks_in_the_current_folder_once() {
+    let mut engine = Engine::new();
+    engine.set_root(".");
+
+    // a file without a folder, like `-e` and the REPL
+    let errors = engine.eval_str("main.hug", "grab nowhere_to_be_found\n").expect_err("it was supposed to fail");
+    let help = errors[0].help.clone().unwrap();
+
+    assert_eq!(help.matches("`.`").count(), 1, "{}", help);
+}
+
+#[test]
+fn grabs_declared_dependencies() {
+    let dir = Project::new(&[
+        ("hugorm.toml", "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\nhelpers = \"../hugorm-modules-shared/helpers\"\nlib = { path = \"../hugorm-modules-shared/lib\" }\ncolors = { vendored = true }\n"),
+        ("vendor/colors/mod.hug", "let red = \"red\"\n"),
+        ("../hugorm-modules-shared/helpers/mod.hug", "let greeting = \"hi\"\n"),
+        ("../hugorm-modules-shared/helpers/text/loud.hug", "fun shout(s):\n    return upper(s)\n"),
+        ("../hugorm-modules-shared/lib/hugorm.toml", "[package]\nname = \"lib\"\nversion = \"1.0.0\"\nentry = \"lib.hug\"\n"),
+        ("../hugorm-modules-shared/lib/lib.hug", "let two = 2\n"),
+    ]);
+
+    assert_eq!(
+        run_in(&dir, "src/main.hug", "grab helpers\ngrab helpers.text.loud\ngrab colors\ngrab lib\nprint(loud.shout(helpers.greeting))\nprint(colors.red)\nprint(lib.two)\n").unwrap(),
+        "HI\nred\n2\n"
+    );
+
+    assert_eq!(error(&dir, "grab helpers.nope\n"), "dependency `helpers` has no module `helpers.nope`");
+}
+
+#[test]
+fn reads_the_manifest_once() {
+    let dir = Project::new(&[
+        ("hugorm.toml", "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\nhelpers = \"helpers\"\n"),
+        ("helpers/mod.hug", "let greeting = \"hi\"\n"),
+    ]);
+
+    let main = dir.join("main.hug").display().to_string();
+
+    let mut engine = Engine::new();
+    engine.set_root(&dir.display().to_string());
+
+    engine.eval_str(&main, "grab helpers\n").unwrap();
+
+    // the engine already knows what the manifest says, so breaking it changes nothing
+    fs::write(dir.join("hugorm.toml"), "[package\n").unwrap();
+
+    engine.eval_str(&main, "grab helpers as again\n").unwrap();
+
+    assert!(Engine::new().eval_str(&main, "grab helpers\n").is_err());
+}
@@ -0,0 +1,67 @@
+extern crate hugorm;
+
+use hugorm::engine::parse_str;
+use hugorm::error;
+use hugorm::lexer::Lexer;
+use hugorm::parser::{Parser, StatementNode};
+use hugorm::source::Source;
+
+// three unrelated mistakes, with fine lines between them
+const BROKEN: &str = "let = 1\nlet ok = 2\nlet x = 1 ` 2\nprint(ok)\nif :\n    print(1)\nlet y = ok + 1\n";
+
+fn source(code: &str) -> Source {
+    Source::from("<test>", code.lines().map(|x| x.into()).collect::<Vec<String>>())
+}
+
+fn line(diagnostic: &hugorm::Diagnostic) -> usize {
+    (diagnostic.span.as_ref().unwrap().0).0
+}
+
+#[test]
+fn reports_every_error_once_in_order() {
+    let errors = parse_str(&source(BROKEN), BROKEN).expect_err("it was supposed to fail");
+
+    let found = errors.iter().map(|d| (line(d), d.message.as_str())).collect::<Vec<_>>();
+
+    assert_eq!(found, vec!(
+        (1, "expected `Identifier` but found `Symbol`"),
+        (3, "bumped into weird character"),
+        (5, "unexpected symbol `:`"),
+    ));
+}
+
+#[test]
+fn keeps_what_parses() {
+    let code = "let = 1\nlet ok = 2\nif :\n    print(1)\nlet y = ok + 1\n";
+    let source = source(code);
+
+    let tokens = Lexer::default(code.chars().collect(), &source).filter_map(Result::ok).collect();
+    let (ast, errors) = Parser::new(tokens, &source).parse_partial();
+
+    error::drain();
+
+    let declared = ast.iter()
+        .filter_map(|statement| match statement.node {
+            StatementNode::Declaration(ref name, _) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(declared, vec!("ok", "y"));
+    assert_eq!(errors.iter().map(line).collect::<Vec<_>>(), vec!(1, 3));
+}
+
+#[test]
+fn reports_literals_that_dont_fit() {
+    let code = "print(-9223372036854775808)\nlet a = 0xFFFFFFFFFFFFFFFFFF\nlet b = 0xZZ\n";
+
+    let errors = parse_str(&source(code), code).expect_err("it was supposed to fail");
+
+    let found = errors.iter().map(|d| (line(d), d.message.as_str())).collect::<Vec<_>>();
+
+    assert_eq!(found, vec!(
+        (1, "int literal `-9223372036854775808` is too big"),
+        (2, "int literal `0xFFFFFFFFFFFFFFFFFF` is too big"),
+        (3, "invalid base 16 literal `0xZZ`"),
+    ));
+}
@@ -0,0 +1,54 @@
+extern crate hugorm;
+
+use hugorm::{Diagnostic, Engine, Render};
+
+// an error with a note, and a warning with help
+fn diagnostics() -> (Diagnostic, Diagnostic) {
+    let error = Engine::new().compile_str("main.hug", "const a = 1\na = 2\n").expect_err("it was supposed to fail");
+    let (_, warnings) = Engine::new().compile_str("main.hug", "fun f(x):\n    return 1\n").unwrap();
+
+    (error[0].clone(), warnings[0].clone())
+}
+
+#[test]
+fn json_is_one_object_per_line() {
+    let (error, warning) = diagnostics();
+
+    assert_eq!(
+        Render::Json.render(&error),
+        concat!(
+            r#"{"file":"main.hug","help":null,"message":"can't assign twice to constant `a`","#,
+            r#""notes":[{"file":"main.hug","help":null,"message":"`a` was declared constant here","notes":[],"severity":"note","#,
+            r#""span":{"column_end":11,"column_start":1,"line":1,"text":"const a = 1"}}],"#,
+            r#""severity":"error","span":{"column_end":1,"column_start":1,"line":2,"text":"a = 2"}}"#,
+            "\n",
+        )
+    );
+
+    assert_eq!(
+        Render::Json.render(&warning),
+        concat!(
+            r#"{"file":"main.hug","help":"`# lint: allow unused-parameter` if that's on purpose","#,
+            r#""message":"unused parameter `x`","notes":[],"severity":"warning","#,
+            r#""span":{"column_end":7,"column_start":7,"line":1,"text":"fun f(x):"}}"#,
+            "\n",
+        )
+    );
+}
+
+#[test]
+fn plain_has_no_escape_codes() {
+    let (error, warning) = diagnostics();
+
+    assert_eq!(
+        Render::Plain.render(&error),
+        "\nerror: can't assign twice to constant `a`\n     --> main.hug\n      │\n    2 │ a = 2\n      │ ^\
+         \nnote: `a` was declared constant here\n     --> main.hug\n      │\n    1 │ const a = 1\n      │ ^^^^^^^^^^^\n"
+    );
+
+    assert_eq!(
+        Render::Plain.render(&warning),
+        "\nhmmm: unused parameter `x`\n     --> main.hug\n      │\n    1 │ fun f(x):\n      │       ^\
+         \nhelp: `# lint: allow unused-parameter` if that's on purpose\n"
+    );
+}
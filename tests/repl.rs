@@ -0,0 +1,270 @@
+extern crate colored;
+extern crate hugorm;
+
+mod common;
+
+use colored::Colorize;
+
+use hugorm::repl::{self, Session};
+
+use common::{Project, Shared};
+
+// feeds entries one by one, collecting what each printed and the messages of what went wrong
+fn session(entries: &[&str]) -> Vec<(String, Vec<String>)> {
+    let output = Shared::default();
+
+    let mut session = Session::new(String::new());
+    session.engine.set_output(Some(Box::new(output.clone())));
+
+    let mut results = Vec::new();
+
+    for entry in entries.iter() {
+        let diagnostics = session.handle(entry);
+        let printed = String::from_utf8(output.0.borrow_mut().drain(..).collect()).unwrap();
+
+        results.push((printed, diagnostics.iter().map(|d| d.message.clone()).collect()))
+    }
+
+    results
+}
+
+#[test]
+fn keeps_globals_between_entries() {
+    let results = session(&[
+        "let a = 1",
+        "fun double(x):\n    return x * 2\n",
+        "a = double(a + 1)",
+        "a",
+    ]);
+
+    assert_eq!(results[3].0, "4\n");
+}
+
+#[test]
+fn runs_every_entry_once() {
+    let results = session(&[
+        "print(\"once\")",
+        "let b = 2",
+        "print(b)",
+    ]);
+
+    assert_eq!(results[0].0, "once\n");
+    assert_eq!(results[1].0, "");
+    assert_eq!(results[2].0, "2\n");
+}
+
+#[test]
+fn math_writes_where_print_does() {
+    let results = session(&[
+        "@math",
+        "let t = student(5, 1, 4)",
+    ]);
+
+    assert_eq!(results[1].0, "mean:    5\nvar:     2\nstd-var: 1.4142135623730951\n\n");
+}
+
+#[test]
+fn survives_errors() {
+    let results = session(&[
+        "let a = 10",
+        "let = 1",
+        "\"unterminated",
+        "print(nope)",
+        "let l = [1]\nl[\"x\"] + 1",
+        "a + 1",
+    ]);
+
+    assert!(!results[1].1.is_empty());
+    assert!(!results[2].1.is_empty());
+    assert_eq!(results[3].1, vec!("no such variable `nope`"));
+    assert!(results[4].1.iter().any(|message| message.starts_with("runtime error")), "{:?}", results[4]);
+    assert_eq!(results[5], ("11\n".to_string(), Vec::new()));
+}
+
+#[test]
+fn answers_commands() {
+    let results = session(&[
+        "let a = 1",
+        ":type a + 2",
+        ":type \"hug\" ++ a",
+        ":ast not a",
+        ":ir let b = a",
+        "b",
+        ":env",
+        ":nope",
+    ]);
+
+    assert_eq!(results[1].0, "int\n");
+    assert_eq!(results[2].0, "string\n");
+    assert!(results[3].0.starts_with("Expression {\n    node: Not("), "{}", results[3].0);
+    assert!(results[4].0.contains("Bind("), "{}", results[4].0);
+    assert_eq!(results[5].1, vec!("no such variable `b`"));
+    assert!(results[6].0.starts_with("frame 0\n  let a: int\n"), "{}", results[6].0);
+    assert_eq!(results[7].1, vec!("no command called `:nope`"));
+}
+
+#[test]
+fn loads_files_and_resets() {
+    let dir = Project::new(&[]);
+    let path = dir.write("load.hug", "let loaded = 40\nfun plus-two(x):\n    return x + 2\n");
+
+    let load = format!(":load {}", path.display());
+
+    let results = session(&[
+        &load,
+        "plus-two(loaded)",
+        ":reset",
+        "loaded",
+        "@math",
+        "sum([1.5, 2.5])",
+    ]);
+
+    assert_eq!(results[0], (String::new(), Vec::new()));
+    assert_eq!(results[1].0, "42\n");
+    assert_eq!(results[3].1, vec!("no such variable `loaded`"));
+    assert_eq!(results[5].0, "4\n");
+}
+
+#[test]
+fn keeps_its_settings_across_a_reset() {
+    let mut session = Session::new(String::new());
+
+    session.handle("let x = 1");
+    session.handle("@debug");
+    session.handle(":reset");
+
+    assert!(session.debug);
+    assert!(session.engine.get_global("x").is_none());
+}
+
+#[test]
+fn a_failed_load_grabs_nothing() {
+    let dir = Project::new(&[
+        ("m.hug", "let x = 5\n"),
+        ("main.hug", "print(1 / 0)\ngrab m\n"),
+        ("grab.hug", "grab m\nprint(m.x)\n"),
+    ]);
+
+    let load = format!(":load {}", dir.join("main.hug").display());
+    let grab = format!(":load {}", dir.join("grab.hug").display());
+
+    let results = session(&[&load, &grab]);
+
+    assert!(results[0].1.iter().any(|message| message.starts_with("runtime error")), "{:?}", results[0]);
+    assert_eq!(results[1], ("5\n".to_string(), Vec::new()));
+}
+
+#[test]
+fn knows_when_an_entry_is_done() {
+    assert!(!repl::incomplete("let a = 1"));
+    assert!(!repl::incomplete("if a: print(a)"));
+    assert!(!repl::incomplete("{x: 1}"));
+    assert!(repl::incomplete("print(1,"));
+    assert!(repl::incomplete("let d = {\n    x: 1"));
+    assert!(repl::incomplete("fun f():"));
+    assert!(repl::incomplete("fun f():\n    return 1"));
+    assert!(!repl::incomplete("fun f():\n    return 1\n"));
+}
+
+#[test]
+fn completes_globals_fields_and_methods() {
+    let mut session = Session::new(String::new());
+
+    session.handle("interface Brr:\n    fun add(a):\n        return a\n");
+    session.handle("let player = {pos: {x: 1, y: 2}, name: \"worm\"} with Brr");
+
+    assert_eq!(session.complete("pla", 3), (0, vec!("player".to_string())));
+    assert_eq!(session.complete("print(pr", 8).1, vec!("print", "prompt"));
+    assert_eq!(session.complete("whi", 3).1, vec!("while"));
+    assert_eq!(session.complete("player.", 7), (7, vec!("add".to_string(), "name".to_string(), "pos".to_string())));
+    assert_eq!(session.complete("f(player.pos.", 13).1, vec!("x", "y"));
+    assert!(session.complete("nothing.", 8).1.is_empty());
+}
+
+#[test]
+fn highlights_with_the_lexer() {
+    colored::control::set_override(true);
+
+    let highlighted = repl::highlight("let s = \"hi\" # note");
+
+    colored::control::unset_override();
+
+    assert!(highlighted.contains(&"let".magenta().to_string()));
+    assert!(highlighted.contains(&"\"hi\"".green().to_string()));
+    assert!(highlighted.contains(&"# note".bright_black().to_string()));
+    assert!(highlighted.contains(" s = "));
+}
+
+#[test]
+fn runs_scripts_with_commands_in_them() {
+    let output = Shared::default();
+
+    let mut session = Session::new(String::new());
+    session.engine.set_output(Some(Box::new(output.clone())));
+
+    let diagnostics = session.script("init.hug", "@math\nlet total = sum([1.5, 2.5])\n\n:type total\nprint(nope)\n");
+
+    assert_eq!(String::from_utf8(output.0.borrow().clone()).unwrap(), "float\n");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!((diagnostics[0].span.as_ref().unwrap().0).0, 5);
+    assert_eq!(diagnostics[0].file.as_deref(), Some("init.hug"));
+}
+
+#[test]
+fn finds_the_startup_script() {
+    let dir = Project::new(&[(".hugormrc.hug", "")]);
+    let home = dir.to_path_buf();
+
+    assert_eq!(repl::pick_init_script(Some("/somewhere/init.hug".into()), Some(home.clone())), Some("/somewhere/init.hug".into()));
+    assert_eq!(repl::pick_init_script(Some("".into()), Some(home.clone())), None);
+    assert_eq!(repl::pick_init_script(None, Some(home.clone())), Some(home.join(".hugormrc.hug")));
+    assert_eq!(repl::pick_init_script(None, Some(home.join("nowhere"))), None);
+}
+
+#[test]
+fn starts_with_its_init_script() {
+    let dir = Project::new(&[]);
+    let path = dir.write("init.hug", "let from-init = 7\n");
+
+    let mut session = Session::new(String::new());
+    session.init = Some(path.clone());
+
+    assert!(session.start().is_empty());
+    assert_eq!(session.engine.get_global("from-init").unwrap().as_float(), 7.0);
+
+    // and again after a reset, once
+    session.handle("let from-init = 8");
+    dir.write("init.hug", "let before = 1\n:reset\nlet from-init = 7\n");
+
+    assert!(session.handle(":reset").is_empty());
+    assert_eq!(session.engine.get_global("from-init").unwrap().as_float(), 7.0);
+    assert!(session.engine.get_global("before").is_none());
+    assert_eq!(session.init, Some(path));
+
+    session.init = None;
+    assert!(session.start().is_empty());
+}
+
+#[test]
+fn ends_on_exit() {
+    let mut session = Session::new(String::new());
+
+    let diagnostics = session.script("init.hug", "let a = 1\nexit(4)\n:reset\n");
+
+    assert!(diagnostics.is_empty());
+    assert_eq!(session.exited, Some(4));
+    assert!(session.engine.get_global("a").is_some());
+}
+
+#[test]
+fn reads_whole_entries_without_an_editor() {
+    let mut lines = ["", "fun f(x):", "    return x", "", "let l = [", "  1]", "print(1)", "while l:"].iter().map(|line| line.to_string());
+
+    assert_eq!(repl::read_entry(&mut lines).as_deref(), Some("fun f(x):\n    return x\n"));
+    assert_eq!(repl::read_entry(&mut lines).as_deref(), Some("let l = [\n  1]"));
+    assert_eq!(repl::read_entry(&mut lines).as_deref(), Some("print(1)"));
+
+    // whatever's left at the end is an entry too
+    assert_eq!(repl::read_entry(&mut lines).as_deref(), Some("while l:"));
+    assert_eq!(repl::read_entry(&mut lines), None);
+}
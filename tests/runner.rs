@@ -0,0 +1,109 @@
+extern crate hugorm;
+
+mod common;
+
+use hugorm::testing;
+
+use common::Project;
+
+fn write(dir: &Project, name: &str, code: &str) -> String {
+    dir.write(name, code).display().to_string()
+}
+
+#[test]
+fn hug_tests_pass() {
+    let files = testing::discover(&["tests/hug".to_string()]);
+
+    assert!(!files.is_empty());
+
+    for file in files.iter() {
+        for outcome in testing::run_file(file) {
+            assert!(outcome.passed(), "{} :: {} failed:\n{:#?}", outcome.file, outcome.name, outcome.failures)
+        }
+    }
+}
+
+#[test]
+fn each_test_fun_runs_on_its_own() {
+    let dir = Project::new(&[]);
+    let path = write(&dir, "own_test.hug", r#"
+let count = 0
+
+test fun first():
+  count += 1
+  assert-eq(count, 1)
+
+test fun second():
+  count += 1
+  assert-eq(count, 1)
+"#);
+
+    let outcomes = testing::run_file(&path);
+
+    assert_eq!(outcomes.iter().map(|o| o.name.as_str()).collect::<Vec<_>>(), vec!["first", "second"]);
+    assert!(outcomes.iter().all(|o| o.passed()));
+}
+
+#[test]
+fn failures_point_at_the_assertion() {
+    let dir = Project::new(&[]);
+    let path = write(&dir, "failing_test.hug", r#"
+test fun fine():
+  assert(true)
+
+test fun broken():
+  let x = 1
+  assert-eq(x + 1, 3)
+"#);
+
+    let outcomes = testing::run_file(&path);
+
+    assert!(outcomes[0].passed());
+    assert!(!outcomes[1].passed());
+
+    let failure = &outcomes[1].failures[0];
+    let span = failure.span.as_ref().unwrap();
+
+    assert_eq!(failure.message, "expected `3`, found `2`");
+    assert_eq!((span.0).0, 7);
+    assert_eq!((span.0).1, "  assert-eq(x + 1, 3)");
+}
+
+#[test]
+fn files_without_test_funs_are_one_test() {
+    let dir = Project::new(&[]);
+    let path = write(&dir, "whole_test.hug", "assert-error(\"let x = nope\")\nassert(false)\n");
+
+    let outcomes = testing::run_file(&path);
+
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!((outcomes[0].failures[0].span.as_ref().unwrap().0).0, 2);
+}
+
+#[test]
+fn failures_in_grabbed_helpers_point_into_them() {
+    let dir = Project::new(&[]);
+    let helpers = write(&dir, "runner_helpers.hug", "fun check(x):\n  assert-eq(x, 2)\n");
+    let path = write(&dir, "grabbing_test.hug", "grab runner_helpers\n\ntest fun uses_helper():\n  runner_helpers.check(3)\n");
+
+    let outcomes = testing::run_file(&path);
+
+    let failure = &outcomes[0].failures[0];
+    let span = failure.span.as_ref().unwrap();
+
+    assert_eq!(failure.file.as_deref(), Some(helpers.as_str()));
+    assert_eq!((span.0).0, 2);
+    assert_eq!((span.0).1, "  assert-eq(x, 2)");
+}
+
+#[test]
+fn discovers_files_declaring_test_funs() {
+    let dir = Project::new(&[
+        ("checks.hug", "test fun works():\n  assert(true)\n"),
+        ("notes.hug", "# a test fun goes here later\nprint(\"test fun\")\n"),
+    ]);
+
+    let files = testing::discover(&[dir.display().to_string()]);
+
+    assert_eq!(files, vec!(dir.join("checks.hug").display().to_string()));
+}
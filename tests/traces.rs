@@ -0,0 +1,97 @@
+extern crate hugorm;
+extern crate zub;
+
+use zub::vm::{Heap, Object, Value};
+
+use hugorm::runtime::{raise, RuntimeError};
+use hugorm::{Diagnostic, Engine};
+
+fn picky(_heap: &mut Heap<Object>, _args: &[Value]) -> Result<Value, RuntimeError> {
+    raise("didn't like that")
+}
+
+fn fail(code: &str) -> Diagnostic {
+    let mut engine = Engine::new();
+
+    engine.register_native("picky", 1, picky);
+
+    let diagnostics = engine.eval_str("trace.hug", code).expect_err("it was supposed to fail");
+
+    diagnostics.into_iter().find(|d| d.is_error()).unwrap()
+}
+
+// line and column of where it points
+fn at(diagnostic: &Diagnostic) -> (usize, usize) {
+    let span = diagnostic.span.as_ref().expect("no position");
+
+    ((span.0).0, (span.1).0)
+}
+
+#[test]
+fn points_at_the_failing_native() {
+    let error = fail("let a = 1\nprint(picky(a))\n");
+
+    assert_eq!(error.message, "runtime error: didn't like that");
+    assert_eq!(error.file.as_deref(), Some("trace.hug"));
+    assert_eq!(at(&error), (2, 7));
+    assert!(error.notes.is_empty());
+}
+
+#[test]
+fn walks_back_through_the_calls() {
+    let error = fail("fun inner(x):\n    return picky(x)\n\nfun outer(x):\n    let y = inner(x)\n    return y\n\nouter(1)\n");
+
+    assert_eq!(at(&error), (2, 12));
+
+    let notes = error.notes.iter().map(|note| (note.message.as_str(), at(note))).collect::<Vec<_>>();
+
+    assert_eq!(notes, vec!(
+        ("in `inner`, called here", (5, 13)),
+        ("in `outer`, called here", (8, 1)),
+    ));
+}
+
+#[test]
+fn forgets_calls_that_returned() {
+    let error = fail("fun id(x):\n    return x\n\nlet a = id(1)\nlet b = id(a) + 1\npicky(b)\n");
+
+    assert_eq!(at(&error), (6, 1));
+    assert!(error.notes.is_empty(), "{:?}", error.notes);
+}
+
+#[test]
+fn names_methods_and_anonymous_functions() {
+    let error = fail("interface Fussy:\n    fun check(x):\n        return picky(x)\n\nlet f = {} with Fussy\nlet g = fun(x): return f.check(x)\ng(1)\n");
+
+    let names = error.notes.iter().map(|note| note.message.as_str()).collect::<Vec<_>>();
+
+    assert_eq!(names, vec!("in `Fussy.check`, called here", "in `<anonymous>`, called here"));
+}
+
+#[test]
+fn catches_the_vm_falling_over() {
+    let error = fail("let l = [1, 2]\nlet i = \"x\"\nprint(l[i])\n");
+
+    assert!(error.message.starts_with("runtime error: "), "{}", error.message);
+    assert_eq!(at(&error), (3, 7));
+}
+
+#[test]
+fn says_what_the_vm_fell_over_in_words() {
+    let message = |code: &str| fail(code).message;
+
+    assert_eq!(message("let l = [1, 2]\nlet i = \"x\"\nprint(l[i])\n"), "runtime error: can't index a list with anything but a number");
+    assert_eq!(message("let l = [1, 2]\nprint(l[5])\n"), "runtime error: index 5 is out of bounds, the list has 2 elements");
+    assert_eq!(message("let p = {x: 1}\nprint(p.y)\n"), "runtime error: no such field `y`");
+    assert_eq!(message("fun f(g): return g(1, 2)\nf(fun(x): return x)\n"), "runtime error: called with 2 arguments, it takes 1");
+    assert_eq!(message("fun f(x): return x(1)\nf(3)\n"), "runtime error: can't call `3`");
+}
+
+#[test]
+fn names_each_function_once() {
+    let error = fail("fun f(x):\n    if x > 0:\n        return f(x - 1)\n    return picky(x)\n\nf(2)\n");
+
+    let names = error.notes.iter().map(|note| note.message.as_str()).collect::<Vec<_>>();
+
+    assert_eq!(names, vec!("in `f`, called here"; 3));
+}